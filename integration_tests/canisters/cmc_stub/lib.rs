@@ -0,0 +1,137 @@
+//! Minimal cycles minting canister for the integration tests. `notify_top_up` reads the
+//! transfer from the ledger stub and deposits cycles from its own balance at a fixed rate.
+use std::{cell::RefCell, collections::HashMap};
+
+use candid::{CandidType, Deserialize, Nat, Principal};
+use ic_cdk::{
+    api::management_canister::main::{deposit_cycles, CanisterIdRecord},
+    id, init, query, update,
+};
+use ic_ledger_types::{query_blocks, AccountIdentifier, GetBlocksArgs, Operation, Subaccount};
+
+const MEMO_TOP_UP_CANISTER: u64 = 1347768404;
+// 5 XDR per ICP, one XDR is one trillion cycles
+const XDR_PERMYRIAD_PER_ICP: u64 = 50_000;
+const CYCLES_PER_ICP: u64 = XDR_PERMYRIAD_PER_ICP * 1_000_000_000_000 / 10_000;
+
+thread_local! {
+    static LEDGER: RefCell<Option<Principal>> = const { RefCell::new(None) };
+    static PROCESSED: RefCell<HashMap<u64, Nat>> = RefCell::new(HashMap::new());
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct NotifyTopUpArg {
+    pub block_index: u64,
+    pub canister_id: Principal,
+}
+
+#[derive(CandidType, Deserialize, Debug)]
+pub enum NotifyError {
+    Refunded {
+        block_index: Option<u64>,
+        reason: String,
+    },
+    InvalidTransaction(String),
+    Other {
+        error_message: String,
+        error_code: u64,
+    },
+    Processing,
+    TransactionTooOld(u64),
+}
+
+#[derive(CandidType, Deserialize)]
+pub enum NotifyTopUpResult {
+    Ok(Nat),
+    Err(NotifyError),
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct IcpXdrConversionRate {
+    pub xdr_permyriad_per_icp: u64,
+    pub timestamp_seconds: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct IcpXdrConversionRateResponse {
+    pub certificate: Vec<u8>,
+    pub data: IcpXdrConversionRate,
+    pub hash_tree: Vec<u8>,
+}
+
+#[init]
+fn init(ledger: Principal) {
+    LEDGER.with(|l| *l.borrow_mut() = Some(ledger));
+}
+
+#[update]
+async fn notify_top_up(arg: NotifyTopUpArg) -> NotifyTopUpResult {
+    if let Some(cycles) = PROCESSED.with(|p| p.borrow().get(&arg.block_index).cloned()) {
+        return NotifyTopUpResult::Ok(cycles);
+    }
+
+    let ledger = LEDGER.with(|l| l.borrow().expect("ledger not set"));
+    let blocks = query_blocks(
+        ledger,
+        GetBlocksArgs {
+            start: arg.block_index,
+            length: 1,
+        },
+    )
+    .await
+    .expect("failed to query blocks");
+
+    let block = match blocks.blocks.into_iter().next() {
+        Some(block) => block,
+        None => {
+            return NotifyTopUpResult::Err(NotifyError::InvalidTransaction(
+                "block not found".to_string(),
+            ))
+        }
+    };
+
+    let amount = match block.transaction.operation {
+        Some(Operation::Transfer { to, amount, .. })
+            if block.transaction.memo.0 == MEMO_TOP_UP_CANISTER
+                && to == AccountIdentifier::new(&id(), &Subaccount::from(arg.canister_id)) =>
+        {
+            amount
+        }
+        _ => {
+            return NotifyTopUpResult::Err(NotifyError::InvalidTransaction(
+                "not a top up transfer".to_string(),
+            ))
+        }
+    };
+
+    let cycles = amount.e8s() as u128 * CYCLES_PER_ICP as u128 / 100_000_000;
+
+    if let Err((_, err)) = deposit_cycles(
+        CanisterIdRecord {
+            canister_id: arg.canister_id,
+        },
+        cycles,
+    )
+    .await
+    {
+        return NotifyTopUpResult::Err(NotifyError::Other {
+            error_message: err,
+            error_code: 0,
+        });
+    }
+
+    PROCESSED.with(|p| p.borrow_mut().insert(arg.block_index, Nat::from(cycles)));
+    NotifyTopUpResult::Ok(Nat::from(cycles))
+}
+
+#[query]
+fn get_icp_xdr_conversion_rate() -> IcpXdrConversionRateResponse {
+    IcpXdrConversionRateResponse {
+        certificate: vec![],
+        data: IcpXdrConversionRate {
+            xdr_permyriad_per_icp: XDR_PERMYRIAD_PER_ICP,
+            timestamp_seconds: 0,
+        },
+        hash_tree: vec![],
+    }
+}
@@ -0,0 +1,153 @@
+//! Minimal ICP ledger for the integration tests, supports `transfer` with deduplication,
+//! `query_blocks` and `account_balance`. Tokens are created with the test-only `mint` call.
+use std::{cell::RefCell, collections::HashMap};
+
+use ic_cdk::{api::time, caller, query, update};
+use ic_ledger_types::{
+    AccountBalanceArgs, AccountIdentifier, Block, BlockIndex, GetBlocksArgs, Memo, Operation,
+    QueryBlocksResponse, Timestamp, Tokens, Transaction, TransferArgs, TransferError, DEFAULT_FEE,
+};
+
+// the ledger only deduplicates transactions created within the last 24 hours
+const TRANSACTION_WINDOW_NANOS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+type DeduplicationKey = (AccountIdentifier, AccountIdentifier, u64, u64, u64);
+
+thread_local! {
+    static BALANCES: RefCell<HashMap<AccountIdentifier, u64>> = RefCell::new(HashMap::new());
+    static BLOCKS: RefCell<Vec<Block>> = const { RefCell::new(Vec::new()) };
+    static SEEN: RefCell<HashMap<DeduplicationKey, BlockIndex>> = RefCell::new(HashMap::new());
+}
+
+fn push_block(memo: Memo, operation: Operation, created_at_time: Timestamp) -> BlockIndex {
+    BLOCKS.with(|blocks| {
+        let mut blocks = blocks.borrow_mut();
+        blocks.push(Block {
+            parent_hash: None,
+            transaction: Transaction {
+                memo,
+                operation: Some(operation),
+                created_at_time,
+                icrc1_memo: None,
+            },
+            timestamp: Timestamp {
+                timestamp_nanos: time(),
+            },
+        });
+        blocks.len() as u64 - 1
+    })
+}
+
+#[update]
+fn mint(to: AccountIdentifier, amount: Tokens) -> BlockIndex {
+    BALANCES.with(|b| *b.borrow_mut().entry(to).or_default() += amount.e8s());
+    push_block(
+        Memo(0),
+        Operation::Mint { to, amount },
+        Timestamp {
+            timestamp_nanos: time(),
+        },
+    )
+}
+
+#[update]
+fn transfer(args: TransferArgs) -> Result<BlockIndex, TransferError> {
+    if args.fee != DEFAULT_FEE {
+        return Err(TransferError::BadFee {
+            expected_fee: DEFAULT_FEE,
+        });
+    }
+
+    let from = AccountIdentifier::new(
+        &caller(),
+        &args
+            .from_subaccount
+            .unwrap_or(ic_ledger_types::DEFAULT_SUBACCOUNT),
+    );
+
+    let key = args.created_at_time.map(|created_at_time| {
+        if time().saturating_sub(created_at_time.timestamp_nanos) > TRANSACTION_WINDOW_NANOS {
+            return Err(TransferError::TxTooOld {
+                allowed_window_nanos: TRANSACTION_WINDOW_NANOS,
+            });
+        }
+
+        Ok((
+            from,
+            args.to,
+            args.amount.e8s(),
+            args.memo.0,
+            created_at_time.timestamp_nanos,
+        ))
+    });
+
+    let key = match key {
+        Some(Ok(key)) => Some(key),
+        Some(Err(err)) => return Err(err),
+        None => None,
+    };
+
+    if let Some(duplicate_of) = key
+        .as_ref()
+        .and_then(|key| SEEN.with(|s| s.borrow().get(key).cloned()))
+    {
+        return Err(TransferError::TxDuplicate { duplicate_of });
+    }
+
+    let balance = BALANCES.with(|b| b.borrow().get(&from).cloned().unwrap_or_default());
+    let total = args.amount.e8s() + args.fee.e8s();
+    if balance < total {
+        return Err(TransferError::InsufficientFunds {
+            balance: Tokens::from_e8s(balance),
+        });
+    }
+
+    BALANCES.with(|b| {
+        let mut b = b.borrow_mut();
+        *b.entry(from).or_default() -= total;
+        *b.entry(args.to).or_default() += args.amount.e8s();
+    });
+
+    let block_index = push_block(
+        args.memo,
+        Operation::Transfer {
+            from,
+            to: args.to,
+            amount: args.amount,
+            fee: args.fee,
+        },
+        args.created_at_time.unwrap_or(Timestamp {
+            timestamp_nanos: time(),
+        }),
+    );
+
+    if let Some(key) = key {
+        SEEN.with(|s| s.borrow_mut().insert(key, block_index));
+    }
+
+    Ok(block_index)
+}
+
+#[query]
+fn query_blocks(args: GetBlocksArgs) -> QueryBlocksResponse {
+    BLOCKS.with(|blocks| {
+        let blocks = blocks.borrow();
+        QueryBlocksResponse {
+            chain_length: blocks.len() as u64,
+            certificate: None,
+            blocks: blocks
+                .iter()
+                .skip(args.start as usize)
+                .take(args.length as usize)
+                .cloned()
+                .collect(),
+            first_block_index: args.start,
+            archived_blocks: vec![],
+        }
+    })
+}
+
+#[query]
+fn account_balance(args: AccountBalanceArgs) -> Tokens {
+    Tokens::from_e8s(BALANCES.with(|b| b.borrow().get(&args.account).cloned().unwrap_or_default()))
+}
@@ -0,0 +1,176 @@
+//! Stand-in for the multisig wallet wasm that the index installs, it keeps the install
+//! arguments across upgrades, implements `set_owner`, `get_wallet_info` and `withdraw_cycles`
+//! and relays notifications and other calls to the index.
+use std::cell::RefCell;
+
+use candid::{CandidType, Deserialize, Nat, Principal, Reserved};
+use ic_cdk::{
+    api::{
+        call::{call, call_raw, CallResult},
+        canister_balance,
+        management_canister::main::{deposit_cycles, CanisterIdRecord},
+    },
+    id, init, post_upgrade, pre_upgrade, query,
+    storage::{stable_restore, stable_save},
+    update,
+};
+
+#[derive(CandidType, Deserialize, Clone)]
+pub struct State {
+    pub owner: Principal,
+    pub whitelist: Vec<Principal>,
+    pub proxy: Principal,
+    pub group_id: u64,
+    pub config: Option<WalletConfig>,
+}
+
+#[derive(CandidType, Deserialize, Clone)]
+pub struct WalletConfig {
+    pub threshold: u64,
+    pub name: String,
+    pub description: Option<String>,
+    pub spending_limits: Option<Vec<SpendingLimit>>,
+}
+
+#[derive(CandidType, Deserialize, Clone)]
+pub struct SpendingLimit {
+    pub token: Principal,
+    pub amount: Nat,
+}
+
+#[derive(CandidType, Deserialize, Clone)]
+pub struct WalletInfo {
+    pub whitelist: Vec<Principal>,
+    pub threshold: u64,
+    pub cycles: u64,
+    pub group_id: u64,
+}
+
+thread_local! {
+    static STATE: RefCell<Option<State>> = const { RefCell::new(None) };
+}
+
+#[init]
+fn init(
+    owner: Principal,
+    whitelist: Vec<Principal>,
+    proxy: Principal,
+    group_id: u64,
+    config: Option<WalletConfig>,
+) {
+    STATE.with(|s| {
+        *s.borrow_mut() = Some(State {
+            owner,
+            whitelist,
+            proxy,
+            group_id,
+            config,
+        })
+    });
+}
+
+// the index upgrades wallets without arguments, the state is kept in stable memory
+#[pre_upgrade]
+fn pre_upgrade() {
+    let state = STATE.with(|s| s.borrow().clone());
+    stable_save((state,)).expect("failed to save the state");
+}
+
+#[post_upgrade]
+fn post_upgrade() {
+    let (state,): (Option<State>,) = stable_restore().expect("failed to restore the state");
+    STATE.with(|s| *s.borrow_mut() = state);
+}
+
+#[query]
+fn get_state() -> Option<State> {
+    STATE.with(|s| s.borrow().clone())
+}
+
+#[query]
+fn get_owner() -> Principal {
+    STATE.with(|s| {
+        s.borrow()
+            .as_ref()
+            .map(|state| state.owner)
+            .unwrap_or_else(Principal::anonymous)
+    })
+}
+
+/// Without a config the stub reports a simple majority of the whitelist as the threshold
+#[query]
+fn get_wallet_info() -> WalletInfo {
+    let state = STATE.with(|s| s.borrow().clone());
+    let whitelist = state
+        .as_ref()
+        .map(|state| state.whitelist.clone())
+        .unwrap_or_default();
+    let group_id = state
+        .as_ref()
+        .map(|state| state.group_id)
+        .unwrap_or_default();
+    let threshold = state
+        .and_then(|state| state.config)
+        .map(|config| config.threshold)
+        .unwrap_or(whitelist.len() as u64 / 2 + 1);
+
+    WalletInfo {
+        whitelist,
+        threshold,
+        cycles: canister_balance(),
+        group_id,
+    }
+}
+
+#[update]
+fn set_owner(owner: Principal) -> Result<Principal, String> {
+    STATE.with(|s| match s.borrow_mut().as_mut() {
+        Some(state) => {
+            state.owner = owner;
+            Ok(id())
+        }
+        None => Err("not initialized".to_string()),
+    })
+}
+
+// cycles the stub keeps to finish the call that sends the rest
+const CYCLES_RESERVE: u64 = 100_000_000_000;
+
+#[update]
+async fn withdraw_cycles(to: Principal) -> Result<u64, String> {
+    let cycles = canister_balance().saturating_sub(CYCLES_RESERVE);
+
+    deposit_cycles(CanisterIdRecord { canister_id: to }, cycles as u128)
+        .await
+        .map(|_| cycles)
+        .map_err(|(_, err)| err)
+}
+
+#[update]
+async fn notify_index(
+    index: Principal,
+    receivers: Vec<Principal>,
+    proposal_id: u64,
+    group_id: u64,
+) -> Result<(), String> {
+    let result: CallResult<(Result<(), Reserved>,)> = call(
+        index,
+        "multisig_new_proposal_notification",
+        (receivers, proposal_id, group_id),
+    )
+    .await;
+
+    match result {
+        Ok((Ok(()),)) => Ok(()),
+        Ok((Err(_),)) => Err("notification rejected by the index".to_string()),
+        Err((_, err)) => Err(err),
+    }
+}
+
+/// Calls `method` on `canister` with candid encoded `args`, so tests can make calls as a wallet
+#[update]
+async fn relay(canister: Principal, method: String, args: Vec<u8>) -> Result<Vec<u8>, String> {
+    call_raw(canister, &method, args, 0)
+        .await
+        .map_err(|(_, err)| err)
+}
@@ -0,0 +1,233 @@
+//! Stand-in for the proxy canister, it answers group member lookups from members that are
+//! registered with `set_group_member` and records the multisig notifications it receives,
+//! both through `multisig_notification` and the older `multisig_*_notification` methods.
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+};
+
+use candid::{CandidType, Deserialize, Principal};
+use ic_cdk::{query, trap, update};
+
+#[derive(CandidType, Deserialize, Clone)]
+pub struct GroupMember {
+    pub principal: Principal,
+    pub group_id: u64,
+    pub roles: Vec<String>,
+}
+
+#[derive(CandidType, Deserialize, Clone)]
+pub enum MultisigEvent {
+    WhitelistNotice {
+        receivers: Vec<Principal>,
+        group_id: u64,
+    },
+    NewProposal {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        proposal_id: u64,
+    },
+    ProposalAccepted {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        proposal_id: u64,
+    },
+    ProposalDeclined {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        proposal_id: u64,
+    },
+    ProposalStatusUpdate {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        proposal_id: u64,
+    },
+    ThresholdChanged {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        threshold: u64,
+    },
+    OwnerTransferred {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        new_owner: Principal,
+    },
+    LowCycles {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        cycles: u64,
+    },
+    WalletSpawned {
+        receivers: Vec<Principal>,
+        group_id: u64,
+    },
+    WalletToppedUp {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        cycles: u64,
+    },
+    WalletUpgraded {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        wasm_version: String,
+    },
+}
+
+#[derive(CandidType, Deserialize, Clone)]
+pub struct ReceivedNotification {
+    pub wallet: Principal,
+    pub event: MultisigEvent,
+}
+
+thread_local! {
+    static MEMBERS: RefCell<HashMap<(u64, Principal), GroupMember>> = RefCell::new(HashMap::new());
+    static NOTIFICATIONS: RefCell<Vec<ReceivedNotification>> = const { RefCell::new(Vec::new()) };
+    static UNAVAILABLE: Cell<bool> = const { Cell::new(false) };
+    static LEGACY_ONLY: Cell<bool> = const { Cell::new(false) };
+}
+
+#[update]
+fn set_group_member(group_id: u64, principal: Principal, roles: Vec<String>) {
+    MEMBERS.with(|m| {
+        m.borrow_mut().insert(
+            (group_id, principal),
+            GroupMember {
+                principal,
+                group_id,
+                roles,
+            },
+        )
+    });
+}
+
+#[update]
+fn remove_group_member(group_id: u64, principal: Principal) {
+    MEMBERS.with(|m| m.borrow_mut().remove(&(group_id, principal)));
+}
+
+#[query]
+fn get_group_member(group_id: u64, principal: Principal) -> Result<GroupMember, String> {
+    MEMBERS.with(|m| {
+        m.borrow()
+            .get(&(group_id, principal))
+            .cloned()
+            .ok_or_else(|| "member not found".to_string())
+    })
+}
+
+/// Makes the notification endpoints reject their calls until it is set back to `false`
+#[update]
+fn set_unavailable(unavailable: bool) {
+    UNAVAILABLE.with(|u| u.set(unavailable));
+}
+
+/// Makes `multisig_notification` reject its calls like the deployed proxy that does not have it
+#[update]
+fn set_legacy_only(legacy_only: bool) {
+    LEGACY_ONLY.with(|l| l.set(legacy_only));
+}
+
+#[query]
+fn get_notifications() -> Vec<ReceivedNotification> {
+    NOTIFICATIONS.with(|n| n.borrow().clone())
+}
+
+fn receive(wallet: Principal, event: MultisigEvent) {
+    if UNAVAILABLE.with(|u| u.get()) {
+        trap("proxy unavailable");
+    }
+
+    NOTIFICATIONS.with(|n| n.borrow_mut().push(ReceivedNotification { wallet, event }));
+}
+
+#[update]
+fn multisig_notification(wallet: Principal, event: MultisigEvent) {
+    if LEGACY_ONLY.with(|l| l.get()) {
+        trap("Canister has no update method 'multisig_notification'");
+    }
+
+    receive(wallet, event);
+}
+
+#[update]
+fn multisig_whitelist_notice_notification(
+    receivers: Vec<Principal>,
+    wallet: Principal,
+    group_id: u64,
+) {
+    receive(
+        wallet,
+        MultisigEvent::WhitelistNotice {
+            receivers,
+            group_id,
+        },
+    );
+}
+
+#[update]
+fn multisig_new_proposal_notification(
+    receivers: Vec<Principal>,
+    wallet: Principal,
+    proposal_id: u64,
+    group_id: u64,
+) {
+    receive(
+        wallet,
+        MultisigEvent::NewProposal {
+            receivers,
+            group_id,
+            proposal_id,
+        },
+    );
+}
+
+#[update]
+fn multisig_proposal_accept_notification(
+    receivers: Vec<Principal>,
+    wallet: Principal,
+    proposal_id: u64,
+    group_id: u64,
+) {
+    receive(
+        wallet,
+        MultisigEvent::ProposalAccepted {
+            receivers,
+            group_id,
+            proposal_id,
+        },
+    );
+}
+
+#[update]
+fn multisig_proposal_decline_notification(
+    receivers: Vec<Principal>,
+    wallet: Principal,
+    proposal_id: u64,
+    group_id: u64,
+) {
+    receive(
+        wallet,
+        MultisigEvent::ProposalDeclined {
+            receivers,
+            group_id,
+            proposal_id,
+        },
+    );
+}
+
+#[update]
+fn multisig_proposal_status_update_notification(
+    receivers: Vec<Principal>,
+    wallet: Principal,
+    proposal_id: u64,
+    group_id: u64,
+) {
+    receive(
+        wallet,
+        MultisigEvent::ProposalStatusUpdate {
+            receivers,
+            group_id,
+            proposal_id,
+        },
+    );
+}
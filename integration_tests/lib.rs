@@ -0,0 +1,390 @@
+//! PocketIC harness for the wallet_index canister. It deploys the index together with the
+//! ledger, cycles minting and multisig stubs from `integration_tests/canisters`.
+//!
+//! The wasms and the PocketIC server are prepared by `scripts/test.sh`, after that the
+//! suite runs offline with `cargo test -p integration_tests -- --ignored`.
+pub mod types;
+
+use std::{env, fs, path::PathBuf, time::Duration};
+
+use candid::{
+    utils::{ArgumentDecoder, ArgumentEncoder},
+    Encode, Principal,
+};
+use ic_ledger_types::{AccountIdentifier, Memo, Tokens, TransferArgs, DEFAULT_SUBACCOUNT};
+use pocket_ic::{call_candid_as, common::rest::RawEffectivePrincipal, query_candid_as, PocketIc};
+
+use crate::types::{
+    Error, IndexArgs, InitArgs, Notification, Page, PageArgs, ReceivedNotification, SpawnStatus,
+    WalletConfig, WalletData, WalletFilter,
+};
+
+pub const ICP_FEE: u64 = 10_000;
+pub const E8S_PER_ICP: u64 = 100_000_000;
+
+const STUB_CYCLES: u128 = 1_000_000_000_000_000;
+const INDEX_CYCLES: u128 = 10_000_000_000_000;
+
+pub fn admin() -> Principal {
+    Principal::from_slice(&[1, 1])
+}
+
+pub fn alice() -> Principal {
+    Principal::from_slice(&[2, 1])
+}
+
+pub fn bob() -> Principal {
+    Principal::from_slice(&[3, 1])
+}
+
+fn wasm(name: &str) -> Vec<u8> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("..")
+        .join("target/wasm32-unknown-unknown/release")
+        .join(format!("{}.wasm", name));
+
+    fs::read(&path).unwrap_or_else(|_| {
+        panic!(
+            "{} not found, build the canisters with scripts/test.sh first",
+            path.display()
+        )
+    })
+}
+
+pub struct TestEnv {
+    pub pic: PocketIc,
+    pub index: Principal,
+    pub ledger: Principal,
+    pub cmc: Principal,
+    pub proxy: Principal,
+}
+
+impl TestEnv {
+    pub fn new() -> Self {
+        let pic = PocketIc::new();
+
+        let ledger = pic.create_canister();
+        pic.add_cycles(ledger, STUB_CYCLES);
+        pic.install_canister(ledger, wasm("ledger_stub"), vec![], None);
+
+        let cmc = pic.create_canister();
+        pic.add_cycles(cmc, STUB_CYCLES);
+        pic.install_canister(cmc, wasm("cmc_stub"), Encode!(&ledger).unwrap(), None);
+
+        let proxy = pic.create_canister();
+        pic.add_cycles(proxy, STUB_CYCLES);
+        pic.install_canister(proxy, wasm("proxy_stub"), vec![], None);
+
+        let index = pic.create_canister();
+        pic.add_cycles(index, INDEX_CYCLES);
+        let args = IndexArgs::Init(InitArgs {
+            proxy_canister_id: proxy,
+            admins: vec![admin()],
+            ledger_canister_id: Some(ledger),
+            cmc_canister_id: Some(cmc),
+            catalyze_fee: None,
+            min_cycles_for_spinup: None,
+            treasury: None,
+            wallet_policy: None,
+            max_signers: None,
+        });
+        pic.install_canister(index, wasm("wallet_index"), Encode!(&args).unwrap(), None);
+
+        let env = Self {
+            pic,
+            index,
+            ledger,
+            cmc,
+            proxy,
+        };
+
+        let (uploaded,): (bool,) = env
+            .update(
+                index,
+                admin(),
+                "_dev_upload_multisig_wasm",
+                (wasm("multisig_stub"),),
+            )
+            .unwrap();
+        assert!(uploaded);
+
+        env
+    }
+
+    pub fn update<A, R>(
+        &self,
+        canister: Principal,
+        sender: Principal,
+        method: &str,
+        args: A,
+    ) -> Result<R, String>
+    where
+        A: ArgumentEncoder,
+        R: for<'a> ArgumentDecoder<'a>,
+    {
+        call_candid_as(
+            &self.pic,
+            canister,
+            RawEffectivePrincipal::None,
+            sender,
+            method,
+            args,
+        )
+        .map_err(|err| format!("{:?}", err))
+    }
+
+    pub fn query<A, R>(
+        &self,
+        canister: Principal,
+        sender: Principal,
+        method: &str,
+        args: A,
+    ) -> Result<R, String>
+    where
+        A: ArgumentEncoder,
+        R: for<'a> ArgumentDecoder<'a>,
+    {
+        query_candid_as(&self.pic, canister, sender, method, args)
+            .map_err(|err| format!("{:?}", err))
+    }
+
+    pub fn mint(&self, to: Principal, e8s: u64) {
+        let _: (u64,) = self
+            .update(
+                self.ledger,
+                admin(),
+                "mint",
+                (
+                    AccountIdentifier::new(&to, &DEFAULT_SUBACCOUNT),
+                    Tokens::from_e8s(e8s),
+                ),
+            )
+            .unwrap();
+    }
+
+    pub fn balance(&self, owner: Principal) -> u64 {
+        let (tokens,): (Tokens,) = self
+            .query(
+                self.ledger,
+                owner,
+                "account_balance",
+                (ic_ledger_types::AccountBalanceArgs {
+                    account: AccountIdentifier::new(&owner, &DEFAULT_SUBACCOUNT),
+                },),
+            )
+            .unwrap();
+        tokens.e8s()
+    }
+
+    /// Transfers `e8s` from `from` to the index and returns the blockheight of the transfer
+    pub fn transfer_to_index(&self, from: Principal, e8s: u64) -> u64 {
+        let args = TransferArgs {
+            memo: Memo(0),
+            amount: Tokens::from_e8s(e8s),
+            fee: Tokens::from_e8s(ICP_FEE),
+            from_subaccount: None,
+            to: AccountIdentifier::new(&self.index, &DEFAULT_SUBACCOUNT),
+            created_at_time: None,
+        };
+
+        let (result,): (Result<u64, ic_ledger_types::TransferError>,) =
+            self.update(self.ledger, from, "transfer", (args,)).unwrap();
+        result.unwrap()
+    }
+
+    /// Registers `principal` as a member of `group_id` in the proxy stub
+    pub fn set_group_member(&self, group_id: u64, principal: Principal, roles: &[&str]) {
+        let roles: Vec<String> = roles.iter().map(|role| role.to_string()).collect();
+        let _: () = self
+            .update(
+                self.proxy,
+                admin(),
+                "set_group_member",
+                (group_id, principal, roles),
+            )
+            .unwrap();
+    }
+
+    pub fn spawn_wallet(
+        &self,
+        sender: Principal,
+        blockheight: u64,
+        whitelist: Vec<Principal>,
+        group_id: u64,
+    ) -> Result<Principal, Error> {
+        self.spawn_wallet_with_config(sender, blockheight, whitelist, group_id, None)
+    }
+
+    pub fn spawn_wallet_with_config(
+        &self,
+        sender: Principal,
+        blockheight: u64,
+        whitelist: Vec<Principal>,
+        group_id: u64,
+        config: Option<WalletConfig>,
+    ) -> Result<Principal, Error> {
+        let (result,): (Result<Principal, Error>,) = self
+            .update(
+                self.index,
+                sender,
+                "spawn_wallet",
+                (blockheight, whitelist, group_id, None::<String>, config),
+            )
+            .unwrap();
+        result
+    }
+
+    /// Funds `owner`, makes it the owner of `group_id`, spawns a wallet for the group and
+    /// returns its principal
+    pub fn spawn_funded_wallet(&self, owner: Principal, group_id: u64) -> Principal {
+        self.set_group_member(group_id, owner, &["owner"]);
+        self.mint(owner, 3 * E8S_PER_ICP);
+        let blockheight = self.transfer_to_index(owner, 2 * E8S_PER_ICP);
+        self.spawn_wallet(owner, blockheight, vec![owner, bob()], group_id)
+            .expect("spawn_wallet failed")
+    }
+
+    /// Uploads the multisig stub with an extra custom section, a new wasm version the
+    /// wallets can be upgraded to
+    pub fn upload_new_wallet_version(&self) {
+        let mut wasm = wasm("multisig_stub");
+        // custom section 0 with the name "v2" and no payload
+        wasm.extend_from_slice(&[0, 3, 2, b'v', b'2']);
+
+        let (uploaded,): (bool,) = self
+            .update(self.index, admin(), "_dev_upload_multisig_wasm", (wasm,))
+            .unwrap();
+        assert!(uploaded);
+    }
+
+    /// Deploys the multisig stub outside of the index, as a group would deploy a wallet by
+    /// hand, and hands control to `controllers`
+    pub fn deploy_wallet(
+        &self,
+        owner: Principal,
+        group_id: u64,
+        controllers: Vec<Principal>,
+    ) -> Principal {
+        let wallet = self.pic.create_canister();
+        self.pic.add_cycles(wallet, INDEX_CYCLES);
+
+        let args = Encode!(
+            &owner,
+            &vec![owner, bob()],
+            &self.proxy,
+            &group_id,
+            &None::<WalletConfig>
+        )
+        .unwrap();
+        self.pic
+            .install_canister(wallet, wasm("multisig_stub"), args, None);
+        self.pic
+            .set_controllers(wallet, None, controllers)
+            .expect("set_controllers failed");
+        wallet
+    }
+
+    pub fn get_spawn(&self, blockheight: u64) -> Result<(u64, SpawnStatus), Error> {
+        let (result,): (Result<(u64, SpawnStatus), Error>,) = self
+            .query(self.index, admin(), "get_spawn", (blockheight,))
+            .unwrap();
+        result
+    }
+
+    /// Collects every page of `get_wallets`
+    pub fn get_wallets(&self) -> Vec<(Principal, WalletData)> {
+        let mut wallets = vec![];
+        let mut cursor: Option<Principal> = None;
+
+        loop {
+            let (page,): (Page<Principal, WalletData>,) = self
+                .query(
+                    self.index,
+                    admin(),
+                    "get_wallets",
+                    (
+                        Some(PageArgs {
+                            cursor,
+                            limit: None,
+                        }),
+                        None::<WalletFilter>,
+                    ),
+                )
+                .unwrap();
+
+            wallets.extend(page.items);
+
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return wallets,
+            }
+        }
+    }
+
+    pub fn get_wallet_by_group(&self, group_id: u64) -> Result<(Principal, WalletData), Error> {
+        let (result,): (Result<(Principal, WalletData), Error>,) = self
+            .query(self.index, admin(), "get_wallet_by_group", (group_id,))
+            .unwrap();
+        result
+    }
+
+    pub fn get_wallets_by_owner(&self, owner: Principal) -> Vec<Principal> {
+        let (wallets,): (Vec<(Principal, WalletData)>,) = self
+            .query(self.index, admin(), "get_wallets_by_owner", (owner,))
+            .unwrap();
+        wallets.into_iter().map(|(wallet, _)| wallet).collect()
+    }
+
+    /// Advances the time past the outbox interval so the delivery timer runs
+    pub fn deliver_notifications(&self) {
+        self.pic.advance_time(Duration::from_secs(60));
+        for _ in 0..5 {
+            self.pic.tick();
+        }
+    }
+
+    /// Makes the proxy stub reject notifications until it is set back to `false`
+    pub fn set_proxy_unavailable(&self, unavailable: bool) {
+        let _: () = self
+            .update(self.proxy, admin(), "set_unavailable", (unavailable,))
+            .unwrap();
+    }
+
+    pub fn set_proxy_legacy_only(&self, legacy_only: bool) {
+        let _: () = self
+            .update(self.proxy, admin(), "set_legacy_only", (legacy_only,))
+            .unwrap();
+    }
+
+    pub fn proxy_notifications(&self) -> Vec<ReceivedNotification> {
+        let (notifications,): (Vec<ReceivedNotification>,) = self
+            .query(self.proxy, admin(), "get_notifications", ())
+            .unwrap();
+        notifications
+    }
+
+    pub fn get_notification_outbox(&self) -> Page<u64, Notification> {
+        let (page,): (Page<u64, Notification>,) = self
+            .query(
+                self.index,
+                admin(),
+                "get_notification_outbox",
+                (None::<PageArgs<u64>>,),
+            )
+            .unwrap();
+        page
+    }
+
+    pub fn get_wallet(&self, wallet: Principal) -> Option<WalletData> {
+        self.get_wallets()
+            .into_iter()
+            .find(|(principal, _)| *principal == wallet)
+            .map(|(_, data)| data)
+    }
+}
+
+impl Default for TestEnv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
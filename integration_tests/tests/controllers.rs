@@ -0,0 +1,65 @@
+use candid::Principal;
+use integration_tests::{
+    alice, bob,
+    types::{ArchivedWallet, Error, ErrorKind, WalletData},
+    TestEnv,
+};
+
+fn add_controllers(
+    env: &TestEnv,
+    sender: Principal,
+    wallet: Principal,
+    controllers: Vec<Principal>,
+) -> Result<(Principal, WalletData), Error> {
+    let (result,): (Result<(Principal, WalletData), Error>,) = env
+        .update(
+            env.index,
+            sender,
+            "add_wallet_controllers",
+            (wallet, controllers),
+        )
+        .unwrap();
+    result
+}
+
+fn release_control(
+    env: &TestEnv,
+    sender: Principal,
+    wallet: Principal,
+) -> Result<(Principal, WalletData), Error> {
+    let (result,): (Result<(Principal, WalletData), Error>,) = env
+        .update(env.index, sender, "release_wallet_control", (wallet,))
+        .unwrap();
+    result
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn owner_adds_a_backup_controller_and_releases_control() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+    assert_eq!(
+        env.get_wallet(wallet).unwrap().controllers,
+        Some(vec![env.index])
+    );
+
+    let err = add_controllers(&env, bob(), wallet, vec![bob()]).unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::Unauthorized);
+
+    add_controllers(&env, alice(), wallet, vec![bob()]).unwrap();
+    let mut controllers = env.pic.get_controllers(wallet);
+    controllers.sort();
+    let mut expected = vec![env.index, bob()];
+    expected.sort();
+    assert_eq!(controllers, expected);
+
+    let (_, data) = release_control(&env, alice(), wallet).unwrap();
+    assert_eq!(data.controllers, Some(vec![bob()]));
+    assert_eq!(env.pic.get_controllers(wallet), vec![bob()]);
+
+    // the index can no longer delete the wallet
+    let (result,): (Result<(Principal, ArchivedWallet), Error>,) = env
+        .update(env.index, alice(), "decommission_wallet", (wallet,))
+        .unwrap();
+    assert_eq!(result.unwrap_err().error_type, ErrorKind::BadRequest);
+}
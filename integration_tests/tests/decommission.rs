@@ -0,0 +1,64 @@
+use candid::Principal;
+use integration_tests::{
+    alice, bob,
+    types::{ArchivedWallet, Error, ErrorKind, Page, PageArgs},
+    TestEnv,
+};
+
+fn decommission(
+    env: &TestEnv,
+    sender: Principal,
+    wallet: Principal,
+) -> Result<(Principal, ArchivedWallet), Error> {
+    let (result,): (Result<(Principal, ArchivedWallet), Error>,) = env
+        .update(
+            env.index,
+            sender,
+            "decommission_wallet",
+            (wallet, None::<bool>),
+        )
+        .unwrap();
+    result
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn decommissioned_wallet_is_deleted_and_archived() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+    let index_cycles = env.pic.cycle_balance(env.index);
+
+    let (_, archived) = decommission(&env, alice(), wallet).unwrap();
+    assert_eq!(archived.archived_by, alice());
+    assert!(archived.cycles_returned > 0);
+    assert!(env.pic.cycle_balance(env.index) > index_cycles);
+
+    assert!(env.get_wallet(wallet).is_none());
+    let result: Result<(Principal,), String> = env.query(wallet, alice(), "get_owner", ());
+    assert!(result.is_err());
+
+    let (archive,): (Page<Principal, ArchivedWallet>,) = env
+        .query(
+            env.index,
+            alice(),
+            "get_archived_wallets",
+            (None::<PageArgs<Principal>>,),
+        )
+        .unwrap();
+    assert_eq!(archive.items[0].0, wallet);
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn only_the_owner_can_decommission_an_empty_wallet() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+
+    let err = decommission(&env, bob(), wallet).unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::Unauthorized);
+
+    env.mint(wallet, 100_000);
+    let err = decommission(&env, alice(), wallet).unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::BadRequest);
+    assert!(env.get_wallet(wallet).is_some());
+}
@@ -0,0 +1,40 @@
+use integration_tests::{
+    alice,
+    types::{IndexEvent, IndexEventKind},
+    TestEnv,
+};
+
+fn get_events(env: &TestEnv, from_id: u64) -> Vec<(u64, IndexEventKind)> {
+    let (events,): (Vec<(u64, IndexEvent)>,) = env
+        .query(env.index, alice(), "get_events", (from_id, None::<u64>))
+        .unwrap();
+    events
+        .into_iter()
+        .map(|(id, event)| (id, event.kind))
+        .collect()
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn events_follow_the_setup_and_a_spawn() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+
+    let events = get_events(&env, 0);
+    let ids: Vec<u64> = events.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids, (0..events.len() as u64).collect::<Vec<_>>());
+
+    assert!(matches!(events[0].1, IndexEventKind::ConfigChanged { .. }));
+    assert!(matches!(events[1].1, IndexEventKind::WasmUploaded { .. }));
+    assert!(matches!(
+        events[2].1,
+        IndexEventKind::SpawnStarted { group_id: 1, caller, .. } if caller == alice()
+    ));
+    assert!(matches!(
+        events[3].1,
+        IndexEventKind::SpawnFinished { wallet: spawned, .. } if spawned == wallet
+    ));
+
+    // polling from the next id only returns newer events
+    assert!(get_events(&env, events.len() as u64).is_empty());
+}
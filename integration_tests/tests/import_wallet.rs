@@ -0,0 +1,82 @@
+use candid::Principal;
+use integration_tests::{
+    admin, alice, bob,
+    types::{Error, ErrorKind, WalletData, WalletOrigin, WasmVersion},
+    TestEnv,
+};
+
+fn import_wallet(
+    env: &TestEnv,
+    sender: Principal,
+    wallet: Principal,
+) -> Result<(Principal, WalletData), Error> {
+    let (result,): (Result<(Principal, WalletData), Error>,) = env
+        .update(env.index, sender, "import_wallet", (wallet, None::<String>))
+        .unwrap();
+    result
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn owner_imports_a_wallet_controlled_by_the_index() {
+    let env = TestEnv::new();
+    let wallet = env.deploy_wallet(alice(), 4, vec![alice()]);
+
+    let err = import_wallet(&env, alice(), wallet).unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::BadRequest);
+
+    env.pic
+        .set_controllers(wallet, Some(alice()), vec![alice(), env.index])
+        .unwrap();
+
+    let err = import_wallet(&env, bob(), wallet).unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::Unauthorized);
+
+    // the owner of the wallet also has to manage the group it reports
+    env.set_group_member(4, alice(), &["member"]);
+    let err = import_wallet(&env, alice(), wallet).unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::Unauthorized);
+
+    env.set_group_member(4, alice(), &["owner"]);
+    let (_, data) = import_wallet(&env, alice(), wallet).unwrap();
+    assert_eq!(data.owner, alice());
+    assert_eq!(data.group_id, 4);
+    assert_eq!(data.origin, Some(WalletOrigin::Imported));
+
+    let (indexed, _) = env.get_wallet_by_group(4).unwrap();
+    assert_eq!(indexed, wallet);
+
+    let err = import_wallet(&env, alice(), wallet).unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::Duplicate);
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn wallets_with_an_unknown_wasm_are_not_imported() {
+    let env = TestEnv::new();
+
+    // the uploaded wasm is registered on upload
+    let (versions,): (Vec<(String, WasmVersion)>,) = env
+        .query(env.index, alice(), "get_wasm_versions", ())
+        .unwrap();
+    assert_eq!(versions.len(), 1);
+    assert!(versions[0].1.uploaded);
+
+    // a canister without a module does not run a registered version
+    let canister = env.pic.create_canister();
+    env.pic
+        .set_controllers(canister, None, vec![env.index])
+        .unwrap();
+    let err = import_wallet(&env, admin(), canister).unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::BadRequest);
+
+    let (result,): (Result<(), Error>,) = env
+        .update(
+            env.index,
+            admin(),
+            "register_wasm_version",
+            ("not a hash".to_string(),),
+        )
+        .unwrap();
+    assert_eq!(result.unwrap_err().error_type, ErrorKind::BadRequest);
+}
@@ -0,0 +1,216 @@
+use candid::{Encode, Principal};
+use integration_tests::{
+    admin, alice, bob,
+    types::{MultisigEvent, NotificationCounter, Page, PageArgs},
+    TestEnv,
+};
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn notifications_are_rejected_for_unknown_callers() {
+    let env = TestEnv::new();
+    env.spawn_funded_wallet(alice(), 1);
+
+    let result: Result<(), String> = env.update(
+        env.index,
+        alice(),
+        "multisig_new_proposal_notification",
+        (vec![bob()], 1_u64, 1_u64),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn notifications_are_accepted_from_known_wallets() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+
+    let (result,): (Result<(), String>,) = env
+        .update(
+            wallet,
+            alice(),
+            "notify_index",
+            (env.index, vec![bob()], 1_u64, 1_u64),
+        )
+        .unwrap();
+    assert!(result.is_ok());
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn notifications_are_delivered_to_the_proxy() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+    env.deliver_notifications();
+    let delivered = env.proxy_notifications().len();
+
+    let _: (Result<(), String>,) = env
+        .update(
+            wallet,
+            alice(),
+            "notify_index",
+            (env.index, vec![bob()], 1_u64, 1_u64),
+        )
+        .unwrap();
+    env.deliver_notifications();
+
+    let notifications = env.proxy_notifications();
+    assert_eq!(notifications.len(), delivered + 1);
+    assert_eq!(notifications[delivered].wallet, wallet);
+    assert_eq!(
+        notifications[delivered].event,
+        MultisigEvent::NewProposal {
+            receivers: vec![bob()],
+            group_id: 1,
+            proposal_id: 1,
+        }
+    );
+    assert_eq!(env.get_notification_outbox().total, 0);
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn notify_relays_the_event_to_the_proxy() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+    env.deliver_notifications();
+    let delivered = env.proxy_notifications().len();
+    let event = MultisigEvent::ThresholdChanged {
+        receivers: vec![bob()],
+        group_id: 1,
+        threshold: 2,
+    };
+
+    let (result,): (Result<Vec<u8>, String>,) = env
+        .update(
+            wallet,
+            alice(),
+            "relay",
+            (env.index, "notify", Encode!(&event).unwrap()),
+        )
+        .unwrap();
+    assert!(result.is_ok());
+    env.deliver_notifications();
+
+    let notifications = env.proxy_notifications();
+    assert_eq!(notifications.len(), delivered + 1);
+    assert_eq!(notifications[delivered].wallet, wallet);
+    assert_eq!(notifications[delivered].event, event);
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn events_wait_in_the_outbox_for_a_proxy_with_multisig_notification() {
+    let env = TestEnv::new();
+    env.set_proxy_legacy_only(true);
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+    env.deliver_notifications();
+
+    assert!(env.proxy_notifications().is_empty());
+    let outbox = env.get_notification_outbox();
+    assert_eq!(outbox.total, 1);
+    assert_eq!(outbox.items[0].1.method, "multisig_notification");
+    assert!(outbox.items[0].1.attempts >= 1);
+
+    env.set_proxy_legacy_only(false);
+    env.deliver_notifications();
+
+    let notifications = env.proxy_notifications();
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0].wallet, wallet);
+    assert_eq!(
+        notifications[0].event,
+        MultisigEvent::WalletSpawned {
+            receivers: vec![alice(), bob()],
+            group_id: 1,
+        }
+    );
+    assert_eq!(env.get_notification_outbox().total, 0);
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn notifications_are_retried_while_the_proxy_is_unavailable() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+    env.deliver_notifications();
+    let delivered = env.proxy_notifications().len();
+    env.set_proxy_unavailable(true);
+
+    let _: (Result<(), String>,) = env
+        .update(
+            wallet,
+            alice(),
+            "notify_index",
+            (env.index, vec![bob()], 1_u64, 1_u64),
+        )
+        .unwrap();
+    env.deliver_notifications();
+
+    let outbox = env.get_notification_outbox();
+    assert_eq!(outbox.total, 1);
+    assert!(outbox.items[0].1.attempts >= 1);
+    assert_eq!(env.proxy_notifications().len(), delivered);
+
+    env.set_proxy_unavailable(false);
+    env.deliver_notifications();
+
+    assert_eq!(env.proxy_notifications().len(), delivered + 1);
+    assert_eq!(env.get_notification_outbox().total, 0);
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn spawning_a_wallet_notifies_its_members() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+    env.deliver_notifications();
+
+    let notifications = env.proxy_notifications();
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0].wallet, wallet);
+    assert_eq!(
+        notifications[0].event,
+        MultisigEvent::WalletSpawned {
+            receivers: vec![alice(), bob()],
+            group_id: 1,
+        }
+    );
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn notifications_for_another_group_are_rejected_and_counted() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+
+    let (result,): (Result<(), String>,) = env
+        .update(
+            wallet,
+            alice(),
+            "notify_index",
+            (env.index, vec![bob()], 1_u64, 2_u64),
+        )
+        .unwrap();
+    assert!(result.is_err());
+
+    let (counters,): (Page<Principal, NotificationCounter>,) = env
+        .query(
+            env.index,
+            admin(),
+            "get_notification_counters",
+            (None::<PageArgs<Principal>>,),
+        )
+        .unwrap();
+    assert_eq!(
+        counters.items,
+        vec![(
+            wallet,
+            NotificationCounter {
+                accepted: 0,
+                rejected: 1
+            }
+        )]
+    );
+}
@@ -0,0 +1,214 @@
+use candid::Principal;
+use integration_tests::{
+    alice, bob,
+    types::{ErrorKind, MultisigState, WalletConfig},
+    TestEnv, E8S_PER_ICP, ICP_FEE,
+};
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn spawn_wallet_installs_and_registers_the_wallet() {
+    let env = TestEnv::new();
+    env.set_group_member(7, alice(), &["owner"]);
+    env.mint(alice(), 3 * E8S_PER_ICP);
+    let blockheight = env.transfer_to_index(alice(), 2 * E8S_PER_ICP);
+
+    let wallet = env
+        .spawn_wallet(alice(), blockheight, vec![alice(), bob()], 7)
+        .expect("spawn_wallet failed");
+
+    let data = env.get_wallet(wallet).expect("wallet not registered");
+    assert_eq!(data.owner, alice());
+    assert_eq!(data.created_by, alice());
+    assert_eq!(data.group_id, 7);
+    assert_eq!(env.get_wallet_by_group(7).unwrap().0, wallet);
+    assert_eq!(env.get_wallets_by_owner(alice()), vec![wallet]);
+
+    let (_, status) = env.get_spawn(blockheight).unwrap();
+    assert_eq!(status.canister_installed, Some(wallet));
+    assert!(status.done.is_some());
+
+    assert_eq!(env.pic.get_controllers(wallet), vec![env.index]);
+    assert!(env.pic.cycle_balance(wallet) > 0);
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn spawn_wallet_below_minimum_refunds_the_caller() {
+    let env = TestEnv::new();
+    env.set_group_member(1, alice(), &["owner"]);
+    env.mint(alice(), E8S_PER_ICP);
+    let blockheight = env.transfer_to_index(alice(), E8S_PER_ICP / 2);
+
+    let err = env
+        .spawn_wallet(alice(), blockheight, vec![alice(), bob()], 1)
+        .unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::InsufficientBalance);
+
+    let (_, status) = env.get_spawn(blockheight).unwrap();
+    assert!(status.min_amount_error.is_some());
+    assert!(status.done.is_none());
+
+    // the transfer to the index and the refund both cost a fee
+    assert_eq!(env.balance(alice()), E8S_PER_ICP - 2 * ICP_FEE);
+    assert_eq!(env.balance(env.index), 0);
+    assert!(env.get_wallets().is_empty());
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn spawn_wallet_rejects_a_duplicate_blockheight() {
+    let env = TestEnv::new();
+    env.set_group_member(1, alice(), &["owner"]);
+    env.mint(alice(), 3 * E8S_PER_ICP);
+    let blockheight = env.transfer_to_index(alice(), 2 * E8S_PER_ICP);
+
+    env.spawn_wallet(alice(), blockheight, vec![alice(), bob()], 1)
+        .expect("spawn_wallet failed");
+
+    let err = env
+        .spawn_wallet(alice(), blockheight, vec![alice(), bob()], 1)
+        .unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::BadRequest);
+    assert_eq!(env.get_wallets().len(), 1);
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn spawn_wallet_rejects_a_transfer_from_another_sender() {
+    let env = TestEnv::new();
+    env.set_group_member(1, bob(), &["owner"]);
+    env.mint(alice(), 3 * E8S_PER_ICP);
+    let blockheight = env.transfer_to_index(alice(), 2 * E8S_PER_ICP);
+
+    let err = env
+        .spawn_wallet(bob(), blockheight, vec![alice(), bob()], 1)
+        .unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::BadRequest);
+    assert!(env.get_wallets().is_empty());
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn spawn_wallet_installs_the_wallet_with_its_config() {
+    let env = TestEnv::new();
+    env.set_group_member(7, alice(), &["owner"]);
+    env.mint(alice(), 3 * E8S_PER_ICP);
+    let blockheight = env.transfer_to_index(alice(), 2 * E8S_PER_ICP);
+    let config = WalletConfig {
+        threshold: 1,
+        name: "Treasury".to_string(),
+        description: Some("Group funds".to_string()),
+        spending_limits: None,
+    };
+
+    let wallet = env
+        .spawn_wallet_with_config(
+            alice(),
+            blockheight,
+            vec![alice(), bob()],
+            7,
+            Some(config.clone()),
+        )
+        .expect("spawn_wallet failed");
+
+    let (state,): (Option<MultisigState>,) = env.query(wallet, alice(), "get_state", ()).unwrap();
+    assert_eq!(state.unwrap().config, Some(config.clone()));
+    assert_eq!(env.get_wallet(wallet).unwrap().config, Some(config));
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn spawn_wallet_rejects_the_anonymous_caller() {
+    let env = TestEnv::new();
+
+    let result: Result<(candid::Reserved,), String> = env.update(
+        env.index,
+        Principal::anonymous(),
+        "spawn_wallet",
+        (0_u64, vec![alice(), bob()], 1_u64, None::<String>),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn spawn_wallet_rejects_an_invalid_whitelist() {
+    let env = TestEnv::new();
+    env.mint(alice(), 3 * E8S_PER_ICP);
+    let blockheight = env.transfer_to_index(alice(), 2 * E8S_PER_ICP);
+
+    let err = env
+        .spawn_wallet(alice(), blockheight, vec![alice(), alice()], 1)
+        .unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::BadRequest);
+
+    // nothing was recorded, so the blockheight can still be used
+    assert!(env.get_spawn(blockheight).is_err());
+    assert_eq!(env.balance(env.index), 2 * E8S_PER_ICP);
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn spawn_wallet_rejects_reserved_signers() {
+    let env = TestEnv::new();
+    env.mint(alice(), 3 * E8S_PER_ICP);
+    let blockheight = env.transfer_to_index(alice(), 2 * E8S_PER_ICP);
+
+    let err = env
+        .spawn_wallet(
+            alice(),
+            blockheight,
+            vec![alice(), Principal::anonymous(), env.index, env.ledger],
+            1,
+        )
+        .unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::BadRequest);
+    assert_eq!(err.info.unwrap().len(), 3);
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn spawn_wallet_rejects_a_caller_outside_the_group() {
+    let env = TestEnv::new();
+    env.mint(alice(), 3 * E8S_PER_ICP);
+    let blockheight = env.transfer_to_index(alice(), 2 * E8S_PER_ICP);
+
+    let err = env
+        .spawn_wallet(alice(), blockheight, vec![alice(), bob()], 1)
+        .unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::Unauthorized);
+    assert!(env.get_spawn(blockheight).is_err());
+    assert_eq!(env.balance(env.index), 2 * E8S_PER_ICP);
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn spawn_wallet_rejects_a_member_without_a_manager_role() {
+    let env = TestEnv::new();
+    env.set_group_member(1, alice(), &["member"]);
+    env.mint(alice(), 3 * E8S_PER_ICP);
+    let blockheight = env.transfer_to_index(alice(), 2 * E8S_PER_ICP);
+
+    let err = env
+        .spawn_wallet(alice(), blockheight, vec![alice(), bob()], 1)
+        .unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::Unauthorized);
+    assert!(env.get_wallets().is_empty());
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn spawn_wallet_allows_one_wallet_per_group_by_default() {
+    let env = TestEnv::new();
+    env.spawn_funded_wallet(alice(), 1);
+
+    env.mint(alice(), 3 * E8S_PER_ICP);
+    let blockheight = env.transfer_to_index(alice(), 2 * E8S_PER_ICP);
+
+    let err = env
+        .spawn_wallet(alice(), blockheight, vec![alice(), bob()], 1)
+        .unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::Duplicate);
+    assert_eq!(env.get_wallets().len(), 1);
+}
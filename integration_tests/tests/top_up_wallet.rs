@@ -0,0 +1,46 @@
+use candid::Principal;
+use integration_tests::{alice, types::Error, TestEnv, E8S_PER_ICP};
+
+fn top_up_wallet(
+    env: &TestEnv,
+    sender: Principal,
+    blockheight: u64,
+    wallet: Principal,
+) -> Result<(), Error> {
+    let (result,): (Result<(), Error>,) = env
+        .update(env.index, sender, "top_up_wallet", (blockheight, wallet))
+        .unwrap();
+    result
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn top_up_wallet_converts_icp_to_cycles_for_the_wallet() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+    let cycles_before = env.pic.cycle_balance(wallet);
+
+    env.mint(alice(), 2 * E8S_PER_ICP);
+    let blockheight = env.transfer_to_index(alice(), E8S_PER_ICP);
+    top_up_wallet(&env, alice(), blockheight, wallet).expect("top_up_wallet failed");
+
+    assert!(env.pic.cycle_balance(wallet) > cycles_before);
+
+    let (_, status) = env.get_spawn(blockheight).unwrap();
+    assert!(status.done.is_some());
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn top_up_wallet_rejects_a_duplicate_blockheight() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+
+    env.mint(alice(), 2 * E8S_PER_ICP);
+    let blockheight = env.transfer_to_index(alice(), E8S_PER_ICP);
+    top_up_wallet(&env, alice(), blockheight, wallet).expect("top_up_wallet failed");
+    let cycles = env.pic.cycle_balance(wallet);
+
+    assert!(top_up_wallet(&env, alice(), blockheight, wallet).is_err());
+    assert_eq!(env.pic.cycle_balance(wallet), cycles);
+}
@@ -0,0 +1,185 @@
+use std::time::Duration;
+
+use candid::Principal;
+use integration_tests::{
+    admin, alice, bob,
+    types::{
+        Error, ErrorKind, MultisigEvent, MultisigState, OwnerMismatch, OwnerReconciliation,
+        OwnershipTransfer, Page, PageArgs, WalletData,
+    },
+    TestEnv,
+};
+
+fn propose(
+    env: &TestEnv,
+    sender: Principal,
+    wallet: Principal,
+    new_owner: Principal,
+) -> Result<(Principal, OwnershipTransfer), Error> {
+    let (result,): (Result<(Principal, OwnershipTransfer), Error>,) = env
+        .update(
+            env.index,
+            sender,
+            "propose_ownership_transfer",
+            (wallet, new_owner),
+        )
+        .unwrap();
+    result
+}
+
+fn accept(
+    env: &TestEnv,
+    sender: Principal,
+    wallet: Principal,
+) -> Result<(Principal, WalletData), Error> {
+    let (result,): (Result<(Principal, WalletData), Error>,) = env
+        .update(env.index, sender, "accept_ownership", (wallet,))
+        .unwrap();
+    result
+}
+
+fn cancel(env: &TestEnv, sender: Principal, wallet: Principal) -> Result<(), Error> {
+    let (result,): (Result<(), Error>,) = env
+        .update(env.index, sender, "cancel_ownership_transfer", (wallet,))
+        .unwrap();
+    result
+}
+
+fn pending_transfers(env: &TestEnv, principal: Principal) -> Page<Principal, OwnershipTransfer> {
+    let (page,): (Page<Principal, OwnershipTransfer>,) = env
+        .query(
+            env.index,
+            principal,
+            "get_pending_ownership_transfers",
+            (None::<PageArgs<Principal>>, Some(principal)),
+        )
+        .unwrap();
+    page
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn accepted_transfer_updates_the_wallet_and_the_index() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+
+    let (_, transfer) = propose(&env, alice(), wallet, bob()).unwrap();
+    assert_eq!(transfer.to, bob());
+    assert_eq!(pending_transfers(&env, bob()).total, 1);
+    assert_eq!(env.get_wallet(wallet).unwrap().owner, alice());
+
+    let (_, data) = accept(&env, bob(), wallet).unwrap();
+    assert_eq!(data.owner, bob());
+    assert_eq!(env.get_wallet(wallet).unwrap().owner, bob());
+    assert!(env.get_wallets_by_owner(alice()).is_empty());
+    assert_eq!(env.get_wallets_by_owner(bob()), vec![wallet]);
+    assert_eq!(pending_transfers(&env, bob()).total, 0);
+
+    let (state,): (Option<MultisigState>,) = env.query(wallet, alice(), "get_state", ()).unwrap();
+    assert_eq!(state.unwrap().owner, bob());
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn accepted_transfer_notifies_the_wallet_members() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+
+    propose(&env, alice(), wallet, bob()).unwrap();
+    accept(&env, bob(), wallet).unwrap();
+    env.deliver_notifications();
+
+    let notification = env.proxy_notifications().pop().unwrap();
+    assert_eq!(notification.wallet, wallet);
+    assert_eq!(
+        notification.event,
+        MultisigEvent::OwnerTransferred {
+            receivers: vec![bob(), alice()],
+            group_id: 1,
+            new_owner: bob(),
+        }
+    );
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn transfer_is_only_proposed_by_the_owner() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+
+    let err = propose(&env, bob(), wallet, bob()).unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::Unauthorized);
+    assert_eq!(pending_transfers(&env, bob()).total, 0);
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn transfer_requires_a_manager_role_in_the_group() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+
+    // the role lookup made by the spawn is cached for a minute
+    env.set_group_member(1, alice(), &["member"]);
+    env.pic.advance_time(Duration::from_secs(61));
+
+    let err = propose(&env, alice(), wallet, bob()).unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::Unauthorized);
+    assert_eq!(env.get_wallet(wallet).unwrap().owner, alice());
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn cancelled_transfer_can_not_be_accepted() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+    propose(&env, alice(), wallet, bob()).unwrap();
+
+    let err = accept(&env, alice(), wallet).unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::Unauthorized);
+
+    cancel(&env, bob(), wallet).unwrap();
+
+    let err = accept(&env, bob(), wallet).unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::NotFound);
+    assert_eq!(env.get_wallet(wallet).unwrap().owner, alice());
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn owners_changed_on_the_wallet_are_found_and_repaired() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+
+    // the stub accepts `set_owner` from anyone, the index does not see the change
+    let _: (Result<Principal, String>,) = env.update(wallet, bob(), "set_owner", (bob(),)).unwrap();
+
+    let (reconciliation,): (OwnerReconciliation,) = env
+        .query(
+            env.index,
+            admin(),
+            "get_owner_mismatches",
+            (None::<PageArgs<Principal>>,),
+        )
+        .unwrap();
+    assert_eq!(
+        reconciliation.mismatches,
+        vec![(
+            wallet,
+            OwnerMismatch {
+                index_owner: alice(),
+                wallet_owner: bob(),
+            }
+        )]
+    );
+
+    let (result,): (Result<OwnerReconciliation, Error>,) = env
+        .update(
+            env.index,
+            admin(),
+            "repair_owners",
+            (None::<PageArgs<Principal>>,),
+        )
+        .unwrap();
+    assert_eq!(result.unwrap().mismatches.len(), 1);
+    assert_eq!(env.get_wallet(wallet).unwrap().owner, bob());
+}
@@ -0,0 +1,67 @@
+use candid::Principal;
+use integration_tests::{
+    admin, alice, bob,
+    types::{CanisterStatusType, Error, ErrorKind, FleetStatus, WalletHealth},
+    TestEnv,
+};
+
+fn wallet_health(
+    env: &TestEnv,
+    sender: Principal,
+    wallet: Principal,
+) -> Result<WalletHealth, Error> {
+    let (result,): (Result<WalletHealth, Error>,) = env
+        .update(env.index, sender, "wallet_health", (wallet,))
+        .unwrap();
+    result
+}
+
+fn get_fleet_status(env: &TestEnv) -> FleetStatus {
+    let (fleet,): (FleetStatus,) = env
+        .query(env.index, admin(), "get_fleet_status", ())
+        .unwrap();
+    fleet
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn wallet_health_reads_the_canister_status() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+
+    assert_eq!(
+        get_fleet_status(&env),
+        FleetStatus {
+            total: 1,
+            unchecked: 1,
+            ..Default::default()
+        }
+    );
+
+    // only the owner and admins read the status of a wallet
+    let err = wallet_health(&env, bob(), wallet).unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::Unauthorized);
+
+    let health = wallet_health(&env, alice(), wallet).unwrap();
+    assert_eq!(health.status, CanisterStatusType::Running);
+    assert!(health.cycles > 0);
+    assert_eq!(
+        health.module_hash,
+        env.get_wallet(wallet).unwrap().wasm_version
+    );
+
+    assert_eq!(
+        get_fleet_status(&env),
+        FleetStatus {
+            total: 1,
+            healthy: 1,
+            ..Default::default()
+        }
+    );
+
+    // a new upload makes the wallet outdated without checking it again
+    env.upload_new_wallet_version();
+    let fleet = get_fleet_status(&env);
+    assert_eq!(fleet.outdated, 1);
+    assert_eq!(fleet.healthy, 0);
+}
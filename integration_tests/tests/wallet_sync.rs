@@ -0,0 +1,47 @@
+use candid::Principal;
+use integration_tests::{
+    admin, alice, bob,
+    types::{PageArgs, WalletSyncReport},
+    TestEnv,
+};
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn synced_wallets_are_returned_with_their_snapshot() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+    assert!(env.get_wallet(wallet).unwrap().snapshot.is_none());
+
+    env.mint(wallet, 100_000);
+
+    let (report,): (WalletSyncReport,) = env
+        .update(
+            env.index,
+            admin(),
+            "sync_wallets",
+            (None::<PageArgs<Principal>>,),
+        )
+        .unwrap();
+    assert_eq!(report.synced, 1);
+    assert!(report.unreachable.is_empty());
+
+    let snapshot = env.get_wallet(wallet).unwrap().snapshot.unwrap();
+    assert_eq!(snapshot.whitelist, vec![alice(), bob()]);
+    assert_eq!(snapshot.threshold, 2);
+    assert_eq!(snapshot.icp_balance.e8s(), 100_000);
+    assert!(snapshot.cycles > 0);
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn only_admins_can_sync_wallets() {
+    let env = TestEnv::new();
+
+    let result: Result<(WalletSyncReport,), _> = env.update(
+        env.index,
+        alice(),
+        "sync_wallets",
+        (None::<PageArgs<Principal>>,),
+    );
+    assert!(result.is_err());
+}
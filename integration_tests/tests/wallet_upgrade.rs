@@ -0,0 +1,81 @@
+use candid::Principal;
+use integration_tests::{
+    admin, alice, bob,
+    types::{CanisterSnapshot, Error, ErrorKind, WalletData},
+    TestEnv,
+};
+
+fn wallet_call<A: candid::utils::ArgumentEncoder>(
+    env: &TestEnv,
+    sender: Principal,
+    method: &str,
+    args: A,
+) -> Result<(Principal, WalletData), Error> {
+    let (result,): (Result<(Principal, WalletData), Error>,) =
+        env.update(env.index, sender, method, args).unwrap();
+    result
+}
+
+fn list_snapshots(env: &TestEnv, wallet: Principal) -> Vec<CanisterSnapshot> {
+    let (result,): (Result<Vec<CanisterSnapshot>, Error>,) = env
+        .update(env.index, alice(), "list_wallet_snapshots", (wallet,))
+        .unwrap();
+    result.unwrap()
+}
+
+#[test]
+#[ignore = "requires PocketIC and the canister wasms, run scripts/test.sh"]
+fn owner_upgrades_a_wallet_after_a_snapshot() {
+    let env = TestEnv::new();
+    let wallet = env.spawn_funded_wallet(alice(), 1);
+
+    // the wallet already runs the uploaded wasm
+    let err = wallet_call(&env, alice(), "upgrade_wallet", (wallet,)).unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::BadRequest);
+
+    env.upload_new_wallet_version();
+
+    let err = wallet_call(&env, bob(), "upgrade_wallet", (wallet,)).unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::Unauthorized);
+
+    let before = env.get_wallet(wallet).unwrap().wasm_version;
+    let (_, data) = wallet_call(&env, alice(), "upgrade_wallet", (wallet,)).unwrap();
+    assert_ne!(data.wasm_version, before);
+
+    let snapshot = data.canister_snapshot.expect("no snapshot recorded");
+    assert_eq!(list_snapshots(&env, wallet), vec![snapshot.clone()]);
+
+    // the stub keeps its state across the upgrade
+    let (owner,): (Principal,) = env.query(wallet, alice(), "get_owner", ()).unwrap();
+    assert_eq!(owner, alice());
+
+    // loading a snapshot rolls back the wallet, only admins do it
+    let err = wallet_call(
+        &env,
+        alice(),
+        "load_wallet_snapshot",
+        (wallet, snapshot.id.clone()),
+    )
+    .unwrap_err();
+    assert_eq!(err.error_type, ErrorKind::Unauthorized);
+
+    // loading the snapshot brings back the previous module
+    let (_, data) = wallet_call(
+        &env,
+        admin(),
+        "load_wallet_snapshot",
+        (wallet, snapshot.id.clone()),
+    )
+    .unwrap();
+    assert_eq!(data.wasm_version, before);
+
+    let (_, data) = wallet_call(
+        &env,
+        alice(),
+        "delete_wallet_snapshot",
+        (wallet, snapshot.id),
+    )
+    .unwrap();
+    assert_eq!(data.canister_snapshot, None);
+    assert!(list_snapshots(&env, wallet).is_empty());
+}
@@ -0,0 +1,387 @@
+//! Client side copies of the wallet_index candid types, only the fields the tests look at
+//! are declared, candid skips the others when decoding.
+use candid::{CandidType, Deserialize, Principal};
+use ic_ledger_types::Tokens;
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum IndexArgs {
+    Init(InitArgs),
+    Upgrade(Option<UpgradeArgs>),
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct InitArgs {
+    pub proxy_canister_id: Principal,
+    pub admins: Vec<Principal>,
+    pub ledger_canister_id: Option<Principal>,
+    pub cmc_canister_id: Option<Principal>,
+    pub catalyze_fee: Option<Tokens>,
+    pub min_cycles_for_spinup: Option<u64>,
+    pub treasury: Option<Principal>,
+    pub wallet_policy: Option<WalletPolicy>,
+    pub max_signers: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct UpgradeArgs {
+    pub proxy_canister_id: Option<Principal>,
+    pub admins: Option<Vec<Principal>>,
+    pub ledger_canister_id: Option<Principal>,
+    pub cmc_canister_id: Option<Principal>,
+    pub catalyze_fee: Option<Tokens>,
+    pub min_cycles_for_spinup: Option<u64>,
+    pub treasury: Option<Principal>,
+    pub wallet_policy: Option<WalletPolicy>,
+    pub max_signers: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug)]
+pub enum WalletPolicy {
+    SingleWallet,
+    MultiWallet,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Error {
+    pub error_type: ErrorKind,
+    pub message: Option<String>,
+    pub info: Option<Vec<String>>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    NotImplemented,
+    Internal,
+    Unauthorized,
+    NotFound,
+    BadRequest,
+    Unsupported,
+    Duplicate,
+    InsufficientBalance,
+    SerializeError,
+    DeserializeError,
+    RateLimited,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct WalletData {
+    pub created_by: Principal,
+    pub owner: Principal,
+    pub group_id: u64,
+    pub snapshot: Option<WalletSnapshot>,
+    pub config: Option<WalletConfig>,
+    pub controllers: Option<Vec<Principal>>,
+    pub origin: Option<WalletOrigin>,
+    pub wasm_version: Option<String>,
+    pub canister_snapshot: Option<CanisterSnapshot>,
+    pub health: Option<WalletHealth>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CanisterSnapshot {
+    pub id: Vec<u8>,
+    pub taken_at_timestamp: u64,
+    pub total_size: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WalletOrigin {
+    Spawned,
+    Imported,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct WalletConfig {
+    pub threshold: u64,
+    pub name: String,
+    pub description: Option<String>,
+    pub spending_limits: Option<Vec<SpendingLimit>>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SpendingLimit {
+    pub token: Principal,
+    pub amount: candid::Nat,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct WalletSnapshot {
+    pub whitelist: Vec<Principal>,
+    pub threshold: u64,
+    pub icp_balance: Tokens,
+    pub cycles: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct WalletSyncReport {
+    pub synced: u64,
+    pub unreachable: Vec<(Principal, String)>,
+    pub next_cursor: Option<Principal>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct SpawnStatus {
+    pub min_amount_error: Option<u64>,
+    pub canister_installed: Option<Principal>,
+    pub done: Option<()>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct MultisigState {
+    pub owner: Principal,
+    pub whitelist: Vec<Principal>,
+    pub proxy: Principal,
+    pub group_id: u64,
+    pub config: Option<WalletConfig>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Page<K, V> {
+    pub items: Vec<(K, V)>,
+    pub next_cursor: Option<K>,
+    pub total: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct PageArgs<K> {
+    pub cursor: Option<K>,
+    pub limit: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct WalletFilter {
+    pub owner: Option<Principal>,
+    pub group_id: Option<u64>,
+    pub origin: Option<WalletOrigin>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct Notification {
+    pub method: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum MultisigEvent {
+    WhitelistNotice {
+        receivers: Vec<Principal>,
+        group_id: u64,
+    },
+    NewProposal {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        proposal_id: u64,
+    },
+    ProposalAccepted {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        proposal_id: u64,
+    },
+    ProposalDeclined {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        proposal_id: u64,
+    },
+    ProposalStatusUpdate {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        proposal_id: u64,
+    },
+    ThresholdChanged {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        threshold: u64,
+    },
+    OwnerTransferred {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        new_owner: Principal,
+    },
+    LowCycles {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        cycles: u64,
+    },
+    WalletSpawned {
+        receivers: Vec<Principal>,
+        group_id: u64,
+    },
+    WalletToppedUp {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        cycles: u64,
+    },
+    WalletUpgraded {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        wasm_version: String,
+    },
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct NotificationCounter {
+    pub accepted: u64,
+    pub rejected: u64,
+}
+
+/// A notification as recorded by the proxy stub
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ReceivedNotification {
+    pub wallet: Principal,
+    pub event: MultisigEvent,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct IndexEvent {
+    pub created_at: u64,
+    pub kind: IndexEventKind,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum IndexEventKind {
+    SpawnStarted {
+        icp_transfer_blockheight: u64,
+        caller: Principal,
+        group_id: u64,
+    },
+    SpawnFinished {
+        icp_transfer_blockheight: u64,
+        wallet: Principal,
+    },
+    SpawnFailed {
+        icp_transfer_blockheight: u64,
+        error: String,
+    },
+    ToppedUp {
+        icp_transfer_blockheight: u64,
+        wallet: Principal,
+        cycles: u64,
+    },
+    OwnershipTransferProposed {
+        wallet: Principal,
+        from: Principal,
+        to: Principal,
+        expires_at: u64,
+    },
+    OwnershipTransferCancelled {
+        wallet: Principal,
+        cancelled_by: Principal,
+    },
+    OwnershipTransferred {
+        wallet: Principal,
+        previous_owner: Principal,
+        new_owner: Principal,
+    },
+    OwnerRepaired {
+        wallet: Principal,
+        previous_owner: Principal,
+        owner: Principal,
+    },
+    ControllersChanged {
+        wallet: Principal,
+        caller: Principal,
+        controllers: Vec<Principal>,
+    },
+    WalletImported {
+        wallet: Principal,
+        caller: Principal,
+        group_id: u64,
+        owner: Principal,
+    },
+    WalletUpgraded {
+        wallet: Principal,
+        caller: Principal,
+        version: String,
+        snapshot_id: Vec<u8>,
+    },
+    WalletRestored {
+        wallet: Principal,
+        caller: Principal,
+        snapshot_id: Vec<u8>,
+    },
+    WalletDecommissioned {
+        wallet: Principal,
+        caller: Principal,
+        cycles_returned: u64,
+    },
+    WasmUploaded {
+        version: String,
+    },
+    WasmRegistered {
+        version: String,
+    },
+    ConfigChanged {
+        caller: Principal,
+    },
+    Refunded {
+        icp_transfer_blockheight: u64,
+        to: Principal,
+        amount: Tokens,
+        blockheight: u64,
+    },
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct OwnershipTransfer {
+    pub from: Principal,
+    pub to: Principal,
+    pub expires_at: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct OwnerMismatch {
+    pub index_owner: Principal,
+    pub wallet_owner: Principal,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct OwnerReconciliation {
+    pub mismatches: Vec<(Principal, OwnerMismatch)>,
+    pub unreachable: Vec<(Principal, String)>,
+    pub checked: u64,
+    pub next_cursor: Option<Principal>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct ArchivedWallet {
+    pub wallet: WalletData,
+    pub archived_by: Principal,
+    pub cycles_returned: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct WasmVersion {
+    pub registered_at: u64,
+    pub registered_by: Principal,
+    pub uploaded: bool,
+}
+
+#[derive(CandidType, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CanisterStatusType {
+    #[serde(rename = "running")]
+    Running,
+    #[serde(rename = "stopping")]
+    Stopping,
+    #[serde(rename = "stopped")]
+    Stopped,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct WalletHealth {
+    pub status: CanisterStatusType,
+    pub cycles: u64,
+    pub module_hash: Option<String>,
+    pub idle_cycles_burned_per_day: u64,
+    pub days_until_freezing: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FleetStatus {
+    pub total: u64,
+    pub healthy: u64,
+    pub low_cycles: u64,
+    pub outdated: u64,
+    pub stopped: u64,
+    pub unchecked: u64,
+}
@@ -9,5 +9,4 @@ ic-repl -r ic << END
 identity ${IDENTITY} "~/.config/dfx/identity/${IDENTITY}/identity.pem"
 import controller = "${CANISTER}" as "candid/wallet_index.did"
 call controller._dev_upload_multisig_wasm(file("${FILE}"))
-call controller._dev_set_proxy(principal "24swh-4iaaa-aaaap-ahevq-cai")
 END
\ No newline at end of file
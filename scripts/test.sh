@@ -0,0 +1,19 @@
+# Test script wallet_index canister
+
+POCKET_IC_VERSION="6.0.0"
+POCKET_IC_BIN="${POCKET_IC_BIN:-$(pwd)/target/pocket-ic}"
+
+# Download the PocketIC server
+if [ ! -f "${POCKET_IC_BIN}" ]; then
+    PLATFORM=$(uname | tr '[:upper:]' '[:lower:]')
+    curl -sL "https://github.com/dfinity/pocketic/releases/download/${POCKET_IC_VERSION}/pocket-ic-x86_64-${PLATFORM}.gz" -o "${POCKET_IC_BIN}.gz"
+    gunzip -f "${POCKET_IC_BIN}.gz"
+    chmod +x "${POCKET_IC_BIN}"
+fi
+
+# Build the index and the stub canisters
+cargo build --release --target wasm32-unknown-unknown \
+    -p wallet_index -p ledger_stub -p cmc_stub -p multisig_stub -p proxy_stub
+
+# Run the unit and integration tests
+POCKET_IC_BIN="${POCKET_IC_BIN}" cargo test --workspace -- --include-ignored
@@ -0,0 +1,223 @@
+use candid::Principal;
+
+use crate::{
+    logic::{event_log::EventLog, locks::Lock},
+    services::{
+        environment::{Environment, IcEnvironment},
+        management_api::{IcManagement, ManagementApi},
+    },
+    storage::{
+        multisig_storage::MultisigStorage,
+        state::MAX_CONTROLLERS,
+        storage_api::{StorageQueryable, StorageUpdateable},
+    },
+    types::{
+        error::Error, index_event::IndexEventKind, result::CanisterResult, wallet_data::WalletData,
+    },
+};
+
+/// Changes the controllers of wallets on behalf of their owner, so a wallet keeps a backup
+/// controller or leaves the index altogether
+pub struct Controllers<G = IcManagement, E = IcEnvironment> {
+    management: G,
+    env: E,
+}
+
+impl Default for Controllers {
+    fn default() -> Self {
+        Self::new(IcManagement, IcEnvironment)
+    }
+}
+
+impl<G: ManagementApi, E: Environment> Controllers<G, E> {
+    pub fn new(management: G, env: E) -> Self {
+        Self { management, env }
+    }
+
+    /// Adds controllers next to the current ones, controllers that are already set are skipped
+    pub async fn add_controllers(
+        &self,
+        canister_id: Principal,
+        controllers: Vec<Principal>,
+    ) -> CanisterResult<(Principal, WalletData)> {
+        let wallet = self.get_managed_wallet(canister_id)?;
+        let mut updated = wallet.controllers(self.env.id());
+
+        for controller in controllers {
+            if controller == Principal::anonymous() {
+                return Err(Error::bad_request()
+                    .add_method_name("add_controllers")
+                    .add_message("The anonymous principal can not be a controller"));
+            }
+
+            if !updated.contains(&controller) {
+                updated.push(controller);
+            }
+        }
+
+        if updated.len() > MAX_CONTROLLERS {
+            return Err(Error::bad_request()
+                .add_method_name("add_controllers")
+                .add_message(
+                    format!("A wallet can have at most {} controllers", MAX_CONTROLLERS).as_str(),
+                ));
+        }
+
+        self.set_controllers(canister_id, updated).await
+    }
+
+    /// Removes the index as controller, the wallet has to have another controller. The wallet
+    /// stays in the index but can no longer be upgraded or decommissioned by it.
+    pub async fn release_control(
+        &self,
+        canister_id: Principal,
+    ) -> CanisterResult<(Principal, WalletData)> {
+        let wallet = self.get_managed_wallet(canister_id)?;
+
+        let remaining: Vec<Principal> = wallet
+            .controllers(self.env.id())
+            .into_iter()
+            .filter(|controller| *controller != self.env.id())
+            .collect();
+
+        if remaining.is_empty() {
+            return Err(Error::bad_request()
+                .add_method_name("release_control")
+                .add_message("Add another controller before releasing control"));
+        }
+
+        self.set_controllers(canister_id, remaining).await
+    }
+
+    fn get_managed_wallet(&self, canister_id: Principal) -> CanisterResult<WalletData> {
+        let (_, wallet) = MultisigStorage::get(canister_id)?;
+
+        if !wallet.is_owner(self.env.caller()) {
+            return Err(Error::unauthorized().add_message("Caller is not the owner"));
+        }
+
+        if !wallet.is_managed_by(self.env.id()) {
+            return Err(Error::bad_request()
+                .add_message("The index is no longer a controller of the wallet"));
+        }
+
+        Ok(wallet)
+    }
+
+    async fn set_controllers(
+        &self,
+        canister_id: Principal,
+        controllers: Vec<Principal>,
+    ) -> CanisterResult<(Principal, WalletData)> {
+        let _lock = Lock::acquire_wallet(canister_id, self.env.time())?;
+
+        self.management
+            .set_controllers(canister_id, controllers.clone())
+            .await?;
+
+        // the wallet can change during the call, read it again so those changes are kept
+        let (_, mut wallet) = MultisigStorage::get(canister_id)?;
+        let result =
+            MultisigStorage::update(canister_id, wallet.set_controllers(controllers.clone()))?;
+
+        EventLog::record(
+            &self.env,
+            IndexEventKind::ControllersChanged {
+                wallet: canister_id,
+                caller: self.env.caller(),
+                controllers,
+            },
+        );
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use candid::Principal;
+    use futures::executor::block_on;
+
+    use super::Controllers;
+    use crate::{
+        logic::{locks::Lock, store::Store},
+        services::fakes::{principal, FakeEnvironment, FakeManagement},
+        types::{error::ErrorKind, wallet_data::WalletData},
+    };
+
+    // wallet 20 is owned by 1 and was spawned before controllers were tracked
+    fn setup(caller: Principal) -> (Controllers<FakeManagement, FakeEnvironment>, FakeManagement) {
+        Store::save_wallet(
+            principal(20),
+            WalletData::new(principal(1), 0, 0, 0, 1, None, None),
+        )
+        .unwrap();
+
+        let management = FakeManagement::default();
+        let env = FakeEnvironment {
+            caller,
+            ..Default::default()
+        };
+
+        (Controllers::new(management.clone(), env), management)
+    }
+
+    #[test]
+    fn add_controllers_keeps_the_index() {
+        let (controllers, management) = setup(principal(1));
+
+        let (_, wallet) = block_on(
+            controllers.add_controllers(principal(20), vec![principal(30), principal(100)]),
+        )
+        .unwrap();
+
+        let expected = vec![principal(100), principal(30)];
+        assert_eq!(wallet.controllers(principal(100)), expected);
+        assert_eq!(management.controllers(principal(20)), Some(expected));
+        assert!(wallet.is_managed_by(principal(100)));
+    }
+
+    #[test]
+    fn release_control_needs_another_controller() {
+        let (controllers, management) = setup(principal(1));
+
+        let err = block_on(controllers.release_control(principal(20))).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BadRequest));
+        assert_eq!(management.controllers(principal(20)), None);
+
+        block_on(controllers.add_controllers(principal(20), vec![principal(30)])).unwrap();
+        let (_, wallet) = block_on(controllers.release_control(principal(20))).unwrap();
+        assert_eq!(wallet.controllers(principal(100)), vec![principal(30)]);
+        assert!(!wallet.is_managed_by(principal(100)));
+
+        // the index can not change the controllers anymore
+        let err =
+            block_on(controllers.add_controllers(principal(20), vec![principal(31)])).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BadRequest));
+    }
+
+    #[test]
+    fn controllers_are_not_changed_while_the_wallet_is_locked() {
+        let (controllers, management) = setup(principal(1));
+
+        // an upgrade or decommission of the wallet that is still running
+        let lock = Lock::acquire_wallet(principal(20), FakeEnvironment::default().time).unwrap();
+        let err =
+            block_on(controllers.add_controllers(principal(20), vec![principal(30)])).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Duplicate));
+        assert_eq!(management.controllers(principal(20)), None);
+
+        drop(lock);
+        assert!(block_on(controllers.add_controllers(principal(20), vec![principal(30)])).is_ok());
+    }
+
+    #[test]
+    fn only_the_owner_changes_controllers() {
+        let (controllers, management) = setup(principal(2));
+
+        let err =
+            block_on(controllers.add_controllers(principal(20), vec![principal(2)])).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Unauthorized));
+        assert_eq!(management.controllers(principal(20)), None);
+    }
+}
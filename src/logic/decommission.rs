@@ -0,0 +1,440 @@
+use candid::Principal;
+use ic_cdk::api::management_canister::main::CanisterStatusType;
+use ic_ledger_types::{AccountIdentifier, DEFAULT_SUBACCOUNT};
+
+use crate::{
+    logic::{event_log::EventLog, locks::Lock},
+    services::{
+        environment::{Environment, IcEnvironment},
+        ledger_api::{IcLedger, LedgerApi},
+        management_api::{IcManagement, ManagementApi},
+        multisig_api::{IcMultisig, MultisigApi},
+    },
+    storage::{
+        archived_wallet_storage::ArchivedWalletStorage,
+        cell_api::CellStorage,
+        config_storage::ConfigStorage,
+        multisig_storage::MultisigStorage,
+        ownership_transfer_storage::OwnershipTransferStorage,
+        storage_api::{StorageInsertableByKey, StorageQueryable, StorageUpdateable},
+    },
+    types::{
+        archived_wallet::ArchivedWallet,
+        error::Error,
+        index_event::IndexEventKind,
+        page::{Page, PageArgs},
+        result::CanisterResult,
+    },
+};
+
+/// Retires wallets, the wallet returns its cycles to the index and is stopped and deleted,
+/// its data is moved to the archive
+pub struct Decommission<M = IcMultisig, G = IcManagement, L = IcLedger, E = IcEnvironment> {
+    multisig: M,
+    management: G,
+    ledger: L,
+    env: E,
+}
+
+impl Default for Decommission {
+    fn default() -> Self {
+        Self::new(IcMultisig, IcManagement, IcLedger, IcEnvironment)
+    }
+}
+
+impl Decommission {
+    pub fn get_archived_wallets(page: PageArgs<Principal>) -> Page<Principal, ArchivedWallet> {
+        let (items, next_cursor) =
+            ArchivedWalletStorage::get_page(page.cursor, page.limit(), |_, _| true);
+
+        Page {
+            items,
+            next_cursor,
+            total: ArchivedWalletStorage::len(),
+        }
+    }
+}
+
+impl<M, G, L, E> Decommission<M, G, L, E>
+where
+    M: MultisigApi,
+    G: ManagementApi,
+    L: LedgerApi,
+    E: Environment,
+{
+    pub fn new(multisig: M, management: G, ledger: L, env: E) -> Self {
+        Self {
+            multisig,
+            management,
+            ledger,
+            env,
+        }
+    }
+
+    /// Decommissions a wallet of the caller that holds no ICP and none of the tokens of its
+    /// spending limits. A wallet that was stopped by a failed attempt is started again to
+    /// return its cycles. A wallet that can not return them is only deleted when an admin
+    /// forces it, which burns its cycles.
+    pub async fn decommission_wallet(
+        &self,
+        canister_id: Principal,
+        force: bool,
+    ) -> CanisterResult<(Principal, ArchivedWallet)> {
+        let caller = self.env.caller();
+        let (_, wallet) = MultisigStorage::get(canister_id)?;
+
+        if force && !self.is_admin() {
+            return Err(Error::unauthorized()
+                .add_method_name("decommission_wallet")
+                .add_message("Only admins can force a decommission"));
+        }
+
+        if !wallet.is_owner(caller) && !force {
+            return Err(Error::unauthorized().add_message("Caller is not the owner"));
+        }
+
+        if !wallet.is_managed_by(self.env.id()) {
+            return Err(Error::bad_request()
+                .add_method_name("decommission_wallet")
+                .add_message("The index is no longer a controller of the wallet"));
+        }
+
+        // the flow spans multiple calls
+        let _lock = Lock::acquire_wallet(canister_id, self.env.time())?;
+
+        // the index can not move the funds of a wallet, they are swept with a wallet proposal
+        let balance = self
+            .ledger
+            .account_balance(AccountIdentifier::new(&canister_id, &DEFAULT_SUBACCOUNT))
+            .await?;
+
+        if balance.e8s() > 0 {
+            return Err(Error::bad_request()
+                .add_method_name("decommission_wallet")
+                .add_info(format!("balance: {} e8s", balance.e8s()).as_str())
+                .add_message(
+                    "The wallet still holds ICP, transfer it with a wallet proposal first",
+                ));
+        }
+
+        // the tokens the wallet was configured with, the index can not read other holdings
+        let mut tokens = wallet
+            .config()
+            .and_then(|config| config.spending_limits.as_ref())
+            .map(|limits| limits.iter().map(|limit| limit.token).collect::<Vec<_>>())
+            .unwrap_or_default();
+        tokens.sort();
+        tokens.dedup();
+
+        for token in tokens {
+            let balance = self.ledger.token_balance(token, canister_id).await?;
+
+            if balance > 0_u64 {
+                return Err(Error::bad_request()
+                    .add_method_name("decommission_wallet")
+                    .add_info(format!("{}: {}", token, balance).as_str())
+                    .add_message(
+                        "The wallet still holds tokens, transfer them with a wallet proposal first",
+                    ));
+            }
+        }
+
+        // a stopped or stopping wallet can not reply, it was stopped by an earlier attempt
+        let status = self.management.canister_status(canister_id).await?;
+        if status.status != CanisterStatusType::Running {
+            self.management.start_canister(canister_id).await?;
+        }
+
+        let cycles_returned = match self
+            .multisig
+            .withdraw_cycles(canister_id, self.env.id())
+            .await
+        {
+            Ok(cycles) => cycles,
+            Err(_) if force => 0,
+            Err(err) => return Err(err),
+        };
+
+        self.management.stop_canister(canister_id).await?;
+
+        self.management.delete_canister(canister_id).await?;
+
+        // the wallet can change during the calls, archive its latest data
+        let (_, wallet) = MultisigStorage::get(canister_id)?;
+        MultisigStorage::remove(canister_id)?;
+
+        if OwnershipTransferStorage::contains_key(canister_id) {
+            OwnershipTransferStorage::remove(canister_id)?;
+        }
+
+        let result = ArchivedWalletStorage::insert_by_key(
+            canister_id,
+            ArchivedWallet {
+                wallet,
+                archived_at: self.env.time(),
+                archived_by: caller,
+                cycles_returned,
+            },
+        )?;
+
+        EventLog::record(
+            &self.env,
+            IndexEventKind::WalletDecommissioned {
+                wallet: canister_id,
+                caller,
+                cycles_returned,
+            },
+        );
+
+        Ok(result)
+    }
+
+    fn is_admin(&self) -> bool {
+        ConfigStorage::get()
+            .map(|config| config.is_admin(self.env.caller()))
+            .unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use candid::Principal;
+    use futures::executor::block_on;
+    use ic_cdk::api::management_canister::main::CanisterStatusType;
+    use ic_ledger_types::Tokens;
+
+    use super::Decommission;
+    use crate::{
+        logic::store::Store,
+        services::{
+            fakes::{principal, FakeEnvironment, FakeLedger, FakeManagement, FakeMultisig},
+            management_api::ManagementApi,
+            multisig_api::WalletInfo,
+        },
+        storage::{
+            archived_wallet_storage::ArchivedWalletStorage,
+            cell_api::CellStorage,
+            config_storage::ConfigStorage,
+            multisig_storage::MultisigStorage,
+            storage_api::{StorageQueryable, StorageUpdateable},
+        },
+        types::{
+            config::{Config, UpgradeArgs},
+            error::ErrorKind,
+            page::PageArgs,
+            wallet_config::{SpendingLimit, WalletConfig},
+            wallet_data::WalletData,
+        },
+    };
+
+    type FakeDecommission = Decommission<FakeMultisig, FakeManagement, FakeLedger, FakeEnvironment>;
+
+    struct Fakes {
+        multisig: FakeMultisig,
+        management: FakeManagement,
+        ledger: FakeLedger,
+    }
+
+    // wallet 20 is owned by 1 and has 5_000 cycles
+    fn setup() -> Fakes {
+        Store::save_wallet(
+            principal(20),
+            WalletData::new(principal(1), 0, 0, 0, 1, None, None),
+        )
+        .unwrap();
+
+        let multisig = FakeMultisig::default();
+        multisig.set_wallet_info(
+            principal(20),
+            WalletInfo {
+                cycles: 5_000,
+                ..Default::default()
+            },
+        );
+
+        Fakes {
+            multisig,
+            management: FakeManagement::default(),
+            ledger: FakeLedger::default(),
+        }
+    }
+
+    fn decommission(fakes: &Fakes, caller: Principal) -> FakeDecommission {
+        Decommission::new(
+            fakes.multisig.clone(),
+            fakes.management.clone(),
+            fakes.ledger.clone(),
+            FakeEnvironment {
+                caller,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn decommission_wallet_returns_the_cycles_and_archives_the_wallet() {
+        let fakes = setup();
+
+        let (_, archived) =
+            block_on(decommission(&fakes, principal(1)).decommission_wallet(principal(20), false))
+                .unwrap();
+        assert_eq!(archived.cycles_returned, 5_000);
+        assert_eq!(archived.archived_by, principal(1));
+
+        assert_eq!(
+            fakes.multisig.withdrawals(),
+            vec![(principal(20), principal(100))]
+        );
+        assert_eq!(fakes.management.deleted(), vec![principal(20)]);
+        assert!(Store::get_wallet(principal(20)).is_err());
+        assert!(Store::get_wallets_by_owner(principal(1)).is_empty());
+
+        assert_eq!(
+            Decommission::get_archived_wallets(PageArgs::default()).total,
+            1
+        );
+        assert!(ArchivedWalletStorage::contains_key(principal(20)));
+    }
+
+    #[test]
+    fn decommission_wallet_requires_the_owner_and_an_empty_wallet() {
+        let fakes = setup();
+
+        let err =
+            block_on(decommission(&fakes, principal(2)).decommission_wallet(principal(20), false))
+                .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Unauthorized));
+
+        fakes
+            .ledger
+            .set_balance(principal(20), Tokens::from_e8s(10_000));
+        let err =
+            block_on(decommission(&fakes, principal(1)).decommission_wallet(principal(20), false))
+                .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BadRequest));
+
+        assert!(fakes.multisig.withdrawals().is_empty());
+        assert_eq!(
+            fakes.management.status(principal(20)),
+            CanisterStatusType::Running
+        );
+        assert!(Store::get_wallet(principal(20)).is_ok());
+    }
+
+    #[test]
+    fn failed_decommission_can_be_retried() {
+        let fakes = setup();
+        fakes.management.set_fail_stop(true);
+
+        assert!(block_on(
+            decommission(&fakes, principal(1)).decommission_wallet(principal(20), false)
+        )
+        .is_err());
+        assert!(Store::get_wallet(principal(20)).is_ok());
+
+        // the stop went through but its reply was lost
+        fakes.management.set_fail_stop(false);
+        block_on(fakes.management.stop_canister(principal(20))).unwrap();
+
+        // the wallet is started to send the cycles it received since, it has none left
+        let (_, archived) =
+            block_on(decommission(&fakes, principal(1)).decommission_wallet(principal(20), false))
+                .unwrap();
+        assert_eq!(archived.cycles_returned, 0);
+        assert_eq!(fakes.multisig.withdrawals().len(), 2);
+        assert_eq!(fakes.management.deleted(), vec![principal(20)]);
+    }
+
+    #[test]
+    fn a_stopped_wallet_is_started_to_return_its_cycles() {
+        let fakes = setup();
+        block_on(fakes.management.stop_canister(principal(20))).unwrap();
+
+        let (_, archived) =
+            block_on(decommission(&fakes, principal(1)).decommission_wallet(principal(20), false))
+                .unwrap();
+        assert_eq!(archived.cycles_returned, 5_000);
+        assert_eq!(
+            fakes.multisig.withdrawals(),
+            vec![(principal(20), principal(100))]
+        );
+        assert_eq!(fakes.management.deleted(), vec![principal(20)]);
+    }
+
+    #[test]
+    fn decommission_wallet_requires_the_tokens_of_the_spending_limits_to_be_moved() {
+        let fakes = setup();
+        MultisigStorage::update(
+            principal(20),
+            WalletData::new(principal(1), 0, 0, 0, 1, None, None).set_config(Some(WalletConfig {
+                threshold: 1,
+                name: "treasury".to_string(),
+                description: None,
+                spending_limits: Some(vec![SpendingLimit {
+                    token: principal(60),
+                    amount: 100_u64.into(),
+                }]),
+            })),
+        )
+        .unwrap();
+
+        fakes
+            .ledger
+            .set_token_balance(principal(60), principal(20), 5);
+        let err =
+            block_on(decommission(&fakes, principal(1)).decommission_wallet(principal(20), false))
+                .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BadRequest));
+        assert!(fakes.management.deleted().is_empty());
+
+        fakes
+            .ledger
+            .set_token_balance(principal(60), principal(20), 0);
+        assert!(block_on(
+            decommission(&fakes, principal(1)).decommission_wallet(principal(20), false)
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn a_wallet_without_withdraw_cycles_is_not_deleted() {
+        let fakes = setup();
+        fakes.multisig.remove_withdraw_cycles(principal(20));
+
+        let err =
+            block_on(decommission(&fakes, principal(1)).decommission_wallet(principal(20), false))
+                .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BadRequest));
+
+        assert!(fakes.management.deleted().is_empty());
+        assert_eq!(
+            fakes.management.status(principal(20)),
+            CanisterStatusType::Running
+        );
+        assert!(Store::get_wallet(principal(20)).is_ok());
+    }
+
+    #[test]
+    fn only_admins_force_the_delete_of_a_wallet_that_keeps_its_cycles() {
+        let fakes = setup();
+        fakes.multisig.remove_withdraw_cycles(principal(20));
+        ConfigStorage::set(Config::default().apply_upgrade_args(UpgradeArgs {
+            admins: Some(vec![principal(3)]),
+            ..Default::default()
+        }))
+        .unwrap();
+
+        let err =
+            block_on(decommission(&fakes, principal(1)).decommission_wallet(principal(20), true))
+                .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Unauthorized));
+        assert!(fakes.management.deleted().is_empty());
+
+        let (_, archived) =
+            block_on(decommission(&fakes, principal(3)).decommission_wallet(principal(20), true))
+                .unwrap();
+        assert_eq!(archived.cycles_returned, 0);
+        assert_eq!(archived.archived_by, principal(3));
+        assert_eq!(fakes.management.deleted(), vec![principal(20)]);
+    }
+}
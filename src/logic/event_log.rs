@@ -0,0 +1,61 @@
+use crate::{
+    services::environment::Environment,
+    storage::{
+        event_log_storage::EventLogStorage,
+        log_api::LogStorage,
+        state::{DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT},
+    },
+    types::index_event::{IndexEvent, IndexEventKind},
+};
+
+/// Append only log of what the index did, for off-chain indexers to follow
+pub struct EventLog;
+
+impl EventLog {
+    pub fn record<E: Environment>(env: &E, kind: IndexEventKind) {
+        // appending only fails when the stable memory can not grow, the operation that is
+        // recorded is not rolled back for that
+        let _ = EventLogStorage::append(IndexEvent::new(kind, env.time()));
+    }
+
+    /// Returns the events starting at `from_id`, poll again with the id after the last event
+    pub fn get_events(from_id: u64, limit: Option<u64>) -> Vec<(u64, IndexEvent)> {
+        let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).clamp(1, MAX_PAGE_LIMIT) as usize;
+
+        EventLogStorage::get_range(from_id, limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EventLog;
+    use crate::{services::fakes::FakeEnvironment, types::index_event::IndexEventKind};
+
+    fn wasm_uploaded(version: u64) -> IndexEventKind {
+        IndexEventKind::WasmUploaded {
+            version: version.to_string(),
+        }
+    }
+
+    #[test]
+    fn get_events_streams_the_events_by_id() {
+        for version in 0..5 {
+            EventLog::record(&FakeEnvironment::default(), wasm_uploaded(version));
+        }
+
+        let events = EventLog::get_events(1, Some(2));
+        assert!(events
+            .iter()
+            .all(|(_, event)| event.created_at() == FakeEnvironment::default().time));
+        assert_eq!(
+            events
+                .iter()
+                .map(|(id, event)| (*id, event.kind().clone()))
+                .collect::<Vec<_>>(),
+            vec![(1, wasm_uploaded(1)), (2, wasm_uploaded(2))]
+        );
+
+        assert_eq!(EventLog::get_events(3, None).len(), 2);
+        assert!(EventLog::get_events(5, None).is_empty());
+    }
+}
@@ -0,0 +1,145 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use candid::Principal;
+
+use crate::{
+    services::{
+        environment::{Environment, IcEnvironment},
+        proxy_api::{GroupMember, IcProxy, ProxyApi},
+    },
+    storage::state::{GROUP_MANAGER_ROLES, GROUP_MEMBER_CACHE_TTL},
+    types::{error::Error, result::CanisterResult},
+};
+
+// a proxy lookup and the time it expires
+type CachedGroupMember = (Option<GroupMember>, u64);
+
+thread_local! {
+    // kept on the heap so an upgrade starts with an empty cache
+    static GROUP_MEMBER_CACHE: RefCell<HashMap<(u64, Principal), CachedGroupMember>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Checks the roles of a principal in a group with the proxy canister
+pub struct GroupAccess<P = IcProxy, E = IcEnvironment> {
+    proxy: P,
+    env: E,
+}
+
+impl Default for GroupAccess {
+    fn default() -> Self {
+        Self::new(IcProxy, IcEnvironment)
+    }
+}
+
+impl<P: ProxyApi, E: Environment> GroupAccess<P, E> {
+    pub fn new(proxy: P, env: E) -> Self {
+        Self { proxy, env }
+    }
+
+    /// Checks that the principal has the owner or admin role in the group
+    pub async fn validate_group_manager(
+        &self,
+        group_id: u64,
+        principal: Principal,
+    ) -> CanisterResult<()> {
+        let is_manager = self
+            .get_group_member(group_id, principal)
+            .await?
+            .map(|member| {
+                member
+                    .roles
+                    .iter()
+                    .any(|role| GROUP_MANAGER_ROLES.contains(&role.as_str()))
+            })
+            .unwrap_or(false);
+
+        match is_manager {
+            true => Ok(()),
+            false => Err(Error::unauthorized()
+                .add_method_name("validate_group_manager")
+                .add_info(format!("group_id: {}", group_id).as_str())
+                .add_info(format!("required role: {}", GROUP_MANAGER_ROLES.join(" or ")).as_str())
+                .add_message("Caller is not an owner or admin of the group")),
+        }
+    }
+
+    async fn get_group_member(
+        &self,
+        group_id: u64,
+        principal: Principal,
+    ) -> CanisterResult<Option<GroupMember>> {
+        let now = self.env.time();
+
+        let cached = GROUP_MEMBER_CACHE.with(|cache| {
+            cache
+                .borrow()
+                .get(&(group_id, principal))
+                .filter(|(_, expires_at)| *expires_at > now)
+                .map(|(member, _)| member.clone())
+        });
+
+        if let Some(member) = cached {
+            return Ok(member);
+        }
+
+        let member = self.proxy.get_group_member(group_id, principal).await?;
+
+        GROUP_MEMBER_CACHE.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            cache.retain(|_, (_, expires_at)| *expires_at > now);
+            cache.insert(
+                (group_id, principal),
+                (member.clone(), now + GROUP_MEMBER_CACHE_TTL),
+            );
+        });
+
+        Ok(member)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::GroupAccess;
+    use crate::{
+        services::fakes::{principal, FakeEnvironment, FakeProxy},
+        storage::state::GROUP_MEMBER_CACHE_TTL,
+        types::error::ErrorKind,
+    };
+
+    #[test]
+    fn validate_group_manager_requires_an_owner_or_admin_role() {
+        let proxy = FakeProxy::default();
+        proxy.add_member(1, principal(1), &["owner"]);
+        proxy.add_member(1, principal(2), &["member", "admin"]);
+        proxy.add_member(1, principal(3), &["member"]);
+        let access = GroupAccess::new(proxy, FakeEnvironment::default());
+
+        assert!(block_on(access.validate_group_manager(1, principal(1))).is_ok());
+        assert!(block_on(access.validate_group_manager(1, principal(2))).is_ok());
+
+        for (group_id, caller) in [(1, principal(3)), (1, principal(4)), (2, principal(1))] {
+            let err = block_on(access.validate_group_manager(group_id, caller)).unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::Unauthorized));
+        }
+    }
+
+    #[test]
+    fn group_members_are_cached_until_the_ttl_expires() {
+        let proxy = FakeProxy::default();
+        proxy.add_member(1, principal(1), &["owner"]);
+        let mut env = FakeEnvironment::default();
+
+        let access = GroupAccess::new(proxy.clone(), env.clone());
+        assert!(block_on(access.validate_group_manager(1, principal(1))).is_ok());
+
+        proxy.remove_member(1, principal(1));
+        assert!(block_on(access.validate_group_manager(1, principal(1))).is_ok());
+
+        env.time += GROUP_MEMBER_CACHE_TTL;
+        let access = GroupAccess::new(proxy, env);
+        assert!(block_on(access.validate_group_manager(1, principal(1))).is_err());
+    }
+}
@@ -1,7 +1,10 @@
 use candid::Principal;
 use ic_cdk::caller;
 
-use crate::types::error::Error;
+use crate::{
+    storage::{cell_api::CellStorage, config_storage::ConfigStorage},
+    types::error::Error,
+};
 
 use super::store::Store;
 
@@ -14,11 +17,12 @@ pub fn is_not_anonymous() -> Result<(), String> {
     }
 }
 
-pub fn is_prod_developer() -> Result<(), String> {
-    let dev =
-        Principal::from_text("ledm3-52ncq-rffuv-6ed44-hg5uo-iicyu-pwkzj-syfva-heo4k-p7itq-aqe")
-            .unwrap();
-    match caller() == dev {
+pub fn is_admin() -> Result<(), String> {
+    let is_admin = ConfigStorage::get()
+        .map(|config| config.is_admin(caller()))
+        .unwrap_or(false);
+
+    match is_admin {
         true => Ok(()),
         false => Err(Error::unauthorized()
             .add_message("unknown caller")
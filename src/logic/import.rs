@@ -0,0 +1,320 @@
+use candid::Principal;
+
+use crate::{
+    logic::{event_log::EventLog, group_access::GroupAccess, store::Store},
+    services::{
+        environment::{Environment, IcEnvironment},
+        management_api::{IcManagement, ManagementApi},
+        multisig_api::{IcMultisig, MultisigApi},
+        proxy_api::{IcProxy, ProxyApi},
+    },
+    storage::{
+        cell_api::CellStorage, config_storage::ConfigStorage, multisig_storage::MultisigStorage,
+        multisig_wasm_storage::MultisigWasmStorage, storage_api::StorageQueryable,
+        wasm_version_storage::WasmVersionStorage,
+    },
+    types::{
+        error::Error,
+        index_event::IndexEventKind,
+        result::CanisterResult,
+        wallet_data::{WalletData, WalletOrigin},
+    },
+};
+
+/// Registers wallets that were not spawned by the index, such as wallets deployed by hand or
+/// left over from an older index. The group and owner are read from the wallet itself.
+pub struct Import<G = IcManagement, M = IcMultisig, P = IcProxy, E = IcEnvironment> {
+    management: G,
+    multisig: M,
+    env: E,
+    access: GroupAccess<P, E>,
+}
+
+impl Default for Import {
+    fn default() -> Self {
+        Self::new(
+            IcManagement,
+            IcMultisig,
+            IcEnvironment,
+            GroupAccess::default(),
+        )
+    }
+}
+
+impl<G: ManagementApi, M: MultisigApi, P: ProxyApi, E: Environment> Import<G, M, P, E> {
+    pub fn new(management: G, multisig: M, env: E, access: GroupAccess<P, E>) -> Self {
+        Self {
+            management,
+            multisig,
+            env,
+            access,
+        }
+    }
+
+    /// Imports a wallet the index controls and that runs a registered wasm version, allowed
+    /// for the owner of the wallet when they manage its group, and for the admins
+    pub async fn import_wallet(
+        &self,
+        canister_id: Principal,
+        label: Option<String>,
+    ) -> CanisterResult<(Principal, WalletData)> {
+        let caller = self.env.caller();
+
+        if MultisigStorage::contains_key(canister_id) {
+            return Err(Error::duplicate()
+                .add_method_name("import_wallet")
+                .add_message(format!("Wallet {} is already indexed", canister_id).as_str()));
+        }
+
+        // only controllers can read the status, the call fails for other canisters
+        let status = self
+            .management
+            .canister_status(canister_id)
+            .await
+            .map_err(|err| {
+                Error::bad_request()
+                    .add_method_name("import_wallet")
+                    .add_info(err.to_string().as_str())
+                    .add_message("The index is not a controller of the wallet")
+            })?;
+
+        if !status.settings.controllers.contains(&self.env.id()) {
+            return Err(Error::bad_request()
+                .add_method_name("import_wallet")
+                .add_message("The index is not a controller of the wallet"));
+        }
+
+        let version = status
+            .module_hash
+            .map(|hash| MultisigWasmStorage::version_of(&hash))
+            .filter(|version| WasmVersionStorage::contains_key(version.clone()))
+            .ok_or_else(|| {
+                Error::bad_request()
+                    .add_method_name("import_wallet")
+                    .add_message("The wallet does not run a registered wasm version")
+            })?;
+
+        let owner = self.multisig.get_owner(canister_id).await?;
+        let info = self.multisig.get_wallet_info(canister_id).await?;
+
+        let is_admin = ConfigStorage::get()
+            .map(|config| config.is_admin(caller))
+            .unwrap_or(false);
+
+        if owner != caller && !is_admin {
+            return Err(Error::unauthorized()
+                .add_method_name("import_wallet")
+                .add_message("Caller is not the owner of the wallet or an admin"));
+        }
+
+        if owner == Principal::anonymous() {
+            return Err(Error::bad_request()
+                .add_method_name("import_wallet")
+                .add_message("The wallet has no owner"));
+        }
+
+        let group_id = info.group_id.ok_or_else(|| {
+            Error::bad_request()
+                .add_method_name("import_wallet")
+                .add_message("The wallet does not report a group")
+        })?;
+
+        // the group is reported by the wallet, the owner has to run it to take its slot
+        if !is_admin {
+            self.access.validate_group_manager(group_id, caller).await?;
+        }
+
+        // the index can change during the calls, the policy is checked against its latest state
+        Store::validate_wallet_policy(group_id, label.as_ref())?;
+
+        let wallet = WalletData::new(
+            caller,
+            self.env.time(),
+            0,
+            0,
+            group_id,
+            Some(version),
+            label,
+        )
+        .set_owner(owner)
+        .set_whitelist(info.whitelist)
+        .set_controllers(status.settings.controllers)
+        .set_origin(WalletOrigin::Imported);
+
+        let result = Store::save_wallet(canister_id, wallet)?;
+
+        EventLog::record(
+            &self.env,
+            IndexEventKind::WalletImported {
+                wallet: canister_id,
+                caller,
+                group_id,
+                owner,
+            },
+        );
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use candid::Principal;
+    use futures::executor::block_on;
+
+    use super::Import;
+    use crate::{
+        logic::{group_access::GroupAccess, store::Store},
+        services::{
+            fakes::{principal, FakeEnvironment, FakeManagement, FakeMultisig, FakeProxy},
+            multisig_api::WalletInfo,
+        },
+        storage::{storage_api::StorageUpdateable, wasm_version_storage::WasmVersionStorage},
+        types::{
+            error::ErrorKind,
+            page::PageArgs,
+            wallet_data::{WalletFilter, WalletOrigin},
+            wasm_version::WasmVersion,
+        },
+    };
+
+    type FakeImport = Import<FakeManagement, FakeMultisig, FakeProxy, FakeEnvironment>;
+
+    struct Fakes {
+        management: FakeManagement,
+        multisig: FakeMultisig,
+        proxy: FakeProxy,
+    }
+
+    // wallet 20 of group 7 is owned by 1, who owns the group, controlled by the index and runs
+    // a registered version
+    fn setup() -> Fakes {
+        WasmVersionStorage::upsert(
+            "07".repeat(32),
+            WasmVersion {
+                registered_at: 0,
+                registered_by: principal(1),
+                uploaded: false,
+            },
+        )
+        .unwrap();
+
+        let fakes = Fakes {
+            management: FakeManagement::default(),
+            multisig: FakeMultisig::default(),
+            proxy: FakeProxy::default(),
+        };
+
+        fakes.management.add_canister(
+            principal(20),
+            vec![principal(100), principal(1)],
+            vec![7; 32],
+        );
+        fakes.multisig.set_wallet_owner(principal(20), principal(1));
+        fakes.multisig.set_wallet_info(
+            principal(20),
+            WalletInfo {
+                whitelist: vec![principal(1), principal(2)],
+                threshold: 2,
+                group_id: Some(7),
+                ..Default::default()
+            },
+        );
+        fakes.proxy.add_member(7, principal(1), &["owner"]);
+        fakes
+    }
+
+    fn import(fakes: &Fakes, caller: Principal) -> FakeImport {
+        let env = FakeEnvironment {
+            caller,
+            ..Default::default()
+        };
+
+        Import::new(
+            fakes.management.clone(),
+            fakes.multisig.clone(),
+            env.clone(),
+            GroupAccess::new(fakes.proxy.clone(), env),
+        )
+    }
+
+    #[test]
+    fn import_wallet_reads_the_owner_and_group_from_the_wallet() {
+        let fakes = setup();
+
+        let (_, wallet) =
+            block_on(import(&fakes, principal(1)).import_wallet(principal(20), None)).unwrap();
+
+        assert_eq!(wallet.origin(), WalletOrigin::Imported);
+        assert!(wallet.is_owner(principal(1)));
+        assert_eq!(wallet.group_id(), 7);
+        assert_eq!(wallet.members(), vec![principal(1), principal(2)]);
+        assert_eq!(
+            wallet.controllers(principal(100)),
+            vec![principal(100), principal(1)]
+        );
+
+        let imported = WalletFilter {
+            origin: Some(WalletOrigin::Imported),
+            ..Default::default()
+        };
+        assert_eq!(Store::get_wallets(PageArgs::default(), imported).total, 1);
+
+        let err =
+            block_on(import(&fakes, principal(1)).import_wallet(principal(20), None)).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Duplicate));
+    }
+
+    #[test]
+    fn import_wallet_requires_control_and_a_registered_version() {
+        let fakes = setup();
+
+        fakes
+            .management
+            .add_canister(principal(20), vec![principal(1)], vec![7; 32]);
+        let err =
+            block_on(import(&fakes, principal(1)).import_wallet(principal(20), None)).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BadRequest));
+
+        fakes
+            .management
+            .add_canister(principal(20), vec![principal(100)], vec![8; 32]);
+        let err =
+            block_on(import(&fakes, principal(1)).import_wallet(principal(20), None)).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BadRequest));
+
+        assert!(Store::get_wallet(principal(20)).is_err());
+    }
+
+    #[test]
+    fn only_the_wallet_owner_or_an_admin_imports() {
+        let fakes = setup();
+
+        let err =
+            block_on(import(&fakes, principal(2)).import_wallet(principal(20), None)).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Unauthorized));
+        assert!(Store::get_wallet(principal(20)).is_err());
+    }
+
+    #[test]
+    fn the_owner_has_to_manage_the_group_the_wallet_reports() {
+        let fakes = setup();
+
+        // the owner of the wallet is only a member of group 8
+        fakes.proxy.add_member(8, principal(1), &["member"]);
+        fakes.multisig.set_wallet_info(
+            principal(20),
+            WalletInfo {
+                whitelist: vec![principal(1)],
+                threshold: 1,
+                group_id: Some(8),
+                ..Default::default()
+            },
+        );
+
+        let err =
+            block_on(import(&fakes, principal(1)).import_wallet(principal(20), None)).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Unauthorized));
+        assert!(Store::get_wallet(principal(20)).is_err());
+    }
+}
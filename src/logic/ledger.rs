@@ -1,73 +1,193 @@
 use candid::Principal;
-use ic_cdk::{caller, id};
 use ic_ledger_types::{
-    query_archived_blocks, query_blocks, transfer, AccountIdentifier, Block, BlockIndex,
-    GetBlocksArgs, Memo, Subaccount, Tokens, TransferArgs, DEFAULT_SUBACCOUNT,
-    MAINNET_CYCLES_MINTING_CANISTER_ID, MAINNET_LEDGER_CANISTER_ID,
+    AccountIdentifier, Block, BlockIndex, GetBlocksArgs, Memo, Subaccount, Tokens, TransferError,
+    DEFAULT_SUBACCOUNT,
 };
 
 use crate::{
-    storage::state::{CATALYZE_E8S_FEE, ICP_TRANSACTION_FEE, MEMO_TOP_UP_CANISTER},
-    types::{error::Error, result::CanisterResult},
+    services::{
+        environment::{Environment, IcEnvironment},
+        ledger_api::{IcLedger, LedgerApi},
+    },
+    storage::{
+        cell_api::CellStorage,
+        config_storage::ConfigStorage,
+        ledger_transfer_storage::LedgerTransferStorage,
+        state::{ICP_TRANSACTION_FEE, MEMO_TOP_UP_CANISTER},
+        storage_api::{StorageInsertableByKey, StorageQueryable, StorageUpdateable},
+    },
+    types::{
+        error::Error,
+        ledger_transfer::{LedgerTransfer, TransferStep},
+        result::CanisterResult,
+    },
 };
 
-pub struct Ledger;
+pub struct Ledger<L = IcLedger, E = IcEnvironment> {
+    api: L,
+    env: E,
+}
+
+impl Default for Ledger {
+    fn default() -> Self {
+        Self::new(IcLedger, IcEnvironment)
+    }
+}
 
 impl Ledger {
-    pub async fn transfer_icp_back_to_caller(amount: Tokens) -> CanisterResult<u64> {
-        let send_back_amount = amount - ICP_TRANSACTION_FEE;
-
-        let transfer_back_args = TransferArgs {
-            memo: Memo(0),
-            amount: send_back_amount,
-            fee: ICP_TRANSACTION_FEE,
-            from_subaccount: None,
-            to: AccountIdentifier::new(&caller(), &DEFAULT_SUBACCOUNT),
-            created_at_time: None,
-        };
+    /// A resumed spawn or top up keeps the branch it took before, a transfer that was stored
+    /// for the step decides it
+    pub fn has_transfer(icp_transfer_blockheight: u64, step: TransferStep) -> bool {
+        LedgerTransferStorage::contains_key(step.key(icp_transfer_blockheight))
+    }
 
-        let blockheight = transfer(MAINNET_LEDGER_CANISTER_ID, transfer_back_args)
-            .await
-            .map_err(|e| Error::internal().add_message(e.1.as_str()))?
-            .map_err(|e| Error::bad_request().add_message(e.to_string().as_str()))?;
+    pub fn get_transfers(icp_transfer_blockheight: u64) -> Vec<LedgerTransfer> {
+        LedgerTransferStorage::range(
+            (icp_transfer_blockheight, 0)..=(icp_transfer_blockheight, u8::MAX),
+        )
+        .into_iter()
+        .map(|(_, transfer)| transfer)
+        .collect()
+    }
+}
 
-        Ok(blockheight)
+impl<L: LedgerApi, E: Environment> Ledger<L, E> {
+    pub fn new(api: L, env: E) -> Self {
+        Self { api, env }
+    }
+
+    pub async fn transfer_icp_back_to_caller(
+        &self,
+        icp_transfer_blockheight: u64,
+        amount: Tokens,
+    ) -> CanisterResult<u64> {
+        if amount <= ICP_TRANSACTION_FEE {
+            return Err(Error::insufficient_balance()
+                .add_method_name("transfer_icp_back_to_caller")
+                .add_message("The amount does not cover the transaction fee of a refund"));
+        }
+
+        let transfer = LedgerTransfer::new(
+            icp_transfer_blockheight,
+            TransferStep::Refund,
+            Memo(icp_transfer_blockheight),
+            self.env.time(),
+            amount - ICP_TRANSACTION_FEE,
+            AccountIdentifier::new(&self.env.caller(), &DEFAULT_SUBACCOUNT),
+        );
+
+        self.transfer_once(transfer).await
     }
 
     pub async fn transfer_icp_to_cmc(
+        &self,
+        icp_transfer_blockheight: u64,
         amount: Tokens,
         canister_id: Principal,
     ) -> CanisterResult<u64> {
-        let catalyze_amount = CATALYZE_E8S_FEE - ICP_TRANSACTION_FEE;
-        let wallet_amount = amount - ICP_TRANSACTION_FEE - catalyze_amount;
-
-        let multig_spinup_ledger_args = TransferArgs {
-            memo: MEMO_TOP_UP_CANISTER,
-            amount: wallet_amount,
-            fee: ICP_TRANSACTION_FEE,
-            from_subaccount: None,
-            to: AccountIdentifier::new(
-                &MAINNET_CYCLES_MINTING_CANISTER_ID,
+        // the catalyze fee includes the fee of this transfer
+        let catalyze_fee = ConfigStorage::get()?.catalyze_fee();
+        if amount <= catalyze_fee {
+            return Err(Error::insufficient_balance()
+                .add_method_name("transfer_icp_to_cmc")
+                .add_message("The amount does not cover the catalyze fee"));
+        }
+
+        let wallet_amount = amount - catalyze_fee;
+
+        // the cycles minting canister only accepts top ups with this memo
+        let transfer = LedgerTransfer::new(
+            icp_transfer_blockheight,
+            TransferStep::CyclesManagement,
+            MEMO_TOP_UP_CANISTER,
+            self.env.time(),
+            wallet_amount,
+            AccountIdentifier::new(
+                &ConfigStorage::get()?.cmc_canister_id(),
                 &Subaccount::from(canister_id),
             ),
-            created_at_time: None,
+        );
+
+        self.transfer_once(transfer).await
+    }
+
+    /// Transfers the catalyze fee that was kept back from `transfer_icp_to_cmc` to the
+    /// configured treasury, returns `None` if no treasury is configured
+    pub async fn transfer_fee_to_treasury(
+        &self,
+        icp_transfer_blockheight: u64,
+    ) -> CanisterResult<Option<u64>> {
+        let config = ConfigStorage::get()?;
+
+        let treasury = match config.treasury() {
+            Some(treasury) => treasury,
+            None => return Ok(None),
         };
 
-        let blockheight = transfer(MAINNET_LEDGER_CANISTER_ID, multig_spinup_ledger_args)
-            .await
-            .map_err(|e| Error::internal().add_message(e.1.as_str()))?
-            .map_err(|e| Error::bad_request().add_message(e.to_string().as_str()))?;
+        let transfer = LedgerTransfer::new(
+            icp_transfer_blockheight,
+            TransferStep::Fee,
+            Memo(icp_transfer_blockheight),
+            self.env.time(),
+            config.catalyze_fee() - ICP_TRANSACTION_FEE - ICP_TRANSACTION_FEE,
+            AccountIdentifier::new(&treasury, &DEFAULT_SUBACCOUNT),
+        );
+
+        self.transfer_once(transfer).await.map(Some)
+    }
+
+    // Sends the transfer at most once, a transfer that was already stored for the same
+    // blockheight and step is resent with its original arguments so the ledger deduplicates it
+    async fn transfer_once(&self, new_transfer: LedgerTransfer) -> CanisterResult<u64> {
+        let key = new_transfer.key();
 
+        let (_, mut pending) = match LedgerTransferStorage::get_opt(key) {
+            Some(existing) => existing,
+            None => LedgerTransferStorage::insert_by_key(key, new_transfer)?,
+        };
+
+        if let Some(blockheight) = pending.blockheight() {
+            return Ok(blockheight);
+        }
+
+        let result = self.api.transfer(pending.to_transfer_args()).await?;
+
+        let blockheight = match result {
+            Ok(blockheight) => blockheight,
+            Err(TransferError::TxDuplicate { duplicate_of }) => duplicate_of,
+            // the ledger only deduplicates for 24 hours, sending the transfer with a new time
+            // could pay twice, so it is left for an admin to reconcile
+            Err(TransferError::TxTooOld {
+                allowed_window_nanos,
+            }) => {
+                return Err(Error::bad_request()
+                    .add_method_name("transfer_once")
+                    .add_info(format!("Allowed window: {} ns", allowed_window_nanos).as_str())
+                    .add_message(
+                        format!(
+                            "The {:?} transfer for blockheight {} is too old to be deduplicated by the ledger",
+                            pending.step(),
+                            pending.origin_blockheight()
+                        )
+                        .as_str(),
+                    ))
+            }
+            Err(e) => return Err(Error::bad_request().add_message(e.to_string().as_str())),
+        };
+
+        LedgerTransferStorage::update(key, pending.set_blockheight(blockheight))?;
         Ok(blockheight)
     }
 
     // This method checks if the transaction is send and received from the given principal
     pub async fn validate_transaction(
+        &self,
         principal: Principal,
         block_index: BlockIndex,
     ) -> CanisterResult<Tokens> {
         // Get the block
-        let block = Self::get_block(block_index)
+        let block = self
+            .get_block(block_index)
             .await
             .ok_or(Error::not_found().add_message("Block not found"))?;
 
@@ -84,7 +204,7 @@ impl Ledger {
                         return Err(Error::bad_request()
                             .add_message("Transaction not from the given principal"));
                     }
-                    if to != Self::principal_to_account_identifier(id()) {
+                    if to != Self::principal_to_account_identifier(self.env.id()) {
                         return Err(Error::bad_request()
                             .add_message("Transaction not to the given principal"));
                     }
@@ -96,15 +216,13 @@ impl Ledger {
         }
     }
 
-    async fn get_block(block_index: BlockIndex) -> Option<Block> {
+    async fn get_block(&self, block_index: BlockIndex) -> Option<Block> {
         let args = GetBlocksArgs {
             start: block_index,
             length: 1,
         };
 
-        let blocks_result = query_blocks(MAINNET_LEDGER_CANISTER_ID, args.clone())
-            .await
-            .ok()?;
+        let blocks_result = self.api.query_blocks(args.clone()).await.ok()?;
 
         if !blocks_result.blocks.is_empty() {
             debug_assert_eq!(blocks_result.first_block_index, block_index);
@@ -114,11 +232,15 @@ impl Ledger {
         if let Some(func) = blocks_result.archived_blocks.into_iter().find_map(|b| {
             (b.start <= block_index && (block_index - b.start) < b.length).then_some(b.callback)
         }) {
-            query_archived_blocks(&func, args)
+            return self
+                .api
+                .query_archived_blocks(&func, args)
                 .await
                 .ok()?
-                .map_err(|e| e.to_string())
-                .ok();
+                .ok()?
+                .blocks
+                .into_iter()
+                .next();
         }
 
         None
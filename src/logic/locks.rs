@@ -0,0 +1,95 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use candid::Principal;
+
+use crate::{
+    storage::state::LOCK_TTL,
+    types::{error::Error, result::CanisterResult},
+};
+
+thread_local! {
+    // operations that span multiple calls with the time their lock expires. A lock is released
+    // when its guard is dropped, which does not happen when a callback traps after an await,
+    // so an expired lock is taken over by the next call
+    static LOCKS: RefCell<HashMap<LockKey, u64>> = RefCell::new(HashMap::new());
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LockKey {
+    SpawnGroup(u64),
+    SpawnBlockheight(u64),
+    NotificationDelivery,
+    WalletSync,
+    /// Upgrades, snapshots, controller changes and the decommission of a wallet
+    Wallet(Principal),
+}
+
+/// Guard of a lock, the lock is released when the guard is dropped
+pub struct Lock(LockKey);
+
+impl Lock {
+    /// Takes the lock, returns `None` while another call holds it and it has not expired
+    pub fn acquire(key: LockKey, now: u64) -> Option<Self> {
+        LOCKS.with(|locks| {
+            let mut locks = locks.borrow_mut();
+
+            match locks.get(&key) {
+                Some(expires_at) if *expires_at > now => None,
+                _ => {
+                    locks.insert(key, now + LOCK_TTL);
+                    Some(Self(key))
+                }
+            }
+        })
+    }
+
+    /// Takes the lock of a wallet, the calls that change its code or controllers or delete it
+    /// span multiple calls and one of them runs per wallet at a time
+    pub fn acquire_wallet(canister_id: Principal, now: u64) -> CanisterResult<Self> {
+        Self::acquire(LockKey::Wallet(canister_id), now).ok_or_else(|| {
+            Error::duplicate().add_message(
+                format!(
+                    "Wallet {} is already being upgraded, restored, decommissioned or given other controllers",
+                    canister_id
+                )
+                .as_str(),
+            )
+        })
+    }
+
+    /// Releases every lock, used after an upgrade as no call survives it
+    pub fn clear_all() {
+        LOCKS.with(|locks| locks.borrow_mut().clear());
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        LOCKS.with(|locks| locks.borrow_mut().remove(&self.0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Lock, LockKey};
+    use crate::storage::state::LOCK_TTL;
+
+    #[test]
+    fn a_lock_is_held_until_it_is_dropped_or_expires() {
+        let lock = Lock::acquire(LockKey::SpawnGroup(1), 0).unwrap();
+        assert!(Lock::acquire(LockKey::SpawnGroup(1), 1).is_none());
+        assert!(Lock::acquire(LockKey::SpawnGroup(2), 1).is_some());
+
+        drop(lock);
+        let leaked = Lock::acquire(LockKey::SpawnGroup(1), 2).unwrap();
+
+        // a guard that is never dropped, like one of a trapped callback
+        std::mem::forget(leaked);
+        assert!(Lock::acquire(LockKey::SpawnGroup(1), 2 + LOCK_TTL - 1).is_none());
+        assert!(Lock::acquire(LockKey::SpawnGroup(1), 2 + LOCK_TTL).is_some());
+
+        std::mem::forget(Lock::acquire(LockKey::SpawnGroup(3), 0).unwrap());
+        Lock::clear_all();
+        assert!(Lock::acquire(LockKey::SpawnGroup(3), 0).is_some());
+    }
+}
@@ -1,5 +1,20 @@
 pub mod cmc;
+pub mod controllers;
+pub mod decommission;
+pub mod event_log;
+pub mod group_access;
 pub mod guards;
+pub mod import;
 pub mod ledger;
+pub mod locks;
+pub mod notification_limits;
+pub mod notification_outbox;
+pub mod ownership;
 pub mod proxy_notifications;
+pub mod reconciliation;
+pub mod setup;
+pub mod spawn;
 pub mod store;
+pub mod wallet_health;
+pub mod wallet_sync;
+pub mod wallet_upgrade;
@@ -0,0 +1,244 @@
+use candid::Principal;
+
+use crate::{
+    logic::store::Store,
+    services::environment::{Environment, IcEnvironment},
+    storage::{
+        notification_storage::NotificationCounterStorage,
+        state::{MAX_NOTIFICATION_RECEIVERS, NOTIFICATION_RATE_LIMIT},
+        storage_api::{StorageQueryable, StorageUpdateable},
+    },
+    types::{
+        error::Error,
+        multisig_event::MultisigEvent,
+        notification_counter::NotificationCounter,
+        page::{Page, PageArgs},
+        result::CanisterResult,
+        wallet_data::WalletData,
+    },
+};
+
+/// Checks the notifications that wallets send before they are relayed to the proxy
+pub struct NotificationLimits<E = IcEnvironment> {
+    env: E,
+}
+
+impl Default for NotificationLimits {
+    fn default() -> Self {
+        Self::new(IcEnvironment)
+    }
+}
+
+impl NotificationLimits {
+    pub fn get_counters(page: PageArgs<Principal>) -> Page<Principal, NotificationCounter> {
+        let (items, next_cursor) =
+            NotificationCounterStorage::get_page(page.cursor, page.limit(), |_, _| true);
+
+        Page {
+            items,
+            next_cursor,
+            total: NotificationCounterStorage::len(),
+        }
+    }
+}
+
+impl<E: Environment> NotificationLimits<E> {
+    pub fn new(env: E) -> Self {
+        Self { env }
+    }
+
+    /// Validates the event against the wallet and counts it, rejected events are counted as well
+    pub fn check(&self, wallet: Principal, event: &MultisigEvent) -> CanisterResult<()> {
+        let (_, wallet_data) = Store::get_wallet(wallet)?;
+        let mut counter = NotificationCounterStorage::get_opt(wallet)
+            .map(|(_, counter)| counter)
+            .unwrap_or_default();
+
+        let result = Self::validate_event(&wallet_data, event).and_then(|_| {
+            match counter.try_accept(self.env.time()) {
+                true => Ok(()),
+                false => Err(Error::rate_limited()
+                    .add_method_name("check")
+                    .add_info(format!("limit: {}", NOTIFICATION_RATE_LIMIT).as_str())
+                    .add_message("Too many notifications from this wallet")),
+            }
+        });
+
+        if result.is_err() {
+            counter.reject();
+        }
+
+        NotificationCounterStorage::upsert(wallet, counter)?;
+        result
+    }
+
+    fn validate_event(wallet: &WalletData, event: &MultisigEvent) -> CanisterResult<()> {
+        if event.is_index_event() {
+            return Err(Error::bad_request()
+                .add_method_name("validate_event")
+                .add_info(format!("event: {:?}", event).as_str())
+                .add_message("Only the index sends this event"));
+        }
+
+        if event.group_id() != wallet.group_id() {
+            return Err(Error::unauthorized()
+                .add_method_name("validate_event")
+                .add_info(format!("group_id: {}", event.group_id()).as_str())
+                .add_info(format!("wallet group_id: {}", wallet.group_id()).as_str())
+                .add_message("The group does not match the group of the wallet"));
+        }
+
+        if event.receivers().len() > MAX_NOTIFICATION_RECEIVERS {
+            return Err(Error::bad_request()
+                .add_method_name("validate_event")
+                .add_info(format!("receivers: {}", event.receivers().len()).as_str())
+                .add_info(format!("maximum: {}", MAX_NOTIFICATION_RECEIVERS).as_str())
+                .add_message("Too many receivers"));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use candid::Principal;
+
+    use super::NotificationLimits;
+    use crate::{
+        logic::store::Store,
+        services::fakes::{principal, FakeEnvironment},
+        storage::{
+            notification_storage::NotificationCounterStorage,
+            state::{
+                MAX_NOTIFICATION_RECEIVERS, NOTIFICATION_RATE_LIMIT, NOTIFICATION_RATE_WINDOW,
+            },
+            storage_api::StorageQueryable,
+        },
+        types::{error::ErrorKind, multisig_event::MultisigEvent, wallet_data::WalletData},
+    };
+
+    fn limits(time: u64) -> NotificationLimits<FakeEnvironment> {
+        NotificationLimits::new(FakeEnvironment {
+            time,
+            ..Default::default()
+        })
+    }
+
+    fn add_wallet() -> Principal {
+        let wallet = principal(20);
+        Store::save_wallet(
+            wallet,
+            WalletData::new(principal(1), 0, 0, 0, 7, None, None),
+        )
+        .unwrap();
+        wallet
+    }
+
+    fn new_proposal(group_id: u64, receivers: usize) -> MultisigEvent {
+        MultisigEvent::NewProposal {
+            receivers: vec![principal(2); receivers],
+            group_id,
+            proposal_id: 1,
+        }
+    }
+
+    #[test]
+    fn check_accepts_an_event_for_the_group_of_the_wallet() {
+        let wallet = add_wallet();
+
+        assert!(limits(0).check(wallet, &new_proposal(7, 2)).is_ok());
+
+        let (_, counter) = NotificationCounterStorage::get(wallet).unwrap();
+        assert_eq!(counter.accepted(), 1);
+        assert_eq!(counter.rejected(), 0);
+    }
+
+    #[test]
+    fn check_rejects_another_group_and_too_many_receivers() {
+        let wallet = add_wallet();
+
+        let err = limits(0).check(wallet, &new_proposal(8, 2)).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Unauthorized));
+
+        let err = limits(0)
+            .check(wallet, &new_proposal(7, MAX_NOTIFICATION_RECEIVERS + 1))
+            .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BadRequest));
+
+        let (_, counter) = NotificationCounterStorage::get(wallet).unwrap();
+        assert_eq!(counter.accepted(), 0);
+        assert_eq!(counter.rejected(), 2);
+    }
+
+    #[test]
+    fn check_rejects_the_events_only_the_index_sends() {
+        let wallet = add_wallet();
+        let receivers = vec![principal(2)];
+
+        let events = vec![
+            MultisigEvent::WalletSpawned {
+                receivers: receivers.clone(),
+                group_id: 7,
+            },
+            MultisigEvent::WalletToppedUp {
+                receivers: receivers.clone(),
+                group_id: 7,
+                cycles: 1,
+            },
+            MultisigEvent::WalletUpgraded {
+                receivers: receivers.clone(),
+                group_id: 7,
+                wasm_version: "v2".to_string(),
+            },
+            MultisigEvent::LowCycles {
+                receivers: receivers.clone(),
+                group_id: 7,
+                cycles: 1,
+            },
+            MultisigEvent::OwnerTransferred {
+                receivers: receivers.clone(),
+                group_id: 7,
+                new_owner: principal(3),
+            },
+        ];
+
+        for event in &events {
+            let err = limits(0).check(wallet, event).unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::BadRequest));
+        }
+
+        // a wallet does report its own threshold changes
+        let threshold = MultisigEvent::ThresholdChanged {
+            receivers,
+            group_id: 7,
+            threshold: 2,
+        };
+        assert!(limits(0).check(wallet, &threshold).is_ok());
+
+        let (_, counter) = NotificationCounterStorage::get(wallet).unwrap();
+        assert_eq!(counter.accepted(), 1);
+        assert_eq!(counter.rejected(), events.len() as u64);
+    }
+
+    #[test]
+    fn check_rate_limits_a_wallet_until_the_window_passed() {
+        let wallet = add_wallet();
+
+        for _ in 0..NOTIFICATION_RATE_LIMIT {
+            limits(0).check(wallet, &new_proposal(7, 2)).unwrap();
+        }
+
+        let err = limits(1).check(wallet, &new_proposal(7, 2)).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::RateLimited));
+
+        assert!(limits(NOTIFICATION_RATE_WINDOW)
+            .check(wallet, &new_proposal(7, 2))
+            .is_ok());
+
+        let (_, counter) = NotificationCounterStorage::get(wallet).unwrap();
+        assert_eq!(counter.accepted(), NOTIFICATION_RATE_LIMIT as u64 + 1);
+        assert_eq!(counter.rejected(), 1);
+        assert_eq!(counter.window_count(), 1);
+    }
+}
@@ -0,0 +1,368 @@
+use std::time::Duration;
+
+use candid::{Encode, Principal};
+
+use crate::{
+    logic::locks::{Lock, LockKey},
+    services::{
+        environment::{Environment, IcEnvironment},
+        proxy_api::{IcProxy, ProxyApi},
+    },
+    storage::{
+        notification_storage::{DeadLetterStorage, NotificationOutboxStorage},
+        state::{
+            NOTIFICATION_DELIVERY_BATCH_SIZE, NOTIFICATION_DELIVERY_INTERVAL,
+            NOTIFICATION_MAX_ATTEMPTS,
+        },
+        storage_api::{StorageInsertable, StorageQueryable, StorageUpdateable},
+    },
+    types::{
+        error::Error,
+        multisig_event::MultisigEvent,
+        notification::Notification,
+        page::{Page, PageArgs},
+        result::CanisterResult,
+    },
+};
+
+/// Proxy method that receives every `MultisigEvent`, together with the wallet it is about.
+/// The deployed proxy does not have it yet, see `legacy_notification`
+const PROXY_NOTIFICATION_METHOD: &str = "multisig_notification";
+
+/// The proxy method and arguments of the events that predate `multisig_notification`, these
+/// keep going to the methods the deployed proxy has
+fn legacy_notification(
+    wallet: Principal,
+    event: &MultisigEvent,
+) -> Option<(&'static str, candid::Result<Vec<u8>>)> {
+    match event {
+        MultisigEvent::WhitelistNotice {
+            receivers,
+            group_id,
+        } => Some((
+            "multisig_whitelist_notice_notification",
+            Encode!(receivers, &wallet, group_id),
+        )),
+        MultisigEvent::NewProposal {
+            receivers,
+            group_id,
+            proposal_id,
+        } => Some((
+            "multisig_new_proposal_notification",
+            Encode!(receivers, &wallet, proposal_id, group_id),
+        )),
+        MultisigEvent::ProposalAccepted {
+            receivers,
+            group_id,
+            proposal_id,
+        } => Some((
+            "multisig_proposal_accept_notification",
+            Encode!(receivers, &wallet, proposal_id, group_id),
+        )),
+        MultisigEvent::ProposalDeclined {
+            receivers,
+            group_id,
+            proposal_id,
+        } => Some((
+            "multisig_proposal_decline_notification",
+            Encode!(receivers, &wallet, proposal_id, group_id),
+        )),
+        MultisigEvent::ProposalStatusUpdate {
+            receivers,
+            group_id,
+            proposal_id,
+        } => Some((
+            "multisig_proposal_status_update_notification",
+            Encode!(receivers, &wallet, proposal_id, group_id),
+        )),
+        _ => None,
+    }
+}
+
+/// Persists notifications for the proxy and delivers them with retries, notifications that
+/// keep failing end up in the dead letters where admins can replay them
+pub struct NotificationOutbox<P = IcProxy, E = IcEnvironment> {
+    proxy: P,
+    env: E,
+}
+
+impl Default for NotificationOutbox {
+    fn default() -> Self {
+        Self::new(IcProxy, IcEnvironment)
+    }
+}
+
+impl NotificationOutbox {
+    /// Retries the pending notifications on an interval, the interval does not survive an
+    /// upgrade so this is called on init and post upgrade
+    pub fn start_delivery_timer() {
+        ic_cdk_timers::set_timer_interval(
+            Duration::from_secs(NOTIFICATION_DELIVERY_INTERVAL),
+            Self::schedule_delivery,
+        );
+    }
+
+    /// Delivers the due notifications right after the current call
+    pub fn schedule_delivery() {
+        ic_cdk_timers::set_timer(Duration::ZERO, || {
+            ic_cdk::spawn(async {
+                Self::default().deliver_due().await;
+            })
+        });
+    }
+
+    pub fn get_outbox(page: PageArgs<u64>) -> Page<u64, Notification> {
+        let (items, next_cursor) =
+            NotificationOutboxStorage::get_page(page.cursor, page.limit(), |_, _| true);
+
+        Page {
+            items,
+            next_cursor,
+            total: NotificationOutboxStorage::len(),
+        }
+    }
+
+    pub fn get_dead_letters(page: PageArgs<u64>) -> Page<u64, Notification> {
+        let (items, next_cursor) =
+            DeadLetterStorage::get_page(page.cursor, page.limit(), |_, _| true);
+
+        Page {
+            items,
+            next_cursor,
+            total: DeadLetterStorage::len(),
+        }
+    }
+}
+
+impl<P: ProxyApi, E: Environment> NotificationOutbox<P, E> {
+    pub fn new(proxy: P, env: E) -> Self {
+        Self { proxy, env }
+    }
+
+    pub fn enqueue(&self, method: &str, args: Vec<u8>) -> CanisterResult<u64> {
+        NotificationOutboxStorage::insert(Notification::new(method, args, self.env.time()))
+            .map(|(id, _)| id)
+    }
+
+    /// Stores the notification of an event about a wallet. Events the proxy has a method for
+    /// go to that method, the others go to `multisig_notification` and stay in the outbox, or
+    /// end up in the dead letters, until the proxy has it
+    pub fn enqueue_event(&self, wallet: Principal, event: MultisigEvent) -> CanisterResult<u64> {
+        let encode_error =
+            |err: candid::Error| Error::internal().add_message(err.to_string().as_str());
+
+        if let Some((method, args)) = legacy_notification(wallet, &event) {
+            return self.enqueue(method, args.map_err(encode_error)?);
+        }
+
+        self.enqueue(
+            PROXY_NOTIFICATION_METHOD,
+            Encode!(&wallet, &event).map_err(encode_error)?,
+        )
+    }
+
+    /// Sends the notifications that are due, returns the number of delivered notifications
+    pub async fn deliver_due(&self) -> u64 {
+        let now = self.env.time();
+
+        // a delivery spans multiple calls, a timer that fires in the meantime skips its run
+        let _lock = match Lock::acquire(LockKey::NotificationDelivery, now) {
+            Some(lock) => lock,
+            None => return 0,
+        };
+
+        let (due, _) = NotificationOutboxStorage::get_page(
+            None,
+            NOTIFICATION_DELIVERY_BATCH_SIZE,
+            |_, notification| notification.is_due(now),
+        );
+
+        let mut delivered = 0;
+
+        for (id, mut notification) in due {
+            let result = self
+                .proxy
+                .send_notification(notification.method(), notification.args())
+                .await;
+
+            match result {
+                Ok(_) => {
+                    let _ = NotificationOutboxStorage::remove(id);
+                    delivered += 1;
+                }
+                Err(err) => {
+                    let notification = notification.failed(err.to_string(), self.env.time());
+
+                    if notification.attempts() >= NOTIFICATION_MAX_ATTEMPTS {
+                        let _ = NotificationOutboxStorage::remove(id);
+                        let _ = DeadLetterStorage::insert(notification);
+                    } else {
+                        let _ = NotificationOutboxStorage::update(id, notification);
+                    }
+                }
+            }
+        }
+
+        delivered
+    }
+
+    /// Moves a dead letter back to the outbox, returns its new id in the outbox
+    pub fn replay_dead_letter(&self, id: u64) -> CanisterResult<u64> {
+        let (_, mut notification) = DeadLetterStorage::get(id)?;
+
+        let (outbox_id, _) =
+            NotificationOutboxStorage::insert(notification.reset(self.env.time()))?;
+        DeadLetterStorage::remove(id)?;
+
+        Ok(outbox_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use candid::{Decode, Principal};
+    use futures::executor::block_on;
+
+    use super::NotificationOutbox;
+    use crate::{
+        services::fakes::{principal, FakeEnvironment, FakeProxy},
+        storage::{
+            notification_storage::{DeadLetterStorage, NotificationOutboxStorage},
+            state::{
+                NOTIFICATION_MAX_ATTEMPTS, NOTIFICATION_RETRY_BASE_DELAY,
+                NOTIFICATION_RETRY_MAX_DELAY,
+            },
+            storage_api::StorageQueryable,
+        },
+        types::multisig_event::MultisigEvent,
+    };
+
+    fn outbox(proxy: &FakeProxy, time: u64) -> NotificationOutbox<FakeProxy, FakeEnvironment> {
+        let env = FakeEnvironment {
+            time,
+            ..Default::default()
+        };
+        NotificationOutbox::new(proxy.clone(), env)
+    }
+
+    #[test]
+    fn deliver_due_sends_and_removes_the_notifications() {
+        let proxy = FakeProxy::default();
+        outbox(&proxy, 0).enqueue("notice", vec![1]).unwrap();
+        outbox(&proxy, 0).enqueue("notice", vec![2]).unwrap();
+
+        assert_eq!(block_on(outbox(&proxy, 0).deliver_due()), 2);
+        assert_eq!(
+            proxy.notifications(),
+            vec![
+                ("notice".to_string(), vec![1]),
+                ("notice".to_string(), vec![2])
+            ]
+        );
+        assert_eq!(NotificationOutboxStorage::len(), 0);
+    }
+
+    #[test]
+    fn older_events_go_to_the_methods_of_the_deployed_proxy() {
+        let proxy = FakeProxy::default();
+        let event = MultisigEvent::ProposalAccepted {
+            receivers: vec![principal(2)],
+            group_id: 7,
+            proposal_id: 3,
+        };
+        outbox(&proxy, 0)
+            .enqueue_event(principal(20), event)
+            .unwrap();
+
+        assert_eq!(block_on(outbox(&proxy, 0).deliver_due()), 1);
+        let (method, args) = proxy.notifications().remove(0);
+        assert_eq!(method, "multisig_proposal_accept_notification");
+        assert_eq!(
+            Decode!(&args, Vec<Principal>, Principal, u64, u64).unwrap(),
+            (vec![principal(2)], principal(20), 3, 7)
+        );
+    }
+
+    #[test]
+    fn newer_events_wait_for_a_proxy_with_multisig_notification() {
+        let proxy = FakeProxy::default();
+        proxy.remove_method("multisig_notification");
+        let event = MultisigEvent::WalletSpawned {
+            receivers: vec![principal(2)],
+            group_id: 7,
+        };
+        let id = outbox(&proxy, 0)
+            .enqueue_event(principal(20), event.clone())
+            .unwrap();
+
+        // the event is not sent to another method, it stays in the outbox
+        assert_eq!(block_on(outbox(&proxy, 0).deliver_due()), 0);
+        assert!(proxy.notifications().is_empty());
+        let (_, notification) = NotificationOutboxStorage::get(id).unwrap();
+        assert_eq!(notification.method(), "multisig_notification");
+        assert_eq!(notification.attempts(), 1);
+
+        // a retry after the proxy got the method delivers it
+        proxy.0.borrow_mut().missing_methods.clear();
+        let time = NOTIFICATION_RETRY_BASE_DELAY;
+        assert_eq!(block_on(outbox(&proxy, time).deliver_due()), 1);
+        let (method, args) = proxy.notifications().remove(0);
+        assert_eq!(method, "multisig_notification");
+        assert_eq!(
+            Decode!(&args, Principal, MultisigEvent).unwrap(),
+            (principal(20), event)
+        );
+    }
+
+    #[test]
+    fn failed_notifications_are_retried_with_backoff() {
+        let proxy = FakeProxy::default();
+        proxy.set_unavailable(true);
+        let id = outbox(&proxy, 0).enqueue("notice", vec![1]).unwrap();
+
+        assert_eq!(block_on(outbox(&proxy, 0).deliver_due()), 0);
+        let (_, notification) = NotificationOutboxStorage::get(id).unwrap();
+        assert_eq!(notification.attempts(), 1);
+
+        // the second attempt waits for the base delay, the third for twice the base delay
+        proxy.set_unavailable(false);
+        assert_eq!(block_on(outbox(&proxy, 1).deliver_due()), 0);
+
+        proxy.set_unavailable(true);
+        let time = NOTIFICATION_RETRY_BASE_DELAY;
+        block_on(outbox(&proxy, time).deliver_due());
+        assert!(!NotificationOutboxStorage::get(id)
+            .unwrap()
+            .1
+            .is_due(time + NOTIFICATION_RETRY_BASE_DELAY));
+
+        proxy.set_unavailable(false);
+        let time = time + 2 * NOTIFICATION_RETRY_BASE_DELAY;
+        assert_eq!(block_on(outbox(&proxy, time).deliver_due()), 1);
+        assert_eq!(proxy.notifications().len(), 1);
+    }
+
+    #[test]
+    fn notifications_end_up_in_the_dead_letters_and_can_be_replayed() {
+        let proxy = FakeProxy::default();
+        proxy.set_unavailable(true);
+        outbox(&proxy, 0).enqueue("notice", vec![1]).unwrap();
+
+        for attempt in 0..NOTIFICATION_MAX_ATTEMPTS as u64 {
+            block_on(outbox(&proxy, attempt * NOTIFICATION_RETRY_MAX_DELAY).deliver_due());
+        }
+
+        assert_eq!(NotificationOutboxStorage::len(), 0);
+        let (dead_letter_id, dead_letter) = DeadLetterStorage::get_all().remove(0);
+        assert_eq!(dead_letter.attempts(), NOTIFICATION_MAX_ATTEMPTS);
+
+        proxy.set_unavailable(false);
+        outbox(&proxy, 0)
+            .replay_dead_letter(dead_letter_id)
+            .unwrap();
+        assert_eq!(DeadLetterStorage::len(), 0);
+
+        assert_eq!(block_on(outbox(&proxy, 0).deliver_due()), 1);
+        assert_eq!(proxy.notifications().len(), 1);
+    }
+}
@@ -0,0 +1,435 @@
+use candid::Principal;
+
+use crate::{
+    logic::{
+        event_log::EventLog, group_access::GroupAccess, notification_outbox::NotificationOutbox,
+    },
+    services::{
+        environment::{Environment, IcEnvironment},
+        multisig_api::{IcMultisig, MultisigApi},
+        proxy_api::{IcProxy, ProxyApi},
+    },
+    storage::{
+        multisig_storage::MultisigStorage,
+        ownership_transfer_storage::OwnershipTransferStorage,
+        storage_api::{StorageQueryable, StorageUpdateable},
+    },
+    types::{
+        error::Error,
+        index_event::IndexEventKind,
+        multisig_event::MultisigEvent,
+        ownership_transfer::OwnershipTransfer,
+        page::{Page, PageArgs},
+        result::CanisterResult,
+        wallet_data::WalletData,
+    },
+};
+
+/// Two step ownership transfers, the owner nominates a new owner who accepts before the
+/// nomination expires, the wallet is only called on acceptance
+pub struct Ownership<M = IcMultisig, P = IcProxy, E = IcEnvironment> {
+    env: E,
+    multisig: M,
+    access: GroupAccess<P, E>,
+    outbox: NotificationOutbox<P, E>,
+}
+
+impl Default for Ownership {
+    fn default() -> Self {
+        Self::new(
+            IcEnvironment,
+            IcMultisig,
+            GroupAccess::default(),
+            NotificationOutbox::default(),
+        )
+    }
+}
+
+impl<M: MultisigApi, P: ProxyApi, E: Environment> Ownership<M, P, E> {
+    pub fn new(
+        env: E,
+        multisig: M,
+        access: GroupAccess<P, E>,
+        outbox: NotificationOutbox<P, E>,
+    ) -> Self {
+        Self {
+            env,
+            multisig,
+            access,
+            outbox,
+        }
+    }
+
+    /// Transfers that did not expire, optionally only those where `principal` is the owner
+    /// or the nominee
+    pub fn get_pending_transfers(
+        &self,
+        page: PageArgs<Principal>,
+        principal: Option<Principal>,
+    ) -> Page<Principal, OwnershipTransfer> {
+        let now = self.env.time();
+        let filter = |_: &Principal, transfer: &OwnershipTransfer| {
+            !transfer.is_expired(now) && principal.is_none_or(|p| transfer.is_party(p))
+        };
+
+        let (items, next_cursor) =
+            OwnershipTransferStorage::get_page(page.cursor, page.limit(), filter);
+
+        Page {
+            items,
+            next_cursor,
+            total: OwnershipTransferStorage::count(filter),
+        }
+    }
+
+    /// Nominates `new_owner`, a previous nomination for the wallet is replaced
+    pub async fn propose_transfer(
+        &self,
+        canister_id: Principal,
+        new_owner: Principal,
+    ) -> CanisterResult<(Principal, OwnershipTransfer)> {
+        let caller = self.env.caller();
+        let (_, wallet) = MultisigStorage::get(canister_id)?;
+
+        if !wallet.is_owner(caller) {
+            return Err(Error::unauthorized().add_message("Caller is not the owner"));
+        }
+
+        if new_owner == Principal::anonymous() || wallet.is_owner(new_owner) {
+            return Err(Error::bad_request()
+                .add_method_name("propose_transfer")
+                .add_info(format!("new_owner: {}", new_owner).as_str())
+                .add_message("The new owner can not be anonymous or the current owner"));
+        }
+
+        // Check if the caller still runs the group of the wallet
+        self.access
+            .validate_group_manager(wallet.group_id(), caller)
+            .await?;
+
+        let transfer = OwnershipTransfer::new(caller, new_owner, self.env.time());
+        let result = OwnershipTransferStorage::upsert(canister_id, transfer.clone())?;
+
+        EventLog::record(
+            &self.env,
+            IndexEventKind::OwnershipTransferProposed {
+                wallet: canister_id,
+                from: caller,
+                to: new_owner,
+                expires_at: transfer.expires_at(),
+            },
+        );
+
+        Ok(result)
+    }
+
+    /// Makes the nominee the owner of the wallet and the index entry
+    pub async fn accept_transfer(
+        &self,
+        canister_id: Principal,
+    ) -> CanisterResult<(Principal, WalletData)> {
+        let caller = self.env.caller();
+        let (_, transfer) = self.get_transfer(canister_id)?;
+
+        if transfer.to() != caller {
+            return Err(Error::unauthorized().add_message("Caller is not the nominated owner"));
+        }
+
+        let (_, wallet) = MultisigStorage::get(canister_id)?;
+
+        // a nomination ends when it expires or when the owner changed in the meantime
+        if transfer.is_expired(self.env.time()) || !wallet.is_owner(transfer.from()) {
+            OwnershipTransferStorage::remove(canister_id)?;
+            return Err(Error::bad_request()
+                .add_method_name("accept_transfer")
+                .add_message("The ownership transfer expired"));
+        }
+
+        // the principal the wallet replies with is not used as the key, the index entry is
+        // the one of the wallet that was called
+        self.multisig.set_owner(canister_id, caller).await?;
+
+        // the wallet changed its owner, so nothing below fails the transfer. The wallet is read
+        // again to keep changes made during the call, an index entry that can not be updated
+        // is fixed by `repair_owners`
+        let wallet = match MultisigStorage::get(canister_id) {
+            Ok((_, mut latest)) => MultisigStorage::update(canister_id, latest.set_owner(caller))
+                .map(|(_, wallet)| wallet)
+                .unwrap_or(latest),
+            Err(_) => wallet.clone().set_owner(caller),
+        };
+
+        // the nomination can be replaced during the call, only the accepted one is removed
+        if OwnershipTransferStorage::get_opt(canister_id)
+            .is_some_and(|(_, pending)| pending == transfer)
+        {
+            let _ = OwnershipTransferStorage::remove(canister_id);
+        }
+
+        EventLog::record(
+            &self.env,
+            IndexEventKind::OwnershipTransferred {
+                wallet: canister_id,
+                previous_owner: transfer.from(),
+                new_owner: caller,
+            },
+        );
+
+        // a notification that can not be stored does not fail the transfer
+        let _ = self.outbox.enqueue_event(
+            canister_id,
+            MultisigEvent::OwnerTransferred {
+                receivers: wallet.members(),
+                group_id: wallet.group_id(),
+                new_owner: caller,
+            },
+        );
+
+        Ok((canister_id, wallet))
+    }
+
+    /// Cancels the nomination, allowed for the owner and the nominee
+    pub fn cancel_transfer(&self, canister_id: Principal) -> CanisterResult<()> {
+        let caller = self.env.caller();
+        let (_, transfer) = self.get_transfer(canister_id)?;
+
+        if !transfer.is_party(caller) {
+            return Err(
+                Error::unauthorized().add_message("Caller is not the owner or the nominated owner")
+            );
+        }
+
+        OwnershipTransferStorage::remove(canister_id)?;
+
+        EventLog::record(
+            &self.env,
+            IndexEventKind::OwnershipTransferCancelled {
+                wallet: canister_id,
+                cancelled_by: caller,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn get_transfer(
+        &self,
+        canister_id: Principal,
+    ) -> CanisterResult<(Principal, OwnershipTransfer)> {
+        OwnershipTransferStorage::get(canister_id).map_err(|_| {
+            Error::not_found()
+                .add_method_name("get_transfer")
+                .add_info(format!("wallet: {}", canister_id).as_str())
+                .add_message("No pending ownership transfer for the wallet")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use candid::Principal;
+    use futures::executor::block_on;
+
+    use super::Ownership;
+    use crate::{
+        logic::{group_access::GroupAccess, notification_outbox::NotificationOutbox, store::Store},
+        services::fakes::{principal, FakeEnvironment, FakeMultisig, FakeProxy},
+        storage::{
+            multisig_storage::MultisigStorage,
+            notification_storage::NotificationOutboxStorage,
+            ownership_transfer_storage::OwnershipTransferStorage,
+            state::OWNERSHIP_TRANSFER_TTL,
+            storage_api::{StorageQueryable, StorageUpdateable},
+        },
+        types::{
+            error::ErrorKind, ownership_transfer::OwnershipTransfer, page::PageArgs,
+            wallet_data::WalletData,
+        },
+    };
+
+    type FakeOwnership = Ownership<FakeMultisig, FakeProxy, FakeEnvironment>;
+
+    struct Fakes {
+        multisig: FakeMultisig,
+        proxy: FakeProxy,
+    }
+
+    impl Fakes {
+        fn new() -> Self {
+            let fakes = Self {
+                multisig: FakeMultisig::default(),
+                proxy: FakeProxy::default(),
+            };
+
+            fakes.proxy.add_member(7, principal(1), &["owner"]);
+            Store::save_wallet(
+                wallet(),
+                WalletData::new(principal(1), 0, 0, 0, 7, None, None),
+            )
+            .unwrap();
+            fakes
+        }
+
+        fn ownership(&self, caller: Principal, time: u64) -> FakeOwnership {
+            let env = FakeEnvironment {
+                caller,
+                time,
+                ..Default::default()
+            };
+
+            Ownership::new(
+                env.clone(),
+                self.multisig.clone(),
+                GroupAccess::new(self.proxy.clone(), env.clone()),
+                NotificationOutbox::new(self.proxy.clone(), env),
+            )
+        }
+    }
+
+    fn wallet() -> Principal {
+        principal(20)
+    }
+
+    #[test]
+    fn accepting_a_transfer_sets_the_owner_on_the_wallet_and_the_index() {
+        let fakes = Fakes::new();
+
+        block_on(
+            fakes
+                .ownership(principal(1), 0)
+                .propose_transfer(wallet(), principal(2)),
+        )
+        .unwrap();
+        assert_eq!(fakes.multisig.owner(wallet()), None);
+        assert_eq!(
+            fakes
+                .ownership(principal(2), 1)
+                .get_pending_transfers(PageArgs::default(), Some(principal(2)))
+                .total,
+            1
+        );
+
+        let (_, data) =
+            block_on(fakes.ownership(principal(2), 1).accept_transfer(wallet())).unwrap();
+
+        assert!(data.is_owner(principal(2)));
+        assert!(Store::get_wallet(wallet())
+            .unwrap()
+            .1
+            .is_owner(principal(2)));
+        assert_eq!(fakes.multisig.owner(wallet()), Some(principal(2)));
+        assert_eq!(OwnershipTransferStorage::len(), 0);
+        assert_eq!(NotificationOutboxStorage::len(), 1);
+    }
+
+    #[test]
+    fn accepting_a_transfer_keeps_the_changes_made_during_the_call() {
+        let fakes = Fakes::new();
+        block_on(
+            fakes
+                .ownership(principal(1), 0)
+                .propose_transfer(wallet(), principal(2)),
+        )
+        .unwrap();
+
+        // the wallet is synced and a new nomination replaces the accepted one in the meantime
+        let replaced = OwnershipTransfer::new(principal(1), principal(3), 1);
+        let during = replaced.clone();
+        fakes.multisig.0.borrow_mut().during_set_owner = Some(Rc::new(move || {
+            let (_, mut data) = Store::get_wallet(wallet()).unwrap();
+            MultisigStorage::update(wallet(), data.set_whitelist(vec![principal(4)])).unwrap();
+            OwnershipTransferStorage::upsert(wallet(), during.clone()).unwrap();
+        }));
+
+        let (_, data) =
+            block_on(fakes.ownership(principal(2), 1).accept_transfer(wallet())).unwrap();
+
+        assert!(data.is_owner(principal(2)));
+        assert!(data.members().contains(&principal(4)));
+        assert_eq!(OwnershipTransferStorage::get(wallet()).unwrap().1, replaced);
+        assert_eq!(NotificationOutboxStorage::len(), 1);
+    }
+
+    #[test]
+    fn only_the_owner_with_a_manager_role_can_propose_a_transfer() {
+        let fakes = Fakes::new();
+
+        let err = block_on(
+            fakes
+                .ownership(principal(2), 0)
+                .propose_transfer(wallet(), principal(3)),
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Unauthorized));
+
+        fakes.proxy.remove_member(7, principal(1));
+        let err = block_on(
+            fakes
+                .ownership(principal(1), 0)
+                .propose_transfer(wallet(), principal(3)),
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Unauthorized));
+        assert_eq!(OwnershipTransferStorage::len(), 0);
+    }
+
+    #[test]
+    fn only_the_nominee_can_accept_before_the_transfer_expires() {
+        let fakes = Fakes::new();
+        block_on(
+            fakes
+                .ownership(principal(1), 0)
+                .propose_transfer(wallet(), principal(2)),
+        )
+        .unwrap();
+
+        let err = block_on(fakes.ownership(principal(3), 1).accept_transfer(wallet())).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Unauthorized));
+
+        let err = block_on(
+            fakes
+                .ownership(principal(2), OWNERSHIP_TRANSFER_TTL)
+                .accept_transfer(wallet()),
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BadRequest));
+
+        assert_eq!(fakes.multisig.owner(wallet()), None);
+        assert!(Store::get_wallet(wallet())
+            .unwrap()
+            .1
+            .is_owner(principal(1)));
+        assert_eq!(OwnershipTransferStorage::len(), 0);
+    }
+
+    #[test]
+    fn the_owner_and_the_nominee_can_cancel_a_transfer() {
+        let fakes = Fakes::new();
+
+        for canceller in [principal(1), principal(2)] {
+            block_on(
+                fakes
+                    .ownership(principal(1), 0)
+                    .propose_transfer(wallet(), principal(2)),
+            )
+            .unwrap();
+
+            let err = fakes
+                .ownership(principal(3), 0)
+                .cancel_transfer(wallet())
+                .unwrap_err();
+            assert!(matches!(err.kind(), ErrorKind::Unauthorized));
+
+            fakes
+                .ownership(canceller, 0)
+                .cancel_transfer(wallet())
+                .unwrap();
+            assert_eq!(OwnershipTransferStorage::len(), 0);
+        }
+
+        let err = block_on(fakes.ownership(principal(2), 0).accept_transfer(wallet())).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::NotFound));
+    }
+}
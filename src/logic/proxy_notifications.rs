@@ -1,83 +1,91 @@
-use crate::storage::{cell_api::CellStorage, proxy_storage::ProxyCanisterStorage};
-
-use crate::logic::guards::is_known_wallet;
 use candid::Principal;
-use ic_cdk::{
-    api::call::{call, CallResult},
-    caller, update,
+use ic_cdk::{caller, update};
+
+use crate::{
+    logic::{
+        guards::is_known_wallet, notification_limits::NotificationLimits,
+        notification_outbox::NotificationOutbox,
+    },
+    types::{multisig_event::MultisigEvent, result::CanisterResult},
 };
 
+// Notifications are stored in the outbox and delivered to the proxy by a timer, so they are
+// retried when the proxy is unavailable. Errors are returned instead of trapping, so the
+// counters of rejected notifications are kept
+fn enqueue(event: MultisigEvent) -> CanisterResult<()> {
+    NotificationLimits::default().check(caller(), &event)?;
+    NotificationOutbox::default().enqueue_event(caller(), event)?;
+
+    NotificationOutbox::schedule_delivery();
+    Ok(())
+}
+
+#[update(guard = "is_known_wallet")]
+pub fn notify(event: MultisigEvent) -> CanisterResult<()> {
+    enqueue(event)
+}
+
+// The endpoints below predate `notify` and are kept for wallets that still call them
+
 #[update(guard = "is_known_wallet")]
-pub async fn multisig_whitelist_notice_notification(receivers: Vec<Principal>, group_id: u64) {
-    if let Ok(proxy) = ProxyCanisterStorage::get() {
-        let _: CallResult<(bool,)> = call(
-            proxy,
-            "multisig_whitelist_notice_notification",
-            (receivers, caller(), group_id),
-        )
-        .await;
-    }
+pub fn multisig_whitelist_notice_notification(
+    receivers: Vec<Principal>,
+    group_id: u64,
+) -> CanisterResult<()> {
+    enqueue(MultisigEvent::WhitelistNotice {
+        receivers,
+        group_id,
+    })
 }
 
 #[update(guard = "is_known_wallet")]
-pub async fn multisig_proposal_accept_notification(
+pub fn multisig_proposal_accept_notification(
     receivers: Vec<Principal>,
     proposal_id: u64,
     group_id: u64,
-) {
-    if let Ok(proxy) = ProxyCanisterStorage::get() {
-        let _: CallResult<(bool,)> = call(
-            proxy,
-            "multisig_proposal_accept_notification",
-            (receivers, caller(), proposal_id, group_id),
-        )
-        .await;
-    }
+) -> CanisterResult<()> {
+    enqueue(MultisigEvent::ProposalAccepted {
+        receivers,
+        group_id,
+        proposal_id,
+    })
 }
 
 #[update(guard = "is_known_wallet")]
-pub async fn multisig_proposal_decline_notification(
+pub fn multisig_proposal_decline_notification(
     receivers: Vec<Principal>,
     proposal_id: u64,
     group_id: u64,
-) {
-    if let Ok(proxy) = ProxyCanisterStorage::get() {
-        let _: CallResult<(bool,)> = call(
-            proxy,
-            "multisig_proposal_decline_notification",
-            (receivers, caller(), proposal_id, group_id),
-        )
-        .await;
-    }
+) -> CanisterResult<()> {
+    enqueue(MultisigEvent::ProposalDeclined {
+        receivers,
+        group_id,
+        proposal_id,
+    })
 }
+
 #[update(guard = "is_known_wallet")]
-pub async fn multisig_proposal_status_update_notification(
+pub fn multisig_proposal_status_update_notification(
     receivers: Vec<Principal>,
     proposal_id: u64,
     group_id: u64,
-) {
-    if let Ok(proxy) = ProxyCanisterStorage::get() {
-        let _: CallResult<(bool,)> = call(
-            proxy,
-            "multisig_proposal_status_update_notification",
-            (receivers, caller(), proposal_id, group_id),
-        )
-        .await;
-    }
+) -> CanisterResult<()> {
+    enqueue(MultisigEvent::ProposalStatusUpdate {
+        receivers,
+        group_id,
+        proposal_id,
+    })
 }
 
 #[update(guard = "is_known_wallet")]
-pub async fn multisig_new_proposal_notification(
+pub fn multisig_new_proposal_notification(
     receivers: Vec<Principal>,
     proposal_id: u64,
     group_id: u64,
-) {
-    if let Ok(proxy) = ProxyCanisterStorage::get() {
-        let _: CallResult<(bool,)> = call(
-            proxy,
-            "multisig_new_proposal_notification",
-            (receivers, caller(), proposal_id, group_id),
-        )
-        .await;
-    }
+) -> CanisterResult<()> {
+    enqueue(MultisigEvent::NewProposal {
+        receivers,
+        group_id,
+        proposal_id,
+    })
 }
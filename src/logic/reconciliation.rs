@@ -0,0 +1,208 @@
+use candid::Principal;
+
+use crate::{
+    logic::event_log::EventLog,
+    services::{
+        environment::{Environment, IcEnvironment},
+        multisig_api::{IcMultisig, MultisigApi},
+    },
+    storage::{
+        multisig_storage::MultisigStorage,
+        ownership_transfer_storage::OwnershipTransferStorage,
+        storage_api::{StorageQueryable, StorageUpdateable},
+    },
+    types::{
+        index_event::IndexEventKind,
+        owner_reconciliation::{OwnerMismatch, OwnerReconciliation},
+        page::PageArgs,
+        result::CanisterResult,
+    },
+};
+
+/// Compares the owners in the index with the owners the wallets have, the wallet is leading
+pub struct Reconciliation<M = IcMultisig, E = IcEnvironment> {
+    multisig: M,
+    env: E,
+}
+
+impl Default for Reconciliation {
+    fn default() -> Self {
+        Self::new(IcMultisig, IcEnvironment)
+    }
+}
+
+impl<M: MultisigApi, E: Environment> Reconciliation<M, E> {
+    pub fn new(multisig: M, env: E) -> Self {
+        Self { multisig, env }
+    }
+
+    /// Reads the owner of each wallet in the page and returns the wallets that disagree
+    pub async fn check_owners(&self, page: PageArgs<Principal>) -> OwnerReconciliation {
+        let (wallets, next_cursor) =
+            MultisigStorage::get_page(page.cursor, page.limit(), |_, _| true);
+
+        let mut reconciliation = OwnerReconciliation {
+            checked: wallets.len() as u64,
+            next_cursor,
+            ..Default::default()
+        };
+
+        for (wallet, data) in wallets {
+            match self.multisig.get_owner(wallet).await {
+                Ok(wallet_owner) if !data.is_owner(wallet_owner) => {
+                    reconciliation.mismatches.push((
+                        wallet,
+                        OwnerMismatch {
+                            index_owner: data.owner(),
+                            wallet_owner,
+                        },
+                    ))
+                }
+                Ok(_) => {}
+                Err(err) => reconciliation.unreachable.push((wallet, err.to_string())),
+            }
+        }
+
+        reconciliation
+    }
+
+    /// Sets the owner in the index to the owner of the wallet for the wallets in the page
+    /// that disagree, every fix is recorded in the event log
+    pub async fn repair_owners(
+        &self,
+        page: PageArgs<Principal>,
+    ) -> CanisterResult<OwnerReconciliation> {
+        let reconciliation = self.check_owners(page).await;
+
+        for (wallet, mismatch) in reconciliation.mismatches.iter() {
+            // the wallet can change while the owners are read, only fix what was compared
+            let (_, mut data) = MultisigStorage::get(*wallet)?;
+            if !data.is_owner(mismatch.index_owner) {
+                continue;
+            }
+
+            MultisigStorage::update(*wallet, data.set_owner(mismatch.wallet_owner))?;
+
+            // a pending transfer was offered by the previous owner
+            if OwnershipTransferStorage::contains_key(*wallet) {
+                OwnershipTransferStorage::remove(*wallet)?;
+            }
+
+            EventLog::record(
+                &self.env,
+                IndexEventKind::OwnerRepaired {
+                    wallet: *wallet,
+                    previous_owner: mismatch.index_owner,
+                    owner: mismatch.wallet_owner,
+                },
+            );
+        }
+
+        Ok(reconciliation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::Reconciliation;
+    use crate::{
+        logic::{event_log::EventLog, store::Store},
+        services::fakes::{principal, FakeEnvironment, FakeMultisig},
+        storage::{
+            ownership_transfer_storage::OwnershipTransferStorage,
+            storage_api::{StorageQueryable, StorageUpdateable},
+        },
+        types::{
+            index_event::IndexEventKind, owner_reconciliation::OwnerMismatch,
+            ownership_transfer::OwnershipTransfer, page::PageArgs, wallet_data::WalletData,
+        },
+    };
+
+    // wallets 20, 21 and 22 are owned by 1 in the index, 21 has another owner on the wallet
+    // and 22 can not be read
+    fn setup() -> FakeMultisig {
+        let multisig = FakeMultisig::default();
+
+        for id in 20..23 {
+            Store::save_wallet(
+                principal(id),
+                WalletData::new(principal(1), 0, 0, 0, 7, None, None),
+            )
+            .unwrap();
+        }
+
+        multisig.set_wallet_owner(principal(20), principal(1));
+        multisig.set_wallet_owner(principal(21), principal(2));
+        multisig
+    }
+
+    #[test]
+    fn check_owners_reports_mismatches_and_unreachable_wallets() {
+        let multisig = setup();
+
+        let reconciliation = block_on(
+            Reconciliation::new(multisig, FakeEnvironment::default())
+                .check_owners(PageArgs::default()),
+        );
+
+        assert_eq!(reconciliation.checked, 3);
+        assert_eq!(
+            reconciliation.mismatches,
+            vec![(
+                principal(21),
+                OwnerMismatch {
+                    index_owner: principal(1),
+                    wallet_owner: principal(2),
+                }
+            )]
+        );
+        assert_eq!(reconciliation.unreachable.len(), 1);
+        assert_eq!(reconciliation.unreachable[0].0, principal(22));
+        assert!(reconciliation.next_cursor.is_none());
+    }
+
+    #[test]
+    fn repair_owners_follows_the_wallet_and_records_the_fix() {
+        let multisig = setup();
+        OwnershipTransferStorage::upsert(
+            principal(21),
+            OwnershipTransfer::new(principal(1), principal(3), 0),
+        )
+        .unwrap();
+
+        block_on(
+            Reconciliation::new(multisig.clone(), FakeEnvironment::default())
+                .repair_owners(PageArgs::default()),
+        )
+        .unwrap();
+
+        assert!(Store::get_wallet(principal(21))
+            .unwrap()
+            .1
+            .is_owner(principal(2)));
+        assert!(Store::get_wallet(principal(22))
+            .unwrap()
+            .1
+            .is_owner(principal(1)));
+        assert_eq!(OwnershipTransferStorage::len(), 0);
+        assert_eq!(
+            EventLog::get_events(0, None)
+                .into_iter()
+                .map(|(_, event)| event.kind().clone())
+                .collect::<Vec<_>>(),
+            vec![IndexEventKind::OwnerRepaired {
+                wallet: principal(21),
+                previous_owner: principal(1),
+                owner: principal(2),
+            }]
+        );
+
+        let reconciliation = block_on(
+            Reconciliation::new(multisig, FakeEnvironment::default())
+                .check_owners(PageArgs::default()),
+        );
+        assert!(reconciliation.mismatches.is_empty());
+    }
+}
@@ -0,0 +1,211 @@
+use candid::Principal;
+
+use crate::{
+    logic::event_log::EventLog,
+    services::environment::{Environment, IcEnvironment},
+    storage::{
+        cell_api::CellStorage,
+        config_storage::ConfigStorage,
+        multisig_storage::MultisigStorage,
+        multisig_wasm_storage::MultisigWasmStorage,
+        proxy_storage::ProxyCanisterStorage,
+        storage_api::{StorageIndexed, StorageQueryable, StorageUpdateable},
+        wasm_version_storage::WasmVersionStorage,
+    },
+    types::{
+        config::{Config, IndexArgs, InitArgs, UpgradeArgs},
+        error::Error,
+        index_event::IndexEventKind,
+        result::CanisterResult,
+        wasm_version::WasmVersion,
+    },
+};
+
+pub struct Setup<E = IcEnvironment> {
+    env: E,
+}
+
+impl Default for Setup {
+    fn default() -> Self {
+        Self::new(IcEnvironment)
+    }
+}
+
+impl Setup {
+    pub fn get_wasm_versions() -> Vec<(String, WasmVersion)> {
+        WasmVersionStorage::get_all()
+    }
+
+    fn validate_proxy(proxy: Principal) -> CanisterResult<()> {
+        if proxy == Principal::anonymous() {
+            return Err(Error::bad_request()
+                .add_method_name("validate_proxy")
+                .add_message("proxy_canister_id can not be anonymous"));
+        }
+
+        Ok(())
+    }
+}
+
+impl<E: Environment> Setup<E> {
+    pub fn new(env: E) -> Self {
+        Self { env }
+    }
+
+    pub fn init(&self, args: IndexArgs) -> CanisterResult<()> {
+        match args {
+            IndexArgs::Init(args) => self.apply_init_args(args),
+            IndexArgs::Upgrade(_) => {
+                Err(Error::bad_request().add_message("Expected init args on install"))
+            }
+        }
+    }
+
+    pub fn upgrade(&self, args: Option<IndexArgs>) -> CanisterResult<()> {
+        match args {
+            None | Some(IndexArgs::Upgrade(None)) => Ok(()),
+            Some(IndexArgs::Upgrade(Some(args))) => self.apply_upgrade_args(args),
+            Some(IndexArgs::Init(_)) => {
+                Err(Error::bad_request().add_message("Expected upgrade args on upgrade"))
+            }
+        }?;
+
+        // wallets stored by a release without the indexes are indexed here
+        MultisigStorage::rebuild_indexes();
+
+        // the version of a wasm uploaded before versions were stored next to it
+        if let (Ok(wasm), Err(_)) = (MultisigWasmStorage::get(), MultisigWasmStorage::version()) {
+            MultisigWasmStorage::set(wasm)?;
+        }
+
+        // the wasm uploaded before versions were registered
+        if let Ok(version) = MultisigWasmStorage::version() {
+            if !WasmVersionStorage::contains_key(version.clone()) {
+                self.save_wasm_version(version, true)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Everything is validated before anything is stored, so invalid args leave the state untouched
+    fn apply_init_args(&self, args: InitArgs) -> CanisterResult<()> {
+        let proxy = args.proxy_canister_id;
+        let config = Config::from_init_args(args);
+
+        Setup::validate_proxy(proxy)?;
+        config.validate()?;
+
+        ProxyCanisterStorage::set(proxy)?;
+        ConfigStorage::set(config)?;
+
+        EventLog::record(
+            &self.env,
+            IndexEventKind::ConfigChanged {
+                caller: self.env.caller(),
+            },
+        );
+        Ok(())
+    }
+
+    fn apply_upgrade_args(&self, args: UpgradeArgs) -> CanisterResult<()> {
+        let proxy = args.proxy_canister_id;
+        let config = ConfigStorage::get()?.apply_upgrade_args(args);
+
+        if let Some(proxy) = proxy {
+            Setup::validate_proxy(proxy)?;
+        }
+        config.validate()?;
+
+        if let Some(proxy) = proxy {
+            ProxyCanisterStorage::set(proxy)?;
+        }
+        ConfigStorage::set(config)?;
+
+        EventLog::record(
+            &self.env,
+            IndexEventKind::ConfigChanged {
+                caller: self.env.caller(),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn upload_wasm(&self, wasm: Vec<u8>) -> CanisterResult<()> {
+        MultisigWasmStorage::set(wasm)?;
+        let version = MultisigWasmStorage::version()?;
+        self.save_wasm_version(version.clone(), true)?;
+
+        EventLog::record(&self.env, IndexEventKind::WasmUploaded { version });
+        Ok(())
+    }
+
+    /// Registers the hash of a wallet wasm that is not uploaded, such as the wasm of wallets
+    /// spawned by an older index, so those wallets can be imported
+    pub fn register_wasm_version(&self, version: String) -> CanisterResult<()> {
+        let valid = version.len() == 64
+            && version
+                .chars()
+                .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c));
+
+        if !valid {
+            return Err(Error::bad_request()
+                .add_method_name("register_wasm_version")
+                .add_message("The version must be a lowercase hex encoded sha256"));
+        }
+
+        // an uploaded version stays uploaded
+        if WasmVersionStorage::contains_key(version.clone()) {
+            return Ok(());
+        }
+
+        self.save_wasm_version(version.clone(), false)?;
+
+        EventLog::record(&self.env, IndexEventKind::WasmRegistered { version });
+        Ok(())
+    }
+
+    fn save_wasm_version(&self, version: String, uploaded: bool) -> CanisterResult<()> {
+        WasmVersionStorage::upsert(
+            version,
+            WasmVersion {
+                registered_at: self.env.time(),
+                registered_by: self.env.caller(),
+                uploaded,
+            },
+        )
+        .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Setup;
+    use crate::{
+        logic::event_log::EventLog,
+        services::fakes::{principal, FakeEnvironment},
+        types::index_event::IndexEventKind,
+    };
+
+    #[test]
+    fn register_wasm_version_records_the_caller_and_time() {
+        let env = FakeEnvironment {
+            caller: principal(3),
+            time: 42,
+            ..Default::default()
+        };
+        let version = "ab".repeat(32);
+
+        Setup::new(env)
+            .register_wasm_version(version.clone())
+            .unwrap();
+
+        let (_, registered) = Setup::get_wasm_versions().pop().unwrap();
+        assert_eq!(registered.registered_by, principal(3));
+        assert_eq!(registered.registered_at, 42);
+        assert!(!registered.uploaded);
+
+        let (_, event) = EventLog::get_events(0, None).pop().unwrap();
+        assert_eq!(event.created_at(), 42);
+        assert_eq!(event.kind(), &IndexEventKind::WasmRegistered { version });
+    }
+}
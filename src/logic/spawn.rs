@@ -0,0 +1,1153 @@
+use std::convert::TryFrom;
+
+use candid::{Encode, Nat, Principal};
+use ic_cdk::api::management_canister::{
+    main::{CanisterInstallMode, CreateCanisterArgument, InstallCodeArgument},
+    provisional::CanisterSettings,
+};
+
+use crate::{
+    logic::{
+        cmc::CyclesManagement,
+        event_log::EventLog,
+        group_access::GroupAccess,
+        ledger::Ledger,
+        locks::{Lock, LockKey},
+        notification_outbox::NotificationOutbox,
+        store::Store,
+    },
+    services::{
+        cmc_api::{CmcApi, IcCmc},
+        environment::{Environment, IcEnvironment},
+        ledger_api::{IcLedger, LedgerApi},
+        management_api::{IcManagement, ManagementApi},
+        proxy_api::{IcProxy, ProxyApi},
+    },
+    storage::{
+        cell_api::CellStorage, config_storage::ConfigStorage,
+        multisig_wasm_storage::MultisigWasmStorage, proxy_storage::ProxyCanisterStorage,
+    },
+    types::{
+        error::Error, index_event::IndexEventKind, ledger_transfer::TransferStep,
+        multisig_event::MultisigEvent, result::CanisterResult, spawn_status::SpawnStatus,
+        wallet_config::WalletConfig, wallet_data::WalletData,
+    },
+};
+
+/// Orchestrates the ICP to cycles conversion, the creation and the installation of wallets
+pub struct Spawn<E = IcEnvironment, L = IcLedger, C = IcCmc, M = IcManagement, P = IcProxy> {
+    env: E,
+    ledger: Ledger<L, E>,
+    cmc: CyclesManagement<C>,
+    management: M,
+    access: GroupAccess<P, E>,
+    outbox: NotificationOutbox<P, E>,
+}
+
+impl Default for Spawn {
+    fn default() -> Self {
+        Self::new(
+            IcEnvironment,
+            Ledger::default(),
+            CyclesManagement::default(),
+            IcManagement,
+            GroupAccess::default(),
+            NotificationOutbox::default(),
+        )
+    }
+}
+
+impl<E, L, C, M, P> Spawn<E, L, C, M, P>
+where
+    E: Environment,
+    L: LedgerApi,
+    C: CmcApi,
+    M: ManagementApi,
+    P: ProxyApi,
+{
+    pub fn new(
+        env: E,
+        ledger: Ledger<L, E>,
+        cmc: CyclesManagement<C>,
+        management: M,
+        access: GroupAccess<P, E>,
+        outbox: NotificationOutbox<P, E>,
+    ) -> Self {
+        Self {
+            env,
+            ledger,
+            cmc,
+            management,
+            access,
+            outbox,
+        }
+    }
+
+    pub async fn spawn_wallet(
+        &self,
+        icp_transfer_blockheight: u64,
+        whitelist: Vec<Principal>,
+        group_id: u64,
+        label: Option<String>,
+        config: Option<WalletConfig>,
+    ) -> CanisterResult<Principal> {
+        Store::validate_whitelist(&whitelist, self.env.id())?;
+
+        if let Some(config) = &config {
+            Store::validate_wallet_config(config, whitelist.len())?;
+        }
+
+        // a finished spawn is not repeated, an unfinished one continues where it stopped
+        Store::validate_spawn_blockheight(icp_transfer_blockheight)?;
+
+        let _blockheight_lock = self.lock_blockheight(icp_transfer_blockheight)?;
+
+        // the wallet policy is checked before the first call and the wallet is saved after the
+        // last, so concurrent spawns for a group are not allowed
+        let _lock =
+            Lock::acquire(LockKey::SpawnGroup(group_id), self.env.time()).ok_or_else(|| {
+                Error::duplicate().add_message(
+                    format!("A wallet is already being spawned for group {}", group_id).as_str(),
+                )
+            })?;
+        Store::validate_wallet_policy(group_id, label.as_ref())?;
+        self.access
+            .validate_group_manager(group_id, self.env.caller())
+            .await?;
+
+        EventLog::record(
+            &self.env,
+            IndexEventKind::SpawnStarted {
+                icp_transfer_blockheight,
+                caller: self.env.caller(),
+                group_id,
+            },
+        );
+
+        let result = self
+            .create_wallet(icp_transfer_blockheight, whitelist, group_id, label, config)
+            .await;
+
+        EventLog::record(
+            &self.env,
+            match &result {
+                Ok(wallet) => IndexEventKind::SpawnFinished {
+                    icp_transfer_blockheight,
+                    wallet: *wallet,
+                },
+                Err(err) => IndexEventKind::SpawnFailed {
+                    icp_transfer_blockheight,
+                    error: err.to_string(),
+                },
+            },
+        );
+
+        result
+    }
+
+    async fn create_wallet(
+        &self,
+        icp_transfer_blockheight: u64,
+        whitelist: Vec<Principal>,
+        group_id: u64,
+        label: Option<String>,
+        config: Option<WalletConfig>,
+    ) -> CanisterResult<Principal> {
+        // initialize new spawn status tracker, or continue the one of an unfinished spawn
+        let mut spawn_status = Store::start_or_resume_status(
+            icp_transfer_blockheight,
+            SpawnStatus::new(Some("Wallet spawn".to_string())).set_group_id(group_id),
+        )?;
+
+        // validate ICP transaction
+        let amount = self
+            .ledger
+            .validate_transaction(self.env.caller(), icp_transfer_blockheight)
+            .await?;
+
+        Store::update_status(
+            icp_transfer_blockheight,
+            spawn_status.transaction_valid(amount),
+        )?;
+
+        // if amount is less than minimum required, transfer ICP back to caller, a started refund
+        // or transfer is finished without asking the exchange rate again, so a changed rate does
+        // not pay the amount twice
+        let refund = match Self::started_refund(icp_transfer_blockheight, &spawn_status) {
+            Some(refund) => refund,
+            None => amount < self.cmc.get_minimum_spawn_icp_amount().await?,
+        };
+
+        if refund {
+            let transfer_back_blockheight = self
+                .ledger
+                .transfer_icp_back_to_caller(icp_transfer_blockheight, amount)
+                .await?;
+
+            Store::update_status(
+                icp_transfer_blockheight,
+                spawn_status.min_amount_error(transfer_back_blockheight),
+            )?;
+
+            EventLog::record(
+                &self.env,
+                IndexEventKind::Refunded {
+                    icp_transfer_blockheight,
+                    to: self.env.caller(),
+                    amount,
+                    blockheight: transfer_back_blockheight,
+                },
+            );
+
+            return Err(Error::insufficient_balance().add_message(
+                format!(
+                    "Amount ({}) is less than the minimum spawn amount, ICP transferred back: blockheight: {}",
+                    amount,
+                    transfer_back_blockheight
+                )
+                .as_str(),
+            ));
+        }
+
+        // transfer ICP to the cycles management canister, a resumed spawn reuses the stored
+        // transfer so the ledger deduplicates it, or the blockheight of a status that was
+        // saved before transfers were stored
+        let cmc_transfer_block_height = match spawn_status.get_transferred_to_cmc() {
+            Some(blockheight) => blockheight,
+            None => {
+                let blockheight = self
+                    .ledger
+                    .transfer_icp_to_cmc(icp_transfer_blockheight, amount, self.env.id())
+                    .await?;
+
+                Store::update_status(
+                    icp_transfer_blockheight,
+                    spawn_status.transferred_to_cmc(blockheight),
+                )?;
+                blockheight
+            }
+        };
+
+        // top up this canister with cycles
+        let cycles = match spawn_status.get_topped_up_self() {
+            Some(cycles) => cycles,
+            None => {
+                let cycles = self
+                    .cmc
+                    .top_up(cmc_transfer_block_height, self.env.id())
+                    .await?;
+
+                Store::update_status(
+                    icp_transfer_blockheight,
+                    spawn_status.topped_up_self(cycles.clone()),
+                )?;
+                cycles
+            }
+        };
+
+        // spawn a new canister, unless the spawn already created one
+        let canister_id = match spawn_status.get_canister_spawned() {
+            Some(canister_id) => canister_id,
+            None => {
+                let canister_id = self.spawn_canister(cycles).await?;
+
+                Store::update_status(
+                    icp_transfer_blockheight,
+                    spawn_status.canister_spawned(canister_id),
+                )?;
+                canister_id
+            }
+        };
+
+        // install the wallet canister
+        let installed_canister_principal = match spawn_status.get_canister_installed() {
+            Some(canister_id) => canister_id,
+            None => {
+                let canister_id = self
+                    .install_canister(canister_id, whitelist.clone(), group_id, config.clone())
+                    .await?;
+
+                Store::update_status(
+                    icp_transfer_blockheight,
+                    spawn_status.canister_installed(canister_id),
+                )?;
+                canister_id
+            }
+        };
+
+        // save the wallet data
+        let (_, wallet) = Store::save_wallet(
+            installed_canister_principal,
+            WalletData::new(
+                self.env.caller(),
+                self.env.time(),
+                icp_transfer_blockheight,
+                cmc_transfer_block_height,
+                group_id,
+                Some(MultisigWasmStorage::version()?),
+                label,
+            )
+            .set_whitelist(whitelist)
+            .set_config(config)
+            .set_controllers(vec![self.env.id()]),
+        )?;
+
+        Store::update_status(icp_transfer_blockheight, spawn_status.done())?;
+
+        // a failed fee transfer stays pending in the ledger transfers and does not fail the spawn
+        let _ = self
+            .ledger
+            .transfer_fee_to_treasury(icp_transfer_blockheight)
+            .await;
+
+        // a notification that can not be stored does not fail the spawn
+        let _ = self.outbox.enqueue_event(
+            installed_canister_principal,
+            MultisigEvent::WalletSpawned {
+                receivers: wallet.members(),
+                group_id,
+            },
+        );
+
+        Ok(installed_canister_principal)
+    }
+
+    pub async fn top_up_wallet(
+        &self,
+        icp_transfer_blockheight: u64,
+        wallet_principal: Principal,
+    ) -> CanisterResult<()> {
+        let _blockheight_lock = self.lock_blockheight(icp_transfer_blockheight)?;
+
+        // initialize new status tracker, or continue the one of an unfinished top up
+        let mut spawn_status = Store::start_or_resume_status(
+            icp_transfer_blockheight,
+            SpawnStatus::new(Some("Top up wallet".to_string())),
+        )?;
+
+        // validate ICP transaction
+        let amount = self
+            .ledger
+            .validate_transaction(self.env.caller(), icp_transfer_blockheight)
+            .await?;
+
+        Store::update_status(
+            icp_transfer_blockheight,
+            spawn_status.transaction_valid(amount),
+        )?;
+
+        // an amount that does not cover the catalyze fee is transferred back to the caller
+        // like a spawn, a started refund or transfer is finished even if the fee changed since
+        let catalyze_fee = ConfigStorage::get()?.catalyze_fee();
+        let refund = Self::started_refund(icp_transfer_blockheight, &spawn_status)
+            .unwrap_or(amount <= catalyze_fee);
+
+        if refund {
+            let transfer_back_blockheight = self
+                .ledger
+                .transfer_icp_back_to_caller(icp_transfer_blockheight, amount)
+                .await?;
+
+            Store::update_status(
+                icp_transfer_blockheight,
+                spawn_status.min_amount_error(transfer_back_blockheight),
+            )?;
+
+            EventLog::record(
+                &self.env,
+                IndexEventKind::Refunded {
+                    icp_transfer_blockheight,
+                    to: self.env.caller(),
+                    amount,
+                    blockheight: transfer_back_blockheight,
+                },
+            );
+
+            return Err(Error::insufficient_balance().add_message(
+                format!(
+                    "Amount ({}) does not cover the fee of {}, ICP transferred back: blockheight: {}",
+                    amount,
+                    catalyze_fee.e8s(),
+                    transfer_back_blockheight
+                )
+                .as_str(),
+            ));
+        }
+
+        // transfer ICP to the cycles management canister, unless the top up already did
+        let cmc_transfer_block_height = match spawn_status.get_transferred_to_cmc() {
+            Some(blockheight) => blockheight,
+            None => {
+                let blockheight = self
+                    .ledger
+                    .transfer_icp_to_cmc(icp_transfer_blockheight, amount, wallet_principal)
+                    .await?;
+
+                Store::update_status(
+                    icp_transfer_blockheight,
+                    spawn_status.transferred_to_cmc(blockheight),
+                )?;
+                blockheight
+            }
+        };
+
+        // top up this canister with cycles
+        let cycles = self
+            .cmc
+            .top_up(cmc_transfer_block_height, wallet_principal)
+            .await?;
+
+        Store::update_status(
+            icp_transfer_blockheight,
+            spawn_status.topped_up_self(cycles.clone()),
+        )?;
+
+        Store::update_status(icp_transfer_blockheight, spawn_status.done())?;
+
+        let cycles = u64::try_from(cycles.0).unwrap_or(u64::MAX);
+        EventLog::record(
+            &self.env,
+            IndexEventKind::ToppedUp {
+                icp_transfer_blockheight,
+                wallet: wallet_principal,
+                cycles,
+            },
+        );
+
+        // top ups are not limited to wallets of the index, only those are notified about
+        if let Ok((_, wallet)) = Store::get_wallet(wallet_principal) {
+            let _ = self.outbox.enqueue_event(
+                wallet_principal,
+                MultisigEvent::WalletToppedUp {
+                    receivers: wallet.members(),
+                    group_id: wallet.group_id(),
+                    cycles,
+                },
+            );
+        }
+
+        // a failed fee transfer stays pending in the ledger transfers and does not fail the top up
+        let _ = self
+            .ledger
+            .transfer_fee_to_treasury(icp_transfer_blockheight)
+            .await;
+
+        Ok(())
+    }
+
+    // returns if a resumed spawn or top up started a refund or a transfer to the cycles
+    // management canister, `None` if it did neither yet
+    fn started_refund(icp_transfer_blockheight: u64, spawn_status: &SpawnStatus) -> Option<bool> {
+        if Ledger::has_transfer(icp_transfer_blockheight, TransferStep::Refund) {
+            Some(true)
+        } else if spawn_status.get_transferred_to_cmc().is_some()
+            || Ledger::has_transfer(icp_transfer_blockheight, TransferStep::CyclesManagement)
+        {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    // an unfinished spawn or top up is resumed by a new call, it must not run twice at once
+    fn lock_blockheight(&self, blockheight: u64) -> CanisterResult<Lock> {
+        Lock::acquire(LockKey::SpawnBlockheight(blockheight), self.env.time()).ok_or_else(|| {
+            Error::duplicate().add_message(
+                format!("Blockheight {} is already being processed", blockheight).as_str(),
+            )
+        })
+    }
+
+    async fn spawn_canister(&self, cycles: Nat) -> CanisterResult<Principal> {
+        let args = CreateCanisterArgument {
+            settings: Some(CanisterSettings {
+                controllers: Some(vec![self.env.id()]),
+                compute_allocation: None,
+                memory_allocation: None,
+                freezing_threshold: None,
+                reserved_cycles_limit: None,
+                wasm_memory_limit: None,
+                log_visibility: None,
+            }),
+        };
+
+        let cycles = u128::try_from(cycles.0)
+            .map_err(|_| Error::internal().add_message("Cycles do not fit in u128"))?;
+
+        self.management.create_canister(args, cycles).await
+    }
+
+    async fn install_canister(
+        &self,
+        canister_id: Principal,
+        whitelist: Vec<Principal>,
+        group_id: u64,
+        config: Option<WalletConfig>,
+    ) -> CanisterResult<Principal> {
+        let wallet_wasm = MultisigWasmStorage::get()?;
+
+        let proxy = ProxyCanisterStorage::get()?;
+
+        let args = InstallCodeArgument {
+            mode: CanisterInstallMode::Install,
+            canister_id,
+            wasm_module: wallet_wasm.to_vec(),
+            // the config is the last argument, so wallet wasms without it ignore it
+            arg: Encode!(&self.env.caller(), &whitelist, &proxy, &group_id, &config).unwrap(),
+        };
+
+        self.management
+            .install_code(args)
+            .await
+            .map(|_| canister_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use candid::{Decode, Principal};
+    use futures::executor::block_on;
+    use ic_ledger_types::{AccountIdentifier, Memo, Subaccount, Tokens, DEFAULT_SUBACCOUNT};
+
+    use super::Spawn;
+    use crate::{
+        logic::{
+            cmc::CyclesManagement, event_log::EventLog, group_access::GroupAccess, ledger::Ledger,
+            notification_outbox::NotificationOutbox, store::Store,
+        },
+        services::fakes::{
+            principal, FakeCmc, FakeEnvironment, FakeLedger, FakeManagement, FakeProxy,
+        },
+        storage::{
+            cell_api::CellStorage,
+            config_storage::ConfigStorage,
+            multisig_storage::MultisigStorage,
+            multisig_wasm_storage::MultisigWasmStorage,
+            notification_storage::NotificationOutboxStorage,
+            proxy_storage::ProxyCanisterStorage,
+            state::{ICP_TRANSACTION_FEE, MEMO_TOP_UP_CANISTER},
+            storage_api::StorageQueryable,
+        },
+        types::{
+            config::{UpgradeArgs, WalletPolicy},
+            error::ErrorKind,
+            index_event::IndexEventKind,
+            multisig_event::MultisigEvent,
+            page::PageArgs,
+            spawn_status::SpawnStatus,
+            wallet_config::{SpendingLimit, WalletConfig},
+            wallet_data::WalletFilter,
+        },
+    };
+
+    const E8S_PER_ICP: u64 = 100_000_000;
+
+    type FakeSpawn = Spawn<FakeEnvironment, FakeLedger, FakeCmc, FakeManagement, FakeProxy>;
+
+    struct Fakes {
+        env: FakeEnvironment,
+        ledger: FakeLedger,
+        cmc: FakeCmc,
+        management: FakeManagement,
+        proxy: FakeProxy,
+    }
+
+    impl Fakes {
+        fn new() -> Self {
+            MultisigWasmStorage::set(vec![0, 97, 115, 109]).unwrap();
+            ProxyCanisterStorage::set(principal(50)).unwrap();
+
+            let fakes = Self {
+                env: FakeEnvironment::default(),
+                ledger: FakeLedger::default(),
+                cmc: FakeCmc::default(),
+                management: FakeManagement::default(),
+                proxy: FakeProxy::default(),
+            };
+
+            fakes.proxy.add_member(7, fakes.env.caller, &["owner"]);
+            fakes
+        }
+
+        fn spawn(&self) -> FakeSpawn {
+            Spawn::new(
+                self.env.clone(),
+                Ledger::new(self.ledger.clone(), self.env.clone()),
+                CyclesManagement::new(self.cmc.clone()),
+                self.management.clone(),
+                GroupAccess::new(self.proxy.clone(), self.env.clone()),
+                NotificationOutbox::new(self.proxy.clone(), self.env.clone()),
+            )
+        }
+
+        fn pay_index(&self, e8s: u64) -> u64 {
+            self.ledger
+                .add_transfer(self.env.caller, self.env.id, Tokens::from_e8s(e8s))
+        }
+    }
+
+    fn whitelist() -> Vec<Principal> {
+        vec![principal(1), principal(2)]
+    }
+
+    fn logged_events() -> Vec<IndexEventKind> {
+        EventLog::get_events(0, None)
+            .into_iter()
+            .map(|(_, event)| event.kind().clone())
+            .collect()
+    }
+
+    fn queued_events() -> Vec<(Principal, MultisigEvent)> {
+        NotificationOutboxStorage::get_all()
+            .into_iter()
+            .map(|(_, notification)| {
+                Decode!(notification.args(), Principal, MultisigEvent).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spawn_wallet_creates_installs_and_saves_the_wallet() {
+        let fakes = Fakes::new();
+        let blockheight = fakes.pay_index(2 * E8S_PER_ICP);
+
+        let wallet = block_on(
+            fakes
+                .spawn()
+                .spawn_wallet(blockheight, whitelist(), 7, None, None),
+        )
+        .unwrap();
+
+        // the amount minus the ledger fee and the catalyze fee is converted to cycles
+        let catalyze_fee = ConfigStorage::get().unwrap().catalyze_fee();
+        let transfers = fakes.ledger.transfers();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].memo, MEMO_TOP_UP_CANISTER);
+        assert_eq!(
+            transfers[0].amount,
+            Tokens::from_e8s(2 * E8S_PER_ICP) - catalyze_fee
+        );
+        assert_eq!(
+            transfers[0].to,
+            AccountIdentifier::new(
+                &ConfigStorage::get().unwrap().cmc_canister_id(),
+                &Subaccount::from(fakes.env.id)
+            )
+        );
+        assert_eq!(fakes.cmc.top_ups(), vec![(0, fakes.env.id)]);
+
+        assert_eq!(
+            fakes.management.created(),
+            vec![(wallet, 5_000_000_000_000)]
+        );
+        let arg = fakes.management.install_arg(wallet).unwrap();
+        let (owner, installed_whitelist, proxy, group_id) =
+            Decode!(&arg, Principal, Vec<Principal>, Principal, u64).unwrap();
+        assert_eq!(owner, fakes.env.caller);
+        assert_eq!(installed_whitelist, whitelist());
+        assert_eq!(proxy, principal(50));
+        assert_eq!(group_id, 7);
+
+        let (_, data) = Store::get_wallet(wallet).unwrap();
+        assert!(data.is_owner(fakes.env.caller));
+        assert!(Store::get_spawn(blockheight).is_ok());
+
+        let filter = WalletFilter {
+            wasm_version: Some(MultisigWasmStorage::version().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(Store::get_wallets(PageArgs::default(), filter).total, 1);
+    }
+
+    #[test]
+    fn spawn_wallet_records_the_start_and_the_end_in_the_event_log() {
+        let fakes = Fakes::new();
+        let blockheight = fakes.pay_index(2 * E8S_PER_ICP);
+
+        let wallet = block_on(
+            fakes
+                .spawn()
+                .spawn_wallet(blockheight, whitelist(), 7, None, None),
+        )
+        .unwrap();
+
+        assert_eq!(
+            logged_events(),
+            vec![
+                IndexEventKind::SpawnStarted {
+                    icp_transfer_blockheight: blockheight,
+                    caller: fakes.env.caller,
+                    group_id: 7,
+                },
+                IndexEventKind::SpawnFinished {
+                    icp_transfer_blockheight: blockheight,
+                    wallet,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn spawn_wallet_notifies_the_wallet_members() {
+        let fakes = Fakes::new();
+        let blockheight = fakes.pay_index(2 * E8S_PER_ICP);
+        let whitelist = vec![principal(1), principal(2), principal(3)];
+
+        let wallet = block_on(
+            fakes
+                .spawn()
+                .spawn_wallet(blockheight, whitelist, 7, None, None),
+        )
+        .unwrap();
+
+        assert_eq!(
+            queued_events(),
+            vec![(
+                wallet,
+                MultisigEvent::WalletSpawned {
+                    receivers: vec![principal(1), principal(2), principal(3)],
+                    group_id: 7,
+                }
+            )]
+        );
+    }
+
+    fn wallet_config(threshold: u64) -> WalletConfig {
+        WalletConfig {
+            threshold,
+            name: "Treasury".to_string(),
+            description: None,
+            spending_limits: Some(vec![SpendingLimit {
+                token: principal(60),
+                amount: 1_000_u64.into(),
+            }]),
+        }
+    }
+
+    #[test]
+    fn spawn_wallet_forwards_and_saves_the_wallet_config() {
+        let fakes = Fakes::new();
+        let blockheight = fakes.pay_index(2 * E8S_PER_ICP);
+
+        let wallet = block_on(fakes.spawn().spawn_wallet(
+            blockheight,
+            whitelist(),
+            7,
+            None,
+            Some(wallet_config(2)),
+        ))
+        .unwrap();
+
+        let arg = fakes.management.install_arg(wallet).unwrap();
+        let (_, _, _, _, config) = Decode!(
+            &arg,
+            Principal,
+            Vec<Principal>,
+            Principal,
+            u64,
+            Option<WalletConfig>
+        )
+        .unwrap();
+        assert_eq!(config, Some(wallet_config(2)));
+
+        let (_, data) = Store::get_wallet(wallet).unwrap();
+        assert_eq!(data.config(), Some(&wallet_config(2)));
+    }
+
+    #[test]
+    fn spawn_wallet_rejects_an_invalid_wallet_config() {
+        let fakes = Fakes::new();
+        let blockheight = fakes.pay_index(2 * E8S_PER_ICP);
+
+        let err = block_on(fakes.spawn().spawn_wallet(
+            blockheight,
+            whitelist(),
+            7,
+            None,
+            Some(wallet_config(3)),
+        ))
+        .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BadRequest));
+        assert!(Store::get_spawn(blockheight).is_err());
+        assert!(fakes.management.created().is_empty());
+    }
+
+    #[test]
+    fn spawn_wallet_below_the_minimum_refunds_the_caller() {
+        let fakes = Fakes::new();
+        let blockheight = fakes.pay_index(E8S_PER_ICP / 2);
+
+        let err = block_on(
+            fakes
+                .spawn()
+                .spawn_wallet(blockheight, whitelist(), 7, None, None),
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InsufficientBalance));
+
+        let transfers = fakes.ledger.transfers();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].memo, Memo(blockheight));
+        assert_eq!(
+            transfers[0].amount,
+            Tokens::from_e8s(E8S_PER_ICP / 2) - ICP_TRANSACTION_FEE
+        );
+        assert_eq!(
+            transfers[0].to,
+            AccountIdentifier::new(&fakes.env.caller, &DEFAULT_SUBACCOUNT)
+        );
+        assert!(fakes.management.created().is_empty());
+        assert_eq!(MultisigStorage::len(), 0);
+
+        let events = logged_events();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(events[0], IndexEventKind::SpawnStarted { .. }));
+        assert!(matches!(
+            events[1],
+            IndexEventKind::Refunded { icp_transfer_blockheight, .. } if icp_transfer_blockheight == blockheight
+        ));
+        assert!(matches!(events[2], IndexEventKind::SpawnFailed { .. }));
+    }
+
+    #[test]
+    fn spawn_wallet_rejects_a_duplicate_blockheight() {
+        let fakes = Fakes::new();
+        let blockheight = fakes.pay_index(2 * E8S_PER_ICP);
+
+        block_on(
+            fakes
+                .spawn()
+                .spawn_wallet(blockheight, whitelist(), 7, None, None),
+        )
+        .unwrap();
+        let err = block_on(
+            fakes
+                .spawn()
+                .spawn_wallet(blockheight, whitelist(), 7, None, None),
+        )
+        .unwrap_err();
+
+        assert!(matches!(err.kind(), ErrorKind::BadRequest));
+        assert_eq!(fakes.management.created().len(), 1);
+    }
+
+    #[test]
+    fn spawn_wallet_resumes_an_unfinished_spawn() {
+        let fakes = Fakes::new();
+        let blockheight = fakes.pay_index(2 * E8S_PER_ICP);
+        fakes.management.set_fail_install(true);
+
+        block_on(
+            fakes
+                .spawn()
+                .spawn_wallet(blockheight, whitelist(), 7, None, None),
+        )
+        .unwrap_err();
+        assert!(!Store::get_spawn(blockheight).unwrap().1.is_finished());
+
+        // the resumed spawn reuses the transfer, the cycles and the created canister
+        fakes.management.set_fail_install(false);
+        let wallet = block_on(
+            fakes
+                .spawn()
+                .spawn_wallet(blockheight, whitelist(), 7, None, None),
+        )
+        .unwrap();
+
+        assert_eq!(fakes.ledger.transfers().len(), 1);
+        assert_eq!(fakes.cmc.top_ups().len(), 1);
+        assert_eq!(fakes.management.created().len(), 1);
+        assert!(Store::get_wallet(wallet).is_ok());
+        assert!(Store::get_spawn(blockheight).unwrap().1.is_finished());
+
+        // another group can not take over the blockheight
+        let err = block_on(
+            fakes
+                .spawn()
+                .spawn_wallet(blockheight, whitelist(), 8, None, None),
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BadRequest));
+    }
+
+    #[test]
+    fn a_transfer_too_old_to_deduplicate_is_not_sent_again() {
+        let fakes = Fakes::new();
+        let blockheight = fakes.pay_index(E8S_PER_ICP);
+        fakes.ledger.0.borrow_mut().drop_replies = 1;
+
+        block_on(fakes.spawn().top_up_wallet(blockheight, principal(42))).unwrap_err();
+
+        // a day later the ledger no longer deduplicates the stored transfer
+        fakes.ledger.0.borrow_mut().too_old_before = Some(u64::MAX);
+        let err = block_on(fakes.spawn().top_up_wallet(blockheight, principal(42))).unwrap_err();
+
+        assert!(matches!(err.kind(), ErrorKind::BadRequest));
+        assert_eq!(fakes.ledger.transfers().len(), 1);
+        assert!(fakes.cmc.top_ups().is_empty());
+    }
+
+    #[test]
+    fn spawn_wallet_rejects_a_transfer_from_another_principal() {
+        let mut fakes = Fakes::new();
+        let blockheight = fakes.pay_index(2 * E8S_PER_ICP);
+        fakes.env.caller = principal(2);
+        fakes.proxy.add_member(7, principal(2), &["admin"]);
+
+        let err = block_on(
+            fakes
+                .spawn()
+                .spawn_wallet(blockheight, whitelist(), 7, None, None),
+        )
+        .unwrap_err();
+
+        assert!(matches!(err.kind(), ErrorKind::BadRequest));
+        assert!(fakes.ledger.transfers().is_empty());
+    }
+
+    #[test]
+    fn spawn_wallet_rejects_a_caller_outside_the_group() {
+        let fakes = Fakes::new();
+        let blockheight = fakes.pay_index(2 * E8S_PER_ICP);
+
+        let err = block_on(
+            fakes
+                .spawn()
+                .spawn_wallet(blockheight, whitelist(), 8, None, None),
+        )
+        .unwrap_err();
+
+        assert!(matches!(err.kind(), ErrorKind::Unauthorized));
+        assert!(Store::get_spawn(blockheight).is_err());
+        assert!(fakes.ledger.transfers().is_empty());
+    }
+
+    #[test]
+    fn spawn_wallet_rejects_a_member_without_a_manager_role() {
+        let fakes = Fakes::new();
+        fakes
+            .proxy
+            .add_member(8, fakes.env.caller, &["member", "moderator"]);
+        let blockheight = fakes.pay_index(2 * E8S_PER_ICP);
+
+        let err = block_on(
+            fakes
+                .spawn()
+                .spawn_wallet(blockheight, whitelist(), 8, None, None),
+        )
+        .unwrap_err();
+
+        assert!(matches!(err.kind(), ErrorKind::Unauthorized));
+        assert!(fakes.ledger.transfers().is_empty());
+    }
+
+    #[test]
+    fn single_wallet_policy_allows_one_wallet_per_group() {
+        let fakes = Fakes::new();
+        let first = fakes.pay_index(2 * E8S_PER_ICP);
+        let second = fakes.pay_index(2 * E8S_PER_ICP);
+
+        block_on(
+            fakes
+                .spawn()
+                .spawn_wallet(first, whitelist(), 7, None, None),
+        )
+        .unwrap();
+        let err = block_on(
+            fakes
+                .spawn()
+                .spawn_wallet(second, whitelist(), 7, None, None),
+        )
+        .unwrap_err();
+
+        assert!(matches!(err.kind(), ErrorKind::Duplicate));
+        assert_eq!(fakes.management.created().len(), 1);
+    }
+
+    #[test]
+    fn multi_wallet_policy_requires_unique_labels() {
+        let fakes = Fakes::new();
+        let config = ConfigStorage::get()
+            .unwrap()
+            .apply_upgrade_args(UpgradeArgs {
+                wallet_policy: Some(WalletPolicy::MultiWallet),
+                ..Default::default()
+            });
+        ConfigStorage::set(config).unwrap();
+
+        let spawn = |label: Option<&str>| {
+            let blockheight = fakes.pay_index(2 * E8S_PER_ICP);
+            block_on(fakes.spawn().spawn_wallet(
+                blockheight,
+                whitelist(),
+                7,
+                label.map(String::from),
+                None,
+            ))
+        };
+
+        assert!(matches!(
+            spawn(None).unwrap_err().kind(),
+            ErrorKind::BadRequest
+        ));
+        spawn(Some("treasury")).unwrap();
+        spawn(Some("payroll")).unwrap();
+        assert!(matches!(
+            spawn(Some("treasury")).unwrap_err().kind(),
+            ErrorKind::Duplicate
+        ));
+        assert_eq!(fakes.management.created().len(), 2);
+    }
+
+    #[test]
+    fn refund_retry_after_a_lost_reply_is_not_paid_twice() {
+        let fakes = Fakes::new();
+        let blockheight = fakes.pay_index(E8S_PER_ICP / 2);
+        fakes.ledger.0.borrow_mut().drop_replies = 1;
+
+        let ledger = Ledger::new(fakes.ledger.clone(), fakes.env.clone());
+        let amount = Tokens::from_e8s(E8S_PER_ICP / 2);
+
+        assert!(block_on(ledger.transfer_icp_back_to_caller(blockheight, amount)).is_err());
+        let refund = block_on(ledger.transfer_icp_back_to_caller(blockheight, amount)).unwrap();
+
+        assert_eq!(refund, 0);
+        assert_eq!(fakes.ledger.transfers().len(), 1);
+        assert_eq!(Ledger::get_transfers(blockheight).len(), 1);
+    }
+
+    #[test]
+    fn a_started_refund_is_finished_after_the_exchange_rate_changed() {
+        let fakes = Fakes::new();
+        let blockheight = fakes.pay_index(E8S_PER_ICP);
+        fakes.ledger.0.borrow_mut().drop_replies = 1;
+
+        let err = block_on(
+            fakes
+                .spawn()
+                .spawn_wallet(blockheight, whitelist(), 7, None, None),
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Internal));
+
+        // the amount covers the minimum at the new rate, but the refund was already sent
+        fakes.cmc.0.borrow_mut().xdr_permyriad_per_icp *= 100;
+        let err = block_on(
+            fakes
+                .spawn()
+                .spawn_wallet(blockheight, whitelist(), 7, None, None),
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InsufficientBalance));
+
+        let transfers = fakes.ledger.transfers();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].memo, Memo(blockheight));
+        assert!(fakes.cmc.top_ups().is_empty());
+        assert!(fakes.management.created().is_empty());
+        assert!(Store::get_spawn(blockheight).unwrap().1.is_finished());
+    }
+
+    #[test]
+    fn refunds_of_blockheights_that_only_differ_in_the_high_bits_are_both_sent() {
+        let fakes = Fakes::new();
+        let ledger = Ledger::new(fakes.ledger.clone(), fakes.env.clone());
+        let amount = Tokens::from_e8s(E8S_PER_ICP / 2);
+
+        for blockheight in [1, 1 | 1 << 60] {
+            block_on(ledger.transfer_icp_back_to_caller(blockheight, amount)).unwrap();
+        }
+
+        assert_eq!(fakes.ledger.transfers().len(), 2);
+        assert_eq!(Ledger::get_transfers(1).len(), 1);
+        assert_eq!(Ledger::get_transfers(1 | 1 << 60).len(), 1);
+    }
+
+    #[test]
+    fn top_up_wallet_converts_the_icp_for_the_wallet() {
+        let fakes = Fakes::new();
+        let blockheight = fakes.pay_index(E8S_PER_ICP);
+        let wallet = principal(42);
+
+        block_on(fakes.spawn().top_up_wallet(blockheight, wallet)).unwrap();
+
+        let transfers = fakes.ledger.transfers();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(
+            transfers[0].to,
+            AccountIdentifier::new(
+                &ConfigStorage::get().unwrap().cmc_canister_id(),
+                &Subaccount::from(wallet)
+            )
+        );
+        assert_eq!(fakes.cmc.top_ups(), vec![(0, wallet)]);
+        assert!(fakes.management.created().is_empty());
+        // the wallet is not known to the index, so there is nobody to notify
+        assert!(queued_events().is_empty());
+    }
+
+    #[test]
+    fn top_up_wallet_resumes_a_status_saved_before_transfers_were_stored() {
+        let fakes = Fakes::new();
+        let blockheight = fakes.pay_index(E8S_PER_ICP);
+        let wallet = principal(42);
+
+        // a top up that stopped after its transfer to the cycles management canister
+        Store::save_status(
+            blockheight,
+            SpawnStatus::new(Some("Top up wallet".to_string())).transferred_to_cmc(9),
+        )
+        .unwrap();
+
+        block_on(fakes.spawn().top_up_wallet(blockheight, wallet)).unwrap();
+
+        assert!(fakes.ledger.transfers().is_empty());
+        assert!(Ledger::get_transfers(blockheight).is_empty());
+        assert_eq!(fakes.cmc.top_ups(), vec![(9, wallet)]);
+        assert!(Store::get_spawn(blockheight).unwrap().1.is_finished());
+    }
+
+    #[test]
+    fn top_up_wallet_below_the_catalyze_fee_refunds_the_caller() {
+        let fakes = Fakes::new();
+        let catalyze_fee = ConfigStorage::get().unwrap().catalyze_fee();
+        let blockheight = fakes.pay_index(catalyze_fee.e8s());
+
+        let err = block_on(fakes.spawn().top_up_wallet(blockheight, principal(42))).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InsufficientBalance));
+
+        let transfers = fakes.ledger.transfers();
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].amount, catalyze_fee - ICP_TRANSACTION_FEE);
+        assert!(fakes.cmc.top_ups().is_empty());
+        assert!(Store::get_spawn(blockheight).unwrap().1.is_finished());
+
+        // nothing is left to refund from an amount below the ledger fee
+        let blockheight = fakes.pay_index(ICP_TRANSACTION_FEE.e8s());
+        let err = block_on(fakes.spawn().top_up_wallet(blockheight, principal(42))).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InsufficientBalance));
+        assert_eq!(fakes.ledger.transfers().len(), 1);
+    }
+
+    #[test]
+    fn top_up_wallet_notifies_the_members_of_a_known_wallet() {
+        let fakes = Fakes::new();
+        let blockheight = fakes.pay_index(2 * E8S_PER_ICP);
+        let wallet = block_on(
+            fakes
+                .spawn()
+                .spawn_wallet(blockheight, whitelist(), 7, None, None),
+        )
+        .unwrap();
+
+        let blockheight = fakes.pay_index(E8S_PER_ICP);
+        block_on(fakes.spawn().top_up_wallet(blockheight, wallet)).unwrap();
+
+        let (principal, event) = queued_events().pop().unwrap();
+        assert_eq!(principal, wallet);
+        assert!(matches!(
+            event,
+            MultisigEvent::WalletToppedUp { group_id: 7, cycles, .. } if cycles > 0
+        ));
+    }
+}
@@ -1,31 +1,30 @@
-use std::{collections::HashSet, convert::TryFrom};
-
-use candid::{Encode, Nat, Principal};
-use ic_cdk::{
-    api::{
-        call::{self, RejectionCode},
-        management_canister::{
-            main::{
-                create_canister, install_code, CanisterInstallMode, CreateCanisterArgument,
-                InstallCodeArgument,
-            },
-            provisional::CanisterSettings,
-        },
-    },
-    caller, id,
-};
+use std::collections::HashSet;
+
+use candid::Principal;
 
 use crate::{
+    services::environment::{Environment, IcEnvironment},
     storage::{
         cell_api::CellStorage,
-        multisig_storage::MultisigStorage,
-        multisig_wasm_storage::MultisigWasmStorage,
-        proxy_storage::ProxyCanisterStorage,
+        config_storage::ConfigStorage,
+        multisig_storage::{GroupIndex, MultisigStorage, OwnerIndex},
         spawn_status_storage::SpawnStatusStorage,
-        storage_api::{StorageInsertableByKey, StorageQueryable, StorageUpdateable},
+        state::{
+            MAX_SPENDING_LIMITS, MAX_WALLET_DESCRIPTION_LENGTH, MAX_WALLET_LABEL_LENGTH,
+            MAX_WALLET_NAME_LENGTH, MIN_SIGNERS,
+        },
+        storage_api::{
+            StorageIndexed, StorageInsertableByKey, StorageQueryable, StorageUpdateable,
+        },
     },
     types::{
-        error::Error, result::CanisterResult, spawn_status::SpawnStatus, wallet_data::WalletData,
+        config::WalletPolicy,
+        error::Error,
+        page::{Page, PageArgs},
+        result::CanisterResult,
+        spawn_status::SpawnStatus,
+        wallet_config::WalletConfig,
+        wallet_data::{WalletData, WalletFilter},
     },
 };
 
@@ -33,73 +32,96 @@ pub struct Store;
 
 impl Store {
     pub fn get_cycles() -> u64 {
-        ic_cdk::api::canister_balance()
+        IcEnvironment.canister_balance()
     }
 
-    pub fn get_wallets() -> Vec<(Principal, WalletData)> {
-        MultisigStorage::get_all()
+    pub fn get_wallets(
+        page: PageArgs<Principal>,
+        filter: WalletFilter,
+    ) -> Page<Principal, WalletData> {
+        // a group or owner filter only reads the wallets of its index key
+        let indexed = match (filter.group_id, filter.owner) {
+            (Some(group_id), _) => Some(MultisigStorage::find_by::<GroupIndex>(group_id)),
+            (None, Some(owner)) => Some(MultisigStorage::find_by::<OwnerIndex>(owner)),
+            (None, None) => None,
+        };
+
+        if let Some(wallets) = indexed {
+            let wallets: Vec<(Principal, WalletData)> = wallets
+                .into_iter()
+                .filter(|(_, wallet)| filter.matches(wallet))
+                .collect();
+            let total = wallets.len() as u64;
+
+            let mut remaining = wallets
+                .into_iter()
+                .filter(|(id, _)| page.cursor.is_none_or(|cursor| *id > cursor));
+            let items: Vec<(Principal, WalletData)> =
+                remaining.by_ref().take(page.limit()).collect();
+            let next_cursor = remaining
+                .next()
+                .and_then(|_| items.last().map(|(id, _)| *id));
+
+            return Page {
+                items,
+                next_cursor,
+                total,
+            };
+        }
+
+        let (items, next_cursor) =
+            MultisigStorage::get_page(page.cursor, page.limit(), |_, wallet| {
+                filter.matches(wallet)
+            });
+
+        let total = if filter.is_empty() {
+            MultisigStorage::len()
+        } else {
+            MultisigStorage::count(|_, wallet| filter.matches(wallet))
+        };
+
+        Page {
+            items,
+            next_cursor,
+            total,
+        }
     }
 
     pub fn get_wallet(principal: Principal) -> CanisterResult<(Principal, WalletData)> {
         MultisigStorage::get(principal)
     }
 
-    pub fn _test_add_wallet(principal: Principal) -> CanisterResult<(Principal, WalletData)> {
-        MultisigStorage::insert_by_key(principal, WalletData::new(0, 0, 0))
+    /// Get the most recently created wallet of a group
+    pub fn get_wallet_by_group(group_id: u64) -> CanisterResult<(Principal, WalletData)> {
+        MultisigStorage::find_by::<GroupIndex>(group_id)
+            .into_iter()
+            .max_by_key(|(_, wallet)| wallet.created_at())
+            .ok_or(
+                Error::not_found()
+                    .add_method_name("get_wallet_by_group")
+                    .add_message(format!("No wallet found for group {}", group_id).as_str()),
+            )
     }
 
-    pub async fn spawn_canister(cycles: Nat) -> CanisterResult<Principal> {
-        let args = CreateCanisterArgument {
-            settings: Some(CanisterSettings {
-                controllers: Some(vec![id()]),
-                compute_allocation: None,
-                memory_allocation: None,
-                freezing_threshold: None,
-                reserved_cycles_limit: None,
-                wasm_memory_limit: None,
-                log_visibility: None,
-            }),
-        };
-
-        create_canister(args, TryFrom::try_from(cycles.0).unwrap())
-            .await
-            .map(|(result,)| result.canister_id)
-            .map_err(|(_, err)| Error::internal().add_message(err.as_str()))
+    pub fn get_wallets_by_owner(owner: Principal) -> Vec<(Principal, WalletData)> {
+        MultisigStorage::find_by::<OwnerIndex>(owner)
     }
 
-    pub async fn install_canister(
-        canister_id: Principal,
-        whitelist: Vec<Principal>,
-        group_id: u64,
-    ) -> CanisterResult<Principal> {
-        let wallet_wasm = MultisigWasmStorage::get()?;
-
-        let proxy = ProxyCanisterStorage::get()?;
-
-        let args = InstallCodeArgument {
-            mode: CanisterInstallMode::Install,
-            canister_id,
-            wasm_module: wallet_wasm.to_vec(),
-            arg: Encode!(&caller(), &whitelist, &proxy, &group_id).unwrap(),
-        };
-        install_code(args)
-            .await
-            .map_err(|(_, err)| Error::internal().add_message(err.as_str()))
-            .map(|_| canister_id)
+    pub fn _test_add_wallet(
+        principal: Principal,
+        created_by: Principal,
+        created_at: u64,
+    ) -> CanisterResult<(Principal, WalletData)> {
+        MultisigStorage::insert_by_key(
+            principal,
+            WalletData::new(created_by, created_at, 0, 0, 0, None, None),
+        )
     }
 
     pub fn save_wallet(
         canister_id: Principal,
-        icp_transfer_blockheight: u64,
-        cmc_transfer_block_height: u64,
-        group_id: u64,
+        wallet: WalletData,
     ) -> CanisterResult<(Principal, WalletData)> {
-        let wallet = WalletData::new(
-            icp_transfer_blockheight,
-            cmc_transfer_block_height,
-            group_id,
-        );
-
         MultisigStorage::insert_by_key(canister_id, wallet)
     }
 
@@ -107,8 +129,15 @@ impl Store {
         SpawnStatusStorage::get(blockheight)
     }
 
-    pub fn get_spawns() -> Vec<(u64, SpawnStatus)> {
-        SpawnStatusStorage::get_all()
+    pub fn get_spawns(page: PageArgs<u64>) -> Page<u64, SpawnStatus> {
+        let (items, next_cursor) =
+            SpawnStatusStorage::get_page(page.cursor, page.limit(), |_, _| true);
+
+        Page {
+            items,
+            next_cursor,
+            total: SpawnStatusStorage::len(),
+        }
     }
 
     pub fn save_status(
@@ -118,6 +147,33 @@ impl Store {
         SpawnStatusStorage::insert_by_key(blockheight, status)
     }
 
+    /// A blockheight pays for one spawn or top up, only an unfinished one can be resumed
+    pub fn validate_spawn_blockheight(blockheight: u64) -> CanisterResult<()> {
+        match SpawnStatusStorage::get_opt(blockheight) {
+            Some((_, status)) if status.is_finished() => Err(Error::bad_request()
+                .add_message(format!("Duplicate blockheight: {}", blockheight).as_str())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the status of an unfinished spawn for the blockheight so it is resumed, or
+    /// saves a new one
+    pub fn start_or_resume_status(
+        blockheight: u64,
+        status: SpawnStatus,
+    ) -> CanisterResult<SpawnStatus> {
+        Self::validate_spawn_blockheight(blockheight)?;
+
+        match SpawnStatusStorage::get_opt(blockheight) {
+            Some((_, existing)) if !existing.is_resumed_by(&status) => Err(Error::bad_request()
+                .add_message(
+                    format!("Blockheight {} is used by another spawn", blockheight).as_str(),
+                )),
+            Some((_, existing)) => Ok(existing),
+            None => Self::save_status(blockheight, status).map(|(_, status)| status),
+        }
+    }
+
     pub fn update_status(
         blockheight: u64,
         status: SpawnStatus,
@@ -125,49 +181,470 @@ impl Store {
         SpawnStatusStorage::update(blockheight, status)
     }
 
-    pub fn validate_whitelist(whitelist: &[Principal]) -> CanisterResult<()> {
-        if whitelist.len() < 2 {
-            return Err(
-                Error::bad_request().add_message("Whitelist must have at least 2 principals")
-            );
+    /// Checks the signers of a new wallet, every rejected entry is listed in the error info
+    pub fn validate_whitelist(whitelist: &[Principal], index: Principal) -> CanisterResult<()> {
+        let config = ConfigStorage::get()?;
+        let mut error = Error::bad_request().add_method_name("validate_whitelist");
+        let mut valid = true;
+
+        if whitelist.len() < MIN_SIGNERS {
+            error = error.add_info(&format!("at least {} principals are required", MIN_SIGNERS));
+            valid = false;
+        }
+
+        if whitelist.len() as u64 > config.max_signers() {
+            error = error.add_info(&format!(
+                "at most {} principals are allowed",
+                config.max_signers()
+            ));
+            valid = false;
         }
 
-        // check for duplicate principals
         let mut seen = HashSet::new();
 
         for principal in whitelist.iter() {
-            if seen.contains(principal) {
-                return Err(Error::bad_request()
-                    .add_message(format!("Duplicate principal: {}", principal).as_str()));
+            let reason = if *principal == Principal::anonymous() {
+                Some("the anonymous principal can not sign")
+            } else if *principal == Principal::management_canister() {
+                Some("the management canister can not sign")
+            } else if *principal == index {
+                Some("the index can not sign")
+            } else if *principal == config.ledger_canister_id() {
+                Some("the ledger can not sign")
+            } else if *principal == config.cmc_canister_id() {
+                Some("the cycles minting canister can not sign")
+            } else if !seen.insert(principal) {
+                Some("duplicate principal")
+            } else {
+                None
+            };
+
+            if let Some(reason) = reason {
+                error = error.add_info(&format!("{}: {}", principal, reason));
+                valid = false;
             }
-            seen.insert(principal);
+        }
+
+        match valid {
+            true => Ok(()),
+            false => Err(error.add_message("Invalid whitelist")),
+        }
+    }
+
+    /// Checks that the number of approvals a wallet needs can be reached by its signers
+    pub fn validate_threshold(threshold: u64, signers: usize) -> CanisterResult<()> {
+        if threshold == 0 || threshold > signers as u64 {
+            return Err(Error::bad_request()
+                .add_method_name("validate_threshold")
+                .add_message(format!("Threshold must be between 1 and {}", signers).as_str()));
         }
 
         Ok(())
     }
 
-    pub async fn transfer_ownership(
-        canister_id: Principal,
-        new_owner: Principal,
-    ) -> CanisterResult<(Principal, WalletData)> {
-        // Get the wallet
-        let (_, mut wallet) = MultisigStorage::get(canister_id)?;
+    /// Checks the settings a wallet with `signers` principals on its whitelist is installed with
+    pub fn validate_wallet_config(config: &WalletConfig, signers: usize) -> CanisterResult<()> {
+        Self::validate_threshold(config.threshold, signers)?;
+
+        let mut error = Error::bad_request().add_method_name("validate_wallet_config");
+        let mut valid = true;
+
+        if config.name.trim().is_empty() || config.name.len() > MAX_WALLET_NAME_LENGTH {
+            error = error.add_info(&format!(
+                "name must be between 1 and {} characters",
+                MAX_WALLET_NAME_LENGTH
+            ));
+            valid = false;
+        }
+
+        if config
+            .description
+            .as_ref()
+            .is_some_and(|description| description.len() > MAX_WALLET_DESCRIPTION_LENGTH)
+        {
+            error = error.add_info(&format!(
+                "description can have at most {} characters",
+                MAX_WALLET_DESCRIPTION_LENGTH
+            ));
+            valid = false;
+        }
+
+        let limits = config.spending_limits.as_deref().unwrap_or_default();
+
+        if limits.len() > MAX_SPENDING_LIMITS {
+            error = error.add_info(&format!(
+                "at most {} spending limits are allowed",
+                MAX_SPENDING_LIMITS
+            ));
+            valid = false;
+        }
+
+        let mut seen = HashSet::new();
+
+        for limit in limits.iter() {
+            if limit.token == Principal::anonymous() {
+                error = error.add_info("spending limit token can not be anonymous");
+                valid = false;
+            }
+
+            if limit.amount == 0_u64 {
+                error = error.add_info(&format!(
+                    "{}: spending limit must be more than 0",
+                    limit.token
+                ));
+                valid = false;
+            }
+
+            if !seen.insert(limit.token) {
+                error = error.add_info(&format!("{}: duplicate spending limit", limit.token));
+                valid = false;
+            }
+        }
+
+        match valid {
+            true => Ok(()),
+            false => Err(error.add_message("Invalid wallet config")),
+        }
+    }
 
-        // Check if the caller is the owner
-        if !wallet.is_owner(caller()) {
-            return Err(Error::unauthorized().add_message("Caller is not the owner"));
+    /// Checks that a wallet for the group and label is allowed by the configured wallet policy
+    pub fn validate_wallet_policy(group_id: u64, label: Option<&String>) -> CanisterResult<()> {
+        if let Some(label) = label {
+            if label.trim().is_empty() || label.len() > MAX_WALLET_LABEL_LENGTH {
+                return Err(Error::bad_request().add_message(
+                    format!(
+                        "Label must be between 1 and {} characters",
+                        MAX_WALLET_LABEL_LENGTH
+                    )
+                    .as_str(),
+                ));
+            }
         }
 
-        // Call the canister to set the new owner
-        let call_result: Result<(Result<Principal, String>,), (RejectionCode, String)> =
-            call::call(canister_id, "set_owner", (new_owner,)).await;
+        let wallets = MultisigStorage::find_by::<GroupIndex>(group_id);
+
+        match ConfigStorage::get()?.wallet_policy() {
+            WalletPolicy::SingleWallet => match wallets.is_empty() {
+                true => Ok(()),
+                false => Err(Error::duplicate()
+                    .add_message(format!("Group {} already has a wallet", group_id).as_str())),
+            },
+            WalletPolicy::MultiWallet => {
+                let label = label.ok_or(
+                    Error::bad_request().add_message("A label is required for multiple wallets"),
+                )?;
+
+                match wallets
+                    .iter()
+                    .any(|(_, wallet)| wallet.label() == Some(label))
+                {
+                    true => Err(Error::duplicate().add_message(
+                        format!("Group {} already has a wallet labeled {}", group_id, label)
+                            .as_str(),
+                    )),
+                    false => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use candid::Principal;
+
+    use super::Store;
+    use crate::{
+        services::fakes::principal,
+        storage::{
+            cell_api::CellStorage,
+            config_storage::ConfigStorage,
+            multisig_storage::{GroupIndex, MultisigStorage},
+            storage_api::{StorageIndexed, StorageQueryable, StorageUpdateable},
+        },
+        types::{
+            config::UpgradeArgs,
+            page::PageArgs,
+            spawn_status::SpawnStatus,
+            wallet_config::{SpendingLimit, WalletConfig},
+            wallet_data::{WalletData, WalletFilter},
+        },
+    };
+
+    fn add_wallets() {
+        for id in 0..5 {
+            let created_by = if id % 2 == 0 {
+                principal(1)
+            } else {
+                principal(2)
+            };
+            Store::save_wallet(
+                principal(10 + id),
+                WalletData::new(
+                    created_by,
+                    id as u64 * 100,
+                    id as u64,
+                    id as u64,
+                    id as u64 % 3,
+                    Some("v1".to_string()),
+                    None,
+                ),
+            )
+            .unwrap();
+        }
+    }
+
+    fn page(cursor: Option<Principal>, limit: u64) -> PageArgs<Principal> {
+        PageArgs {
+            cursor,
+            limit: Some(limit),
+        }
+    }
+
+    #[test]
+    fn get_wallets_pages_through_all_wallets() {
+        add_wallets();
+
+        let first = Store::get_wallets(page(None, 2), WalletFilter::default());
+        assert_eq!(first.items.len(), 2);
+        assert_eq!(first.total, 5);
+
+        let second = Store::get_wallets(page(first.next_cursor, 2), WalletFilter::default());
+        let third = Store::get_wallets(page(second.next_cursor, 2), WalletFilter::default());
+        assert_eq!(third.items.len(), 1);
+        assert_eq!(third.next_cursor, None);
+
+        let mut keys: Vec<Principal> = [first.items, second.items, third.items]
+            .concat()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        keys.dedup();
+        assert_eq!(keys.len(), 5);
+    }
+
+    #[test]
+    fn get_wallets_filters_and_counts_the_matches() {
+        add_wallets();
+
+        let by_creator = WalletFilter {
+            created_by: Some(principal(1)),
+            ..Default::default()
+        };
+        let result = Store::get_wallets(page(None, 2), by_creator);
+        assert_eq!(result.total, 3);
+        assert_eq!(result.items.len(), 2);
+        assert!(result.next_cursor.is_some());
+
+        let by_group_and_time = WalletFilter {
+            group_id: Some(1),
+            created_after: Some(100),
+            created_before: Some(400),
+            ..Default::default()
+        };
+        let result = Store::get_wallets(PageArgs::default(), by_group_and_time);
+        assert_eq!(result.total, 1);
+        assert_eq!(result.items[0].0, principal(11));
+
+        let by_version = WalletFilter {
+            wasm_version: Some("v2".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(Store::get_wallets(PageArgs::default(), by_version).total, 0);
+    }
+
+    #[test]
+    fn get_wallets_pages_through_the_wallets_of_an_owner_and_a_group() {
+        add_wallets();
+
+        let by_owner = || WalletFilter {
+            owner: Some(principal(1)),
+            ..Default::default()
+        };
+        let first = Store::get_wallets(page(None, 2), by_owner());
+        assert_eq!(keys(first.items), vec![principal(10), principal(12)]);
+        assert_eq!(first.next_cursor, Some(principal(12)));
+        assert_eq!(first.total, 3);
+
+        let second = Store::get_wallets(page(first.next_cursor, 2), by_owner());
+        assert_eq!(keys(second.items), vec![principal(14)]);
+        assert_eq!(second.next_cursor, None);
+        assert_eq!(second.total, 3);
+
+        let by_group_and_owner = WalletFilter {
+            group_id: Some(0),
+            owner: Some(principal(2)),
+            ..Default::default()
+        };
+        let result = Store::get_wallets(PageArgs::default(), by_group_and_owner);
+        assert_eq!(keys(result.items), vec![principal(13)]);
+        assert_eq!(result.total, 1);
+    }
+
+    #[test]
+    fn get_spawns_caps_the_limit() {
+        for blockheight in 0..3 {
+            Store::save_status(blockheight, SpawnStatus::default()).unwrap();
+        }
+
+        let result = Store::get_spawns(PageArgs {
+            cursor: Some(0),
+            limit: Some(0),
+        });
+        assert_eq!(result.items.len(), 1);
+        assert_eq!(result.items[0].0, 1);
+        assert_eq!(result.next_cursor, Some(1));
+        assert_eq!(result.total, 3);
+    }
+
+    fn keys(wallets: Vec<(Principal, impl Sized)>) -> Vec<Principal> {
+        wallets.into_iter().map(|(key, _)| key).collect()
+    }
+
+    #[test]
+    fn get_wallet_by_group_returns_the_newest_wallet() {
+        add_wallets();
+        Store::save_wallet(
+            principal(20),
+            WalletData::new(principal(3), 1_000, 0, 0, 1, None, None),
+        )
+        .unwrap();
+
+        let (wallet, _) = Store::get_wallet_by_group(1).unwrap();
+        assert_eq!(wallet, principal(20));
+        assert!(Store::get_wallet_by_group(9).is_err());
+    }
+
+    #[test]
+    fn owner_index_follows_updates_and_removals() {
+        add_wallets();
+        assert_eq!(
+            keys(Store::get_wallets_by_owner(principal(2))),
+            vec![principal(11), principal(13)]
+        );
+
+        let (_, mut wallet) = MultisigStorage::get(principal(11)).unwrap();
+        MultisigStorage::update(principal(11), wallet.set_owner(principal(3))).unwrap();
+        assert_eq!(
+            keys(Store::get_wallets_by_owner(principal(2))),
+            vec![principal(13)]
+        );
+        assert_eq!(
+            keys(Store::get_wallets_by_owner(principal(3))),
+            vec![principal(11)]
+        );
+
+        MultisigStorage::remove(principal(13)).unwrap();
+        assert!(Store::get_wallets_by_owner(principal(2)).is_empty());
+        assert_eq!(
+            keys(MultisigStorage::find_by::<GroupIndex>(0)),
+            vec![principal(10)]
+        );
+    }
+
+    #[test]
+    fn rebuild_indexes_restores_the_indexes() {
+        add_wallets();
+        MultisigStorage::rebuild_indexes();
+
+        assert_eq!(
+            keys(MultisigStorage::find_by::<GroupIndex>(0)),
+            vec![principal(10), principal(13)]
+        );
+        assert_eq!(Store::get_wallets_by_owner(principal(1)).len(), 3);
+    }
+
+    #[test]
+    fn range_by_returns_the_wallets_of_a_range_of_index_keys() {
+        use std::ops::Bound;
+
+        add_wallets();
+
+        assert_eq!(
+            keys(MultisigStorage::range_by::<GroupIndex, _>(1..)),
+            vec![principal(11), principal(14), principal(12)]
+        );
+        assert_eq!(
+            keys(MultisigStorage::range_by::<GroupIndex, _>((
+                Bound::Excluded(0),
+                Bound::Excluded(2)
+            ))),
+            vec![principal(11), principal(14)]
+        );
+        assert!(MultisigStorage::range_by::<GroupIndex, _>(3..).is_empty());
+    }
+
+    #[test]
+    fn validate_whitelist_lists_every_rejected_principal() {
+        let index = principal(100);
+        let config = ConfigStorage::get().unwrap();
+        let whitelist = vec![
+            principal(1),
+            Principal::anonymous(),
+            Principal::management_canister(),
+            index,
+            config.ledger_canister_id(),
+            config.cmc_canister_id(),
+            principal(1),
+        ];
+
+        let err = Store::validate_whitelist(&whitelist, index).unwrap_err();
+        let info = err.info().unwrap();
+        assert_eq!(info.len(), 6);
+        assert!(info[0].starts_with(&Principal::anonymous().to_string()));
+        assert!(info[5].ends_with("duplicate principal"));
+
+        assert!(Store::validate_whitelist(&[principal(1)], index).is_err());
+        assert!(Store::validate_whitelist(&[principal(1), principal(2)], index).is_ok());
+    }
+
+    #[test]
+    fn validate_whitelist_uses_the_configured_max_signers() {
+        let whitelist: Vec<Principal> = (1..=3).map(principal).collect();
+        assert!(Store::validate_whitelist(&whitelist, principal(100)).is_ok());
+
+        let config = ConfigStorage::get()
+            .unwrap()
+            .apply_upgrade_args(UpgradeArgs {
+                max_signers: Some(2),
+                ..Default::default()
+            });
+        ConfigStorage::set(config).unwrap();
+
+        let err = Store::validate_whitelist(&whitelist, principal(100)).unwrap_err();
+        assert_eq!(
+            err.info().unwrap(),
+            &vec!["at most 2 principals are allowed"]
+        );
+    }
+
+    #[test]
+    fn validate_threshold_needs_a_reachable_threshold() {
+        assert!(Store::validate_threshold(0, 3).is_err());
+        assert!(Store::validate_threshold(1, 3).is_ok());
+        assert!(Store::validate_threshold(3, 3).is_ok());
+        assert!(Store::validate_threshold(4, 3).is_err());
+    }
+
+    #[test]
+    fn validate_wallet_config_lists_every_invalid_field() {
+        let limit = |amount: u64| SpendingLimit {
+            token: principal(60),
+            amount: amount.into(),
+        };
+        let mut config = WalletConfig {
+            threshold: 2,
+            name: "Treasury".to_string(),
+            description: Some("Group funds".to_string()),
+            spending_limits: Some(vec![limit(100)]),
+        };
+        assert!(Store::validate_wallet_config(&config, 2).is_ok());
 
-        // Check if the call was successful
-        let wallet_canister_id = call_result
-            .map_err(|(_, err)| Error::internal().add_message(err.as_str()))?
-            .0
-            .map_err(|err| Error::internal().add_message(err.as_str()))?;
+        config.name = " ".to_string();
+        config.description = Some("x".repeat(1025));
+        config.spending_limits = Some(vec![limit(0), limit(100)]);
 
-        MultisigStorage::update(wallet_canister_id, wallet.set_owner(new_owner))
+        let err = Store::validate_wallet_config(&config, 2).unwrap_err();
+        assert_eq!(err.info().unwrap().len(), 4);
     }
 }
@@ -0,0 +1,353 @@
+use candid::Principal;
+
+use crate::{
+    logic::notification_outbox::NotificationOutbox,
+    services::{
+        environment::{Environment, IcEnvironment},
+        management_api::{IcManagement, ManagementApi},
+        proxy_api::{IcProxy, ProxyApi},
+    },
+    storage::{
+        cell_api::CellStorage,
+        config_storage::ConfigStorage,
+        multisig_storage::{FleetIndex, MultisigStorage},
+        multisig_wasm_storage::MultisigWasmStorage,
+        storage_api::{StorageQueryable, StorageUpdateable},
+    },
+    types::{
+        error::Error,
+        multisig_event::MultisigEvent,
+        page::PageArgs,
+        result::CanisterResult,
+        wallet_health::{FleetStatus, WalletHealth},
+        wallet_snapshot::WalletSyncReport,
+    },
+};
+
+/// Reads the canister status of the wallets, only a controller can, and keeps the result on
+/// the wallet so the fleet can be summarized without calling every wallet. The members of a
+/// wallet are notified when it drops below `LOW_CYCLES_DAYS` of cycles.
+pub struct WalletHealthCheck<G = IcManagement, P = IcProxy, E = IcEnvironment> {
+    management: G,
+    env: E,
+    outbox: NotificationOutbox<P, E>,
+}
+
+impl Default for WalletHealthCheck {
+    fn default() -> Self {
+        Self::new(IcManagement, IcEnvironment, NotificationOutbox::default())
+    }
+}
+
+impl WalletHealthCheck {
+    /// Counts the wallets by their last health check, outdated is compared with the wasm
+    /// that is uploaded now
+    pub fn get_fleet_status() -> FleetStatus {
+        let version = MultisigWasmStorage::version().ok();
+        let mut fleet = FleetStatus::default();
+
+        for (summary, wallets) in FleetIndex::counts() {
+            fleet.add(summary.as_ref(), wallets, version.as_deref());
+        }
+
+        fleet
+    }
+}
+
+impl<G: ManagementApi, P: ProxyApi, E: Environment> WalletHealthCheck<G, P, E> {
+    pub fn new(management: G, env: E, outbox: NotificationOutbox<P, E>) -> Self {
+        Self {
+            management,
+            env,
+            outbox,
+        }
+    }
+
+    /// Checks a wallet for its owner or an admin, the status is not public
+    pub async fn wallet_health(&self, canister_id: Principal) -> CanisterResult<WalletHealth> {
+        let caller = self.env.caller();
+        let (_, wallet) = MultisigStorage::get(canister_id)?;
+
+        let is_admin = ConfigStorage::get()
+            .map(|config| config.is_admin(caller))
+            .unwrap_or(false);
+
+        if !wallet.is_owner(caller) && !is_admin {
+            return Err(Error::unauthorized()
+                .add_method_name("wallet_health")
+                .add_message("Caller is not the owner or an admin"));
+        }
+
+        self.check_wallet(canister_id).await
+    }
+
+    /// Reads the canister status of a wallet the index controls and stores it on the wallet
+    pub async fn check_wallet(&self, canister_id: Principal) -> CanisterResult<WalletHealth> {
+        let (_, wallet) = MultisigStorage::get(canister_id)?;
+
+        if !wallet.is_managed_by(self.env.id()) {
+            return Err(Error::bad_request()
+                .add_method_name("check_wallet")
+                .add_message("The index is no longer a controller of the wallet"));
+        }
+
+        let status = self.management.canister_status(canister_id).await?;
+        let health = WalletHealth::from_status(status, self.env.time());
+
+        // the wallet can change during the call, read it again so those changes are kept
+        let (_, mut wallet) = MultisigStorage::get(canister_id)?;
+        let was_low_on_cycles = wallet
+            .health()
+            .is_some_and(|previous| previous.is_low_on_cycles());
+        MultisigStorage::update(canister_id, wallet.set_health(health.clone()))?;
+
+        // the members are notified once when the wallet drops below the threshold, not on
+        // every check, a notification that can not be stored does not fail the check
+        if health.is_low_on_cycles() && !was_low_on_cycles {
+            let _ = self.outbox.enqueue_event(
+                canister_id,
+                MultisigEvent::LowCycles {
+                    receivers: wallet.members(),
+                    group_id: wallet.group_id(),
+                    cycles: health.cycles,
+                },
+            );
+        }
+
+        Ok(health)
+    }
+
+    /// Checks the wallets of a page, wallets that can not be checked keep their last health
+    pub async fn check_wallets(&self, page: PageArgs<Principal>) -> WalletSyncReport {
+        let (wallets, next_cursor) =
+            MultisigStorage::get_page(page.cursor, page.limit(), |_, wallet| {
+                wallet.is_managed_by(self.env.id())
+            });
+
+        let mut report = WalletSyncReport {
+            next_cursor,
+            ..Default::default()
+        };
+
+        for (wallet, _) in wallets {
+            match self.check_wallet(wallet).await {
+                Ok(_) => report.synced += 1,
+                Err(err) => report.unreachable.push((wallet, err.to_string())),
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use candid::{Decode, Nat, Principal};
+    use futures::executor::block_on;
+    use ic_cdk::api::management_canister::main::{
+        CanisterStatusResponse, CanisterStatusType, DefiniteCanisterSettings, QueryStats,
+    };
+    use sha2::{Digest, Sha256};
+
+    use super::WalletHealthCheck;
+    use crate::{
+        logic::{notification_outbox::NotificationOutbox, store::Store},
+        services::{
+            fakes::{principal, FakeEnvironment, FakeManagement, FakeProxy},
+            management_api::ManagementApi,
+        },
+        storage::{
+            cell_api::CellStorage,
+            multisig_storage::MultisigStorage,
+            multisig_wasm_storage::MultisigWasmStorage,
+            notification_storage::NotificationOutboxStorage,
+            storage_api::{StorageQueryable, StorageUpdateable},
+        },
+        types::{
+            error::ErrorKind, multisig_event::MultisigEvent, page::PageArgs,
+            wallet_data::WalletData, wallet_health::WalletHealth,
+        },
+    };
+
+    fn status(cycles: u64, burned_per_day: u64) -> CanisterStatusResponse {
+        CanisterStatusResponse {
+            status: CanisterStatusType::Running,
+            settings: DefiniteCanisterSettings {
+                freezing_threshold: Nat::from(30 * 24 * 60 * 60_u64),
+                ..Default::default()
+            },
+            module_hash: None,
+            memory_size: Nat::from(0_u64),
+            cycles: Nat::from(cycles),
+            idle_cycles_burned_per_day: Nat::from(burned_per_day),
+            query_stats: QueryStats {
+                num_calls_total: Nat::from(0_u64),
+                num_instructions_total: Nat::from(0_u64),
+                request_payload_bytes_total: Nat::from(0_u64),
+                response_payload_bytes_total: Nat::from(0_u64),
+            },
+            reserved_cycles: Nat::from(0_u64),
+        }
+    }
+
+    fn save_wallet(wallet: Principal) {
+        Store::save_wallet(
+            wallet,
+            WalletData::new(principal(1), 0, 0, 0, 1, None, None),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn days_until_freezing_leave_out_the_freezing_threshold() {
+        // 30 days of the freezing threshold and 10 days above it
+        let health = WalletHealth::from_status(status(40_000, 1_000), 0);
+        assert_eq!(health.days_until_freezing, Some(10));
+        assert!(health.is_low_on_cycles());
+
+        let health = WalletHealth::from_status(status(40_000, 0), 0);
+        assert_eq!(health.days_until_freezing, None);
+        assert!(!health.is_low_on_cycles());
+    }
+
+    #[test]
+    fn fleet_status_counts_the_last_health_checks() {
+        MultisigWasmStorage::set(vec![0, 97, 115, 109]).unwrap();
+        let uploaded = Sha256::digest(MultisigWasmStorage::get().unwrap()).to_vec();
+        let management = FakeManagement::default();
+
+        // 20 runs the uploaded wasm, 21 an older one and 22 is stopped, 23 is not checked
+        for wallet in [principal(20), principal(21), principal(22), principal(23)] {
+            save_wallet(wallet);
+        }
+        management.add_canister(principal(20), vec![principal(100)], uploaded.clone());
+        management.add_canister(principal(21), vec![principal(100)], vec![1; 32]);
+        management.add_canister(principal(22), vec![principal(100)], uploaded);
+        block_on(management.stop_canister(principal(22))).unwrap();
+
+        let check = WalletHealthCheck::new(
+            management,
+            FakeEnvironment::default(),
+            NotificationOutbox::new(FakeProxy::default(), FakeEnvironment::default()),
+        );
+        let report = block_on(check.check_wallets(PageArgs {
+            cursor: None,
+            limit: Some(3),
+        }));
+        assert_eq!(report.synced, 3);
+        assert_eq!(report.next_cursor, Some(principal(22)));
+
+        let fleet = WalletHealthCheck::get_fleet_status();
+        assert_eq!(fleet.total, 4);
+        assert_eq!(fleet.healthy, 1);
+        assert_eq!(fleet.outdated, 1);
+        assert_eq!(fleet.stopped, 1);
+        assert_eq!(fleet.low_cycles, 0);
+        assert_eq!(fleet.unchecked, 1);
+
+        // the counts follow the wallets that are removed
+        MultisigStorage::remove(principal(21)).unwrap();
+        MultisigStorage::remove(principal(23)).unwrap();
+        let fleet = WalletHealthCheck::get_fleet_status();
+        assert_eq!(fleet.total, 2);
+        assert_eq!(fleet.healthy, 1);
+        assert_eq!(fleet.outdated, 0);
+        assert_eq!(fleet.unchecked, 0);
+    }
+
+    #[test]
+    fn wallet_health_is_for_the_owner_and_admins() {
+        save_wallet(principal(20));
+        let management = FakeManagement::default();
+        management.add_canister(principal(20), vec![principal(100)], vec![]);
+
+        let check = |caller| {
+            let env = FakeEnvironment {
+                caller,
+                ..Default::default()
+            };
+            WalletHealthCheck::new(
+                management.clone(),
+                env.clone(),
+                NotificationOutbox::new(FakeProxy::default(), env),
+            )
+        };
+
+        let err = block_on(check(principal(2)).wallet_health(principal(20))).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Unauthorized));
+        assert!(Store::get_wallet(principal(20))
+            .unwrap()
+            .1
+            .health()
+            .is_none());
+
+        block_on(check(principal(1)).wallet_health(principal(20))).unwrap();
+        assert!(Store::get_wallet(principal(20))
+            .unwrap()
+            .1
+            .health()
+            .is_some());
+    }
+
+    #[test]
+    fn members_are_notified_once_when_a_wallet_runs_low_on_cycles() {
+        save_wallet(principal(20));
+        let management = FakeManagement::default();
+        management.add_canister(principal(20), vec![principal(100)], vec![]);
+        let proxy = FakeProxy::default();
+        let check = WalletHealthCheck::new(
+            management.clone(),
+            FakeEnvironment::default(),
+            NotificationOutbox::new(proxy, FakeEnvironment::default()),
+        );
+
+        // 10 days of cycles, then 100 days after a top up and 10 days again
+        for cycles in [10_000, 10_000, 100_000, 10_000] {
+            management.set_cycles(principal(20), cycles, 1_000);
+            block_on(check.check_wallet(principal(20))).unwrap();
+        }
+
+        let events = NotificationOutboxStorage::get_all()
+            .into_iter()
+            .map(|(_, notification)| {
+                Decode!(notification.args(), Principal, MultisigEvent)
+                    .unwrap()
+                    .1
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                MultisigEvent::LowCycles {
+                    receivers: vec![principal(1)],
+                    group_id: 1,
+                    cycles: 10_000,
+                };
+                2
+            ]
+        );
+    }
+
+    #[test]
+    fn check_wallet_requires_control() {
+        save_wallet(principal(20));
+        let management = FakeManagement::default();
+        management.add_canister(principal(20), vec![principal(100)], vec![]);
+
+        let check = WalletHealthCheck::new(
+            management,
+            FakeEnvironment::default(),
+            NotificationOutbox::new(FakeProxy::default(), FakeEnvironment::default()),
+        );
+        let health = block_on(check.check_wallet(principal(20))).unwrap();
+        assert_eq!(
+            Store::get_wallet(principal(20)).unwrap().1.health(),
+            Some(&health)
+        );
+
+        let (_, mut wallet) = Store::get_wallet(principal(20)).unwrap();
+        MultisigStorage::update(principal(20), wallet.set_controllers(vec![principal(1)])).unwrap();
+        let err = block_on(check.check_wallet(principal(20))).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BadRequest));
+    }
+}
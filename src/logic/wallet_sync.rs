@@ -0,0 +1,217 @@
+use std::{cell::RefCell, time::Duration};
+
+use candid::Principal;
+use ic_ledger_types::{AccountIdentifier, DEFAULT_SUBACCOUNT};
+
+use crate::{
+    logic::{
+        locks::{Lock, LockKey},
+        wallet_health::WalletHealthCheck,
+    },
+    services::{
+        environment::{Environment, IcEnvironment},
+        ledger_api::{IcLedger, LedgerApi},
+        multisig_api::{IcMultisig, MultisigApi},
+    },
+    storage::{
+        multisig_storage::MultisigStorage,
+        state::{WALLET_SYNC_BATCH_SIZE, WALLET_SYNC_INTERVAL},
+        storage_api::{StorageQueryable, StorageUpdateable},
+    },
+    types::{
+        page::PageArgs,
+        result::CanisterResult,
+        wallet_data::WalletData,
+        wallet_snapshot::{WalletSnapshot, WalletSyncReport},
+    },
+};
+
+thread_local! {
+    // the wallet the next timer run continues after, the timer walks over all wallets in pages
+    static SYNC_CURSOR: RefCell<Option<Principal>> = const { RefCell::new(None) };
+}
+
+/// Caches the whitelist, threshold and balances of the wallets in the index, so they can be
+/// read with the wallet queries instead of calling every wallet
+pub struct WalletSync<M = IcMultisig, L = IcLedger, E = IcEnvironment> {
+    multisig: M,
+    ledger: L,
+    env: E,
+}
+
+impl Default for WalletSync {
+    fn default() -> Self {
+        Self::new(IcMultisig, IcLedger, IcEnvironment)
+    }
+}
+
+impl WalletSync {
+    /// Syncs and health checks the next page of wallets on an interval, the interval does not
+    /// survive an upgrade so this is called on init and post upgrade
+    pub fn start_sync_timer() {
+        ic_cdk_timers::set_timer_interval(Duration::from_secs(WALLET_SYNC_INTERVAL), || {
+            ic_cdk::spawn(async {
+                // a sync spans multiple calls, a timer that fires in the meantime skips its run
+                let _lock = match Lock::acquire(LockKey::WalletSync, IcEnvironment.time()) {
+                    Some(lock) => lock,
+                    None => return,
+                };
+
+                let page = PageArgs {
+                    cursor: SYNC_CURSOR.with(|cursor| *cursor.borrow()),
+                    limit: Some(WALLET_SYNC_BATCH_SIZE),
+                };
+
+                let report = Self::default().sync_wallets(page.clone()).await;
+                WalletHealthCheck::default().check_wallets(page).await;
+                SYNC_CURSOR.with(|cursor| *cursor.borrow_mut() = report.next_cursor);
+            })
+        });
+    }
+}
+
+impl<M: MultisigApi, L: LedgerApi, E: Environment> WalletSync<M, L, E> {
+    pub fn new(multisig: M, ledger: L, env: E) -> Self {
+        Self {
+            multisig,
+            ledger,
+            env,
+        }
+    }
+
+    /// Reads the wallet and its ICP balance and stores the result as the snapshot of the wallet
+    pub async fn sync_wallet(&self, wallet: Principal) -> CanisterResult<(Principal, WalletData)> {
+        // fail early for unknown wallets, before making any calls
+        MultisigStorage::get(wallet)?;
+
+        let info = self.multisig.get_wallet_info(wallet).await?;
+        let icp_balance = self
+            .ledger
+            .account_balance(AccountIdentifier::new(&wallet, &DEFAULT_SUBACCOUNT))
+            .await?;
+
+        let snapshot = WalletSnapshot {
+            whitelist: info.whitelist,
+            threshold: info.threshold,
+            icp_balance,
+            cycles: info.cycles,
+            synced_at: self.env.time(),
+        };
+
+        // the wallet can change during the calls, read it again so those changes are kept
+        let (_, mut data) = MultisigStorage::get(wallet)?;
+        MultisigStorage::update(wallet, data.set_snapshot(snapshot))
+    }
+
+    /// Syncs the wallets of a page, wallets that can not be read keep their previous snapshot
+    pub async fn sync_wallets(&self, page: PageArgs<Principal>) -> WalletSyncReport {
+        let (wallets, next_cursor) =
+            MultisigStorage::get_page(page.cursor, page.limit(), |_, _| true);
+
+        let mut report = WalletSyncReport {
+            next_cursor,
+            ..Default::default()
+        };
+
+        for (wallet, _) in wallets {
+            match self.sync_wallet(wallet).await {
+                Ok(_) => report.synced += 1,
+                Err(err) => report.unreachable.push((wallet, err.to_string())),
+            }
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use ic_ledger_types::Tokens;
+
+    use super::WalletSync;
+    use crate::{
+        logic::store::Store,
+        services::{
+            fakes::{principal, FakeEnvironment, FakeLedger, FakeMultisig},
+            multisig_api::WalletInfo,
+        },
+        types::{page::PageArgs, wallet_data::WalletData},
+    };
+
+    // wallets 20 and 21 are known, 21 does not answer
+    fn setup() -> (
+        WalletSync<FakeMultisig, FakeLedger, FakeEnvironment>,
+        FakeMultisig,
+    ) {
+        let multisig = FakeMultisig::default();
+        let ledger = FakeLedger::default();
+
+        for wallet in [principal(20), principal(21)] {
+            Store::save_wallet(
+                wallet,
+                WalletData::new(principal(1), 0, 0, 0, 1, None, None)
+                    .set_whitelist(vec![principal(1), principal(2)]),
+            )
+            .unwrap();
+        }
+
+        multisig.set_wallet_info(
+            principal(20),
+            WalletInfo {
+                whitelist: vec![principal(2), principal(3)],
+                threshold: 2,
+                cycles: 5_000,
+                ..Default::default()
+            },
+        );
+        ledger.set_balance(principal(20), Tokens::from_e8s(42));
+
+        (
+            WalletSync::new(multisig.clone(), ledger, FakeEnvironment::default()),
+            multisig,
+        )
+    }
+
+    #[test]
+    fn sync_wallets_stores_the_snapshots() {
+        let (sync, _) = setup();
+
+        let report = block_on(sync.sync_wallets(PageArgs::default()));
+        assert_eq!(report.synced, 1);
+        assert_eq!(report.unreachable.len(), 1);
+        assert_eq!(report.unreachable[0].0, principal(21));
+        assert_eq!(report.next_cursor, None);
+
+        let (_, wallet) = Store::get_wallet(principal(20)).unwrap();
+        let snapshot = wallet.snapshot().unwrap();
+        assert_eq!(snapshot.threshold, 2);
+        assert_eq!(snapshot.cycles, 5_000);
+        assert_eq!(snapshot.icp_balance, Tokens::from_e8s(42));
+        assert_eq!(snapshot.synced_at, FakeEnvironment::default().time);
+
+        // the synced whitelist replaces the whitelist the wallet was installed with
+        assert_eq!(
+            wallet.members(),
+            vec![principal(1), principal(2), principal(3)]
+        );
+        assert!(Store::get_wallet(principal(21))
+            .unwrap()
+            .1
+            .snapshot()
+            .is_none());
+    }
+
+    #[test]
+    fn failed_sync_keeps_the_previous_snapshot() {
+        let (sync, multisig) = setup();
+        block_on(sync.sync_wallet(principal(20))).unwrap();
+
+        multisig.set_unavailable(true);
+        assert!(block_on(sync.sync_wallet(principal(20))).is_err());
+        assert!(block_on(sync.sync_wallet(principal(30))).is_err());
+
+        let (_, wallet) = Store::get_wallet(principal(20)).unwrap();
+        assert_eq!(wallet.snapshot().unwrap().threshold, 2);
+    }
+}
@@ -0,0 +1,457 @@
+use candid::{Encode, Principal};
+use ic_cdk::api::management_canister::main::{
+    CanisterInstallMode, CanisterStatusType, InstallCodeArgument,
+};
+
+use crate::{
+    logic::{event_log::EventLog, locks::Lock, notification_outbox::NotificationOutbox},
+    services::{
+        environment::{Environment, IcEnvironment},
+        management_api::{IcManagement, ManagementApi},
+        multisig_api::{IcMultisig, MultisigApi},
+        proxy_api::{IcProxy, ProxyApi},
+    },
+    storage::{
+        cell_api::CellStorage,
+        config_storage::ConfigStorage,
+        multisig_storage::MultisigStorage,
+        multisig_wasm_storage::MultisigWasmStorage,
+        storage_api::{StorageQueryable, StorageUpdateable},
+    },
+    types::{
+        canister_snapshot::CanisterSnapshot, error::Error, index_event::IndexEventKind,
+        multisig_event::MultisigEvent, result::CanisterResult, wallet_data::WalletData,
+    },
+};
+
+/// Upgrades wallets to the uploaded wasm. A canister snapshot is taken before every upgrade
+/// and loaded again when the upgraded wallet fails its health check. Owners and admins can
+/// also take, list and delete snapshots themselves, admins can load them.
+pub struct WalletUpgrade<G = IcManagement, M = IcMultisig, P = IcProxy, E = IcEnvironment> {
+    management: G,
+    multisig: M,
+    env: E,
+    outbox: NotificationOutbox<P, E>,
+}
+
+impl Default for WalletUpgrade {
+    fn default() -> Self {
+        Self::new(
+            IcManagement,
+            IcMultisig,
+            IcEnvironment,
+            NotificationOutbox::default(),
+        )
+    }
+}
+
+impl<G, M, P, E> WalletUpgrade<G, M, P, E>
+where
+    G: ManagementApi,
+    M: MultisigApi,
+    P: ProxyApi,
+    E: Environment,
+{
+    pub fn new(management: G, multisig: M, env: E, outbox: NotificationOutbox<P, E>) -> Self {
+        Self {
+            management,
+            multisig,
+            env,
+            outbox,
+        }
+    }
+
+    /// Upgrades the wallet to the uploaded wasm, the snapshot taken before the upgrade is
+    /// kept on the wallet until the next one replaces it
+    pub async fn upgrade_wallet(
+        &self,
+        canister_id: Principal,
+    ) -> CanisterResult<(Principal, WalletData)> {
+        let wallet = self.get_managed_wallet(canister_id)?;
+        let _lock = Lock::acquire_wallet(canister_id, self.env.time())?;
+
+        let wasm = MultisigWasmStorage::get()?;
+        let version = MultisigWasmStorage::version()?;
+
+        if wallet.wasm_version() == Some(&version) {
+            return Err(Error::bad_request()
+                .add_method_name("upgrade_wallet")
+                .add_info(format!("version: {}", version).as_str())
+                .add_message("The wallet already runs the uploaded wasm"));
+        }
+
+        let snapshot = self.take_stopped_snapshot(canister_id).await?;
+
+        let install = self
+            .management
+            .install_code(InstallCodeArgument {
+                mode: CanisterInstallMode::Upgrade(None),
+                canister_id,
+                wasm_module: wasm,
+                arg: Encode!().unwrap(),
+            })
+            .await;
+
+        // a failed install leaves the previous module in place, the wallet is started either way
+        self.management.start_canister(canister_id).await?;
+        install?;
+
+        if let Err(err) = self.health_check(canister_id, &version).await {
+            self.restore(canister_id, snapshot.id).await?;
+
+            return Err(Error::internal()
+                .add_method_name("upgrade_wallet")
+                .add_info(err.to_string().as_str())
+                .add_message("The upgraded wallet failed its health check and was restored"));
+        }
+
+        // the wallet can change during the calls, read it again so those changes are kept
+        let (_, mut wallet) = MultisigStorage::get(canister_id)?;
+        let result =
+            MultisigStorage::update(canister_id, wallet.set_wasm_version(version.clone()))?;
+
+        EventLog::record(
+            &self.env,
+            IndexEventKind::WalletUpgraded {
+                wallet: canister_id,
+                caller: self.env.caller(),
+                version: version.clone(),
+                snapshot_id: snapshot.id,
+            },
+        );
+
+        // a notification that can not be stored does not fail the upgrade
+        let _ = self.outbox.enqueue_event(
+            canister_id,
+            MultisigEvent::WalletUpgraded {
+                receivers: result.1.members(),
+                group_id: result.1.group_id(),
+                wasm_version: version,
+            },
+        );
+
+        Ok(result)
+    }
+
+    /// Takes a snapshot that replaces the one recorded on the wallet, the wallet is stopped
+    /// while the snapshot is taken
+    pub async fn take_snapshot(&self, canister_id: Principal) -> CanisterResult<CanisterSnapshot> {
+        self.get_managed_wallet(canister_id)?;
+        let _lock = Lock::acquire_wallet(canister_id, self.env.time())?;
+
+        let snapshot = self.take_stopped_snapshot(canister_id).await?;
+        self.management.start_canister(canister_id).await?;
+
+        Ok(snapshot)
+    }
+
+    pub async fn list_snapshots(
+        &self,
+        canister_id: Principal,
+    ) -> CanisterResult<Vec<CanisterSnapshot>> {
+        self.get_managed_wallet(canister_id)?;
+        self.management.list_canister_snapshots(canister_id).await
+    }
+
+    /// Restores the module and memory of the wallet from a snapshot. Only admins load snapshots,
+    /// it rolls back the whitelist, threshold and proposals the signers agreed on since.
+    pub async fn load_snapshot(
+        &self,
+        canister_id: Principal,
+        snapshot_id: Vec<u8>,
+    ) -> CanisterResult<(Principal, WalletData)> {
+        if !self.is_admin() {
+            return Err(Error::unauthorized()
+                .add_method_name("load_snapshot")
+                .add_message("Caller is not an admin"));
+        }
+
+        self.get_managed_wallet(canister_id)?;
+        let _lock = Lock::acquire_wallet(canister_id, self.env.time())?;
+
+        self.restore(canister_id, snapshot_id).await?;
+        MultisigStorage::get(canister_id)
+    }
+
+    pub async fn delete_snapshot(
+        &self,
+        canister_id: Principal,
+        snapshot_id: Vec<u8>,
+    ) -> CanisterResult<(Principal, WalletData)> {
+        self.get_managed_wallet(canister_id)?;
+        let _lock = Lock::acquire_wallet(canister_id, self.env.time())?;
+
+        self.management
+            .delete_canister_snapshot(canister_id, snapshot_id.clone())
+            .await?;
+
+        let (_, mut wallet) = MultisigStorage::get(canister_id)?;
+
+        match wallet.canister_snapshot() {
+            Some(snapshot) if snapshot.id == snapshot_id => {
+                MultisigStorage::update(canister_id, wallet.set_canister_snapshot(None))
+            }
+            _ => Ok((canister_id, wallet)),
+        }
+    }
+
+    fn is_admin(&self) -> bool {
+        ConfigStorage::get()
+            .map(|config| config.is_admin(self.env.caller()))
+            .unwrap_or(false)
+    }
+
+    fn get_managed_wallet(&self, canister_id: Principal) -> CanisterResult<WalletData> {
+        let (_, wallet) = MultisigStorage::get(canister_id)?;
+
+        if !wallet.is_owner(self.env.caller()) && !self.is_admin() {
+            return Err(Error::unauthorized().add_message("Caller is not the owner or an admin"));
+        }
+
+        if !wallet.is_managed_by(self.env.id()) {
+            return Err(Error::bad_request()
+                .add_message("The index is no longer a controller of the wallet"));
+        }
+
+        Ok(wallet)
+    }
+
+    /// Stops the wallet, so no call is halfway when the snapshot is taken, and records the
+    /// snapshot on the wallet. The wallet is started again when the snapshot fails.
+    async fn take_stopped_snapshot(
+        &self,
+        canister_id: Principal,
+    ) -> CanisterResult<CanisterSnapshot> {
+        let (_, wallet) = MultisigStorage::get(canister_id)?;
+        let replace = wallet
+            .canister_snapshot()
+            .map(|snapshot| snapshot.id.clone());
+
+        self.management.stop_canister(canister_id).await?;
+
+        let snapshot = match self
+            .management
+            .take_canister_snapshot(canister_id, replace)
+            .await
+        {
+            Ok(snapshot) => snapshot,
+            Err(err) => {
+                let _ = self.management.start_canister(canister_id).await;
+                return Err(err);
+            }
+        };
+
+        let (_, mut wallet) = MultisigStorage::get(canister_id)?;
+        MultisigStorage::update(
+            canister_id,
+            wallet.set_canister_snapshot(Some(snapshot.clone())),
+        )?;
+
+        Ok(snapshot)
+    }
+
+    /// Loads the snapshot into the stopped wallet and records the module it runs afterwards
+    async fn restore(&self, canister_id: Principal, snapshot_id: Vec<u8>) -> CanisterResult<()> {
+        self.management.stop_canister(canister_id).await?;
+        let load = self
+            .management
+            .load_canister_snapshot(canister_id, snapshot_id.clone())
+            .await;
+        self.management.start_canister(canister_id).await?;
+        load?;
+
+        let status = self.management.canister_status(canister_id).await?;
+
+        if let Some(module_hash) = status.module_hash {
+            let (_, mut wallet) = MultisigStorage::get(canister_id)?;
+            MultisigStorage::update(
+                canister_id,
+                wallet.set_wasm_version(MultisigWasmStorage::version_of(&module_hash)),
+            )?;
+        }
+
+        EventLog::record(
+            &self.env,
+            IndexEventKind::WalletRestored {
+                wallet: canister_id,
+                caller: self.env.caller(),
+                snapshot_id,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// The upgraded wallet runs the new module and answers a wallet call
+    async fn health_check(&self, canister_id: Principal, version: &str) -> CanisterResult<()> {
+        let status = self.management.canister_status(canister_id).await?;
+        let running = status
+            .module_hash
+            .map(|hash| MultisigWasmStorage::version_of(&hash));
+
+        if status.status != CanisterStatusType::Running || running.as_deref() != Some(version) {
+            return Err(Error::internal()
+                .add_method_name("health_check")
+                .add_message("The wallet does not run the uploaded wasm"));
+        }
+
+        self.multisig.get_wallet_info(canister_id).await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use candid::Principal;
+    use futures::executor::block_on;
+    use ic_cdk::api::management_canister::main::CanisterStatusType;
+
+    use super::WalletUpgrade;
+    use crate::{
+        logic::{notification_outbox::NotificationOutbox, store::Store},
+        services::fakes::{principal, FakeEnvironment, FakeManagement, FakeMultisig, FakeProxy},
+        storage::{
+            cell_api::CellStorage, config_storage::ConfigStorage,
+            multisig_wasm_storage::MultisigWasmStorage,
+            notification_storage::NotificationOutboxStorage, storage_api::StorageQueryable,
+        },
+        types::{
+            config::{Config, UpgradeArgs},
+            error::ErrorKind,
+            wallet_data::WalletData,
+        },
+    };
+
+    type FakeUpgrade = WalletUpgrade<FakeManagement, FakeMultisig, FakeProxy, FakeEnvironment>;
+
+    struct Fakes {
+        management: FakeManagement,
+        multisig: FakeMultisig,
+    }
+
+    // wallet 20 is owned by 1 and runs an older wasm than the uploaded one
+    fn setup() -> Fakes {
+        MultisigWasmStorage::set(vec![0, 97, 115, 109, 2]).unwrap();
+
+        let fakes = Fakes {
+            management: FakeManagement::default(),
+            multisig: FakeMultisig::default(),
+        };
+
+        fakes
+            .management
+            .add_canister(principal(20), vec![principal(100)], vec![1; 32]);
+        fakes
+            .multisig
+            .set_wallet_info(principal(20), Default::default());
+        Store::save_wallet(
+            principal(20),
+            WalletData::new(principal(1), 0, 0, 0, 1, Some("01".repeat(32)), None),
+        )
+        .unwrap();
+        fakes
+    }
+
+    fn upgrade(fakes: &Fakes, caller: Principal) -> FakeUpgrade {
+        let env = FakeEnvironment {
+            caller,
+            ..Default::default()
+        };
+
+        WalletUpgrade::new(
+            fakes.management.clone(),
+            fakes.multisig.clone(),
+            env.clone(),
+            NotificationOutbox::new(FakeProxy::default(), env),
+        )
+    }
+
+    #[test]
+    fn upgrade_wallet_takes_a_snapshot_and_records_the_version() {
+        let fakes = setup();
+
+        let (_, wallet) =
+            block_on(upgrade(&fakes, principal(1)).upgrade_wallet(principal(20))).unwrap();
+
+        let version = MultisigWasmStorage::version().unwrap();
+        assert_eq!(wallet.wasm_version(), Some(&version));
+        assert_eq!(wallet.canister_snapshot().unwrap().id, vec![1]);
+        assert_eq!(fakes.management.snapshots(principal(20)).len(), 1);
+        assert_eq!(
+            fakes.management.status(principal(20)),
+            CanisterStatusType::Running
+        );
+        assert!(fakes.management.loaded().is_empty());
+        assert_eq!(NotificationOutboxStorage::len(), 1);
+
+        let err =
+            block_on(upgrade(&fakes, principal(1)).upgrade_wallet(principal(20))).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::BadRequest));
+    }
+
+    #[test]
+    fn failed_health_check_restores_the_snapshot() {
+        let fakes = setup();
+        fakes.multisig.set_unavailable(true);
+
+        let err =
+            block_on(upgrade(&fakes, principal(1)).upgrade_wallet(principal(20))).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Internal));
+
+        assert_eq!(fakes.management.loaded(), vec![(principal(20), vec![1])]);
+        assert_eq!(
+            fakes.management.status(principal(20)),
+            CanisterStatusType::Running
+        );
+
+        let (_, wallet) = Store::get_wallet(principal(20)).unwrap();
+        assert_eq!(wallet.wasm_version(), Some(&"01".repeat(32)));
+        assert_eq!(wallet.canister_snapshot().unwrap().id, vec![1]);
+        assert_eq!(NotificationOutboxStorage::len(), 0);
+    }
+
+    #[test]
+    fn owners_take_and_delete_snapshots() {
+        let fakes = setup();
+
+        let err = block_on(upgrade(&fakes, principal(2)).take_snapshot(principal(20))).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Unauthorized));
+
+        block_on(upgrade(&fakes, principal(1)).take_snapshot(principal(20))).unwrap();
+        let snapshot =
+            block_on(upgrade(&fakes, principal(1)).take_snapshot(principal(20))).unwrap();
+
+        // the recorded snapshot is replaced
+        let snapshots =
+            block_on(upgrade(&fakes, principal(1)).list_snapshots(principal(20))).unwrap();
+        assert_eq!(snapshots, vec![snapshot.clone()]);
+
+        let (_, wallet) =
+            block_on(upgrade(&fakes, principal(1)).delete_snapshot(principal(20), snapshot.id))
+                .unwrap();
+        assert!(wallet.canister_snapshot().is_none());
+        assert!(fakes.management.snapshots(principal(20)).is_empty());
+    }
+
+    #[test]
+    fn only_admins_load_snapshots() {
+        let fakes = setup();
+        ConfigStorage::set(Config::default().apply_upgrade_args(UpgradeArgs {
+            admins: Some(vec![principal(3)]),
+            ..Default::default()
+        }))
+        .unwrap();
+
+        let snapshot =
+            block_on(upgrade(&fakes, principal(1)).take_snapshot(principal(20))).unwrap();
+
+        let err = block_on(
+            upgrade(&fakes, principal(1)).load_snapshot(principal(20), snapshot.id.clone()),
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Unauthorized));
+        assert!(fakes.management.loaded().is_empty());
+
+        block_on(upgrade(&fakes, principal(3)).load_snapshot(principal(20), snapshot.id)).unwrap();
+        assert_eq!(fakes.management.loaded(), vec![(principal(20), vec![1])]);
+    }
+}
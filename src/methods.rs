@@ -1,31 +1,87 @@
 use candid::Principal;
-use ic_cdk::{caller, id, query, update};
+use ic_cdk::{api::time, caller, init, post_upgrade, query, trap, update};
 use ic_ledger_types::Tokens;
 
 use crate::{
     logic::{
         cmc::CyclesManagement,
-        guards::{is_not_anonymous, is_prod_developer},
+        controllers::Controllers,
+        decommission::Decommission,
+        event_log::EventLog,
+        guards::{is_admin, is_not_anonymous},
+        import::Import,
         ledger::Ledger,
+        locks::Lock,
+        notification_limits::NotificationLimits,
+        notification_outbox::NotificationOutbox,
+        ownership::Ownership,
+        reconciliation::Reconciliation,
+        setup::Setup,
+        spawn::Spawn,
         store::Store,
+        wallet_health::WalletHealthCheck,
+        wallet_sync::WalletSync,
+        wallet_upgrade::WalletUpgrade,
     },
-    storage::{
-        cell_api::CellStorage, multisig_wasm_storage::MultisigWasmStorage,
-        proxy_storage::ProxyCanisterStorage,
-    },
+    storage::{cell_api::CellStorage, config_storage::ConfigStorage},
     types::{
-        error::Error, result::CanisterResult, spawn_status::SpawnStatus, wallet_data::WalletData,
+        archived_wallet::ArchivedWallet,
+        canister_snapshot::CanisterSnapshot,
+        config::{Config, IndexArgs},
+        index_event::IndexEvent,
+        ledger_transfer::LedgerTransfer,
+        multisig_event::MultisigEvent,
+        notification::Notification,
+        notification_counter::NotificationCounter,
+        owner_reconciliation::OwnerReconciliation,
+        ownership_transfer::OwnershipTransfer,
+        page::{Page, PageArgs},
+        result::CanisterResult,
+        spawn_status::SpawnStatus,
+        wallet_config::WalletConfig,
+        wallet_data::{WalletData, WalletFilter},
+        wallet_health::{FleetStatus, WalletHealth},
+        wallet_snapshot::WalletSyncReport,
+        wasm_version::WasmVersion,
     },
 };
 
+#[init]
+fn init(args: IndexArgs) {
+    if let Err(err) = Setup::default().init(args) {
+        trap(&err.to_string());
+    }
+
+    NotificationOutbox::start_delivery_timer();
+    WalletSync::start_sync_timer();
+}
+
+#[post_upgrade]
+fn post_upgrade(args: Option<IndexArgs>) {
+    if let Err(err) = Setup::default().upgrade(args) {
+        trap(&err.to_string());
+    }
+
+    // no call survives an upgrade, a lock left behind by a trapped call is released here
+    Lock::clear_all();
+
+    NotificationOutbox::start_delivery_timer();
+    WalletSync::start_sync_timer();
+}
+
+#[query]
+fn get_config() -> CanisterResult<Config> {
+    ConfigStorage::get()
+}
+
 #[query]
 fn get_cycles() -> u64 {
     Store::get_cycles()
 }
 
 #[query]
-fn get_spawns() -> Vec<(u64, SpawnStatus)> {
-    Store::get_spawns()
+fn get_spawns(page: Option<PageArgs<u64>>) -> Page<u64, SpawnStatus> {
+    Store::get_spawns(page.unwrap_or_default())
 }
 
 #[query]
@@ -34,8 +90,31 @@ fn get_spawn(blockheight: u64) -> CanisterResult<(u64, SpawnStatus)> {
 }
 
 #[query]
-fn get_wallets() -> Vec<(Principal, WalletData)> {
-    Store::get_wallets()
+fn get_ledger_transfers(icp_transfer_blockheight: u64) -> Vec<LedgerTransfer> {
+    Ledger::get_transfers(icp_transfer_blockheight)
+}
+
+#[query]
+fn get_events(from_id: u64, limit: Option<u64>) -> Vec<(u64, IndexEvent)> {
+    EventLog::get_events(from_id, limit)
+}
+
+#[query]
+fn get_wallets(
+    page: Option<PageArgs<Principal>>,
+    filter: Option<WalletFilter>,
+) -> Page<Principal, WalletData> {
+    Store::get_wallets(page.unwrap_or_default(), filter.unwrap_or_default())
+}
+
+#[query]
+fn get_wallet_by_group(group_id: u64) -> CanisterResult<(Principal, WalletData)> {
+    Store::get_wallet_by_group(group_id)
+}
+
+#[query]
+fn get_wallets_by_owner(owner: Principal) -> Vec<(Principal, WalletData)> {
+    Store::get_wallets_by_owner(owner)
 }
 
 #[update(guard = "is_not_anonymous")]
@@ -43,93 +122,14 @@ async fn spawn_wallet(
     icp_transfer_blockheight: u64,
     whitelist: Vec<Principal>,
     group_id: u64,
+    label: Option<String>,
+    config: Option<WalletConfig>,
 ) -> CanisterResult<Principal> {
-    Store::validate_whitelist(&whitelist)?;
-
-    // check if spawn already exists
-    if Store::get_spawn(icp_transfer_blockheight).is_ok() {
-        return Err(Error::bad_request()
-            .add_message(format!("Duplicate blockheight: {}", icp_transfer_blockheight).as_str()));
-    }
-
-    // initialize new spawn status tracker
-    let mut spawn_status = SpawnStatus::new(Some("Wallet spawn".to_string()));
-    Store::save_status(icp_transfer_blockheight, spawn_status.clone())?;
-
-    // validate ICP transaction
-    let amount = Ledger::validate_transaction(caller(), icp_transfer_blockheight).await?;
-
-    Store::update_status(
-        icp_transfer_blockheight,
-        spawn_status.transaction_valid(amount),
-    )?;
-
-    let minimum_spawn_icp_amount = CyclesManagement::get_minimum_spawn_icp_amount().await?;
-
-    // if amount is less than minimum required, transfer ICP back to caller
-    if amount < minimum_spawn_icp_amount {
-        let transfer_back_blockheight = Ledger::transfer_icp_back_to_caller(amount).await?;
-
-        Store::update_status(
-            icp_transfer_blockheight,
-            spawn_status.min_amount_error(transfer_back_blockheight),
-        )?;
-
-        return Err(Error::insufficient_balance().add_message(
-            format!(
-                "Amount ({}) is less than {}, ICP transferred back: blockheight: {}",
-                amount,
-                minimum_spawn_icp_amount.e8s(),
-                transfer_back_blockheight
-            )
-            .as_str(),
-        ));
-    }
-
-    // transfer ICP to the cycles management canister
-    let cmc_transfer_block_height = Ledger::transfer_icp_to_cmc(amount, id()).await?;
-
-    Store::update_status(
-        icp_transfer_blockheight,
-        spawn_status.transferred_to_cmc(cmc_transfer_block_height),
-    )?;
-
-    // top up this canister with cycles
-    let cycles = CyclesManagement::top_up(cmc_transfer_block_height, id()).await?;
-
-    Store::update_status(
-        icp_transfer_blockheight,
-        spawn_status.topped_up_self(cycles.clone()),
-    )?;
-
-    // spawn a new canister
-    let canister_id = Store::spawn_canister(cycles).await?;
-
-    Store::update_status(
-        icp_transfer_blockheight,
-        spawn_status.canister_spawned(canister_id),
-    )?;
-
-    // install the wallet canister
-    let installed_canister_principal =
-        Store::install_canister(canister_id, whitelist, group_id).await?;
-
-    Store::update_status(
-        icp_transfer_blockheight,
-        spawn_status.canister_installed(installed_canister_principal),
-    )?;
-
-    // save the wallet data
-    Store::save_wallet(
-        installed_canister_principal,
-        icp_transfer_blockheight,
-        cmc_transfer_block_height,
-        group_id,
-    )?;
-
-    Store::update_status(icp_transfer_blockheight, spawn_status.done())?;
-
-    Ok(installed_canister_principal)
+    let result = Spawn::default()
+        .spawn_wallet(icp_transfer_blockheight, whitelist, group_id, label, config)
+        .await;
+    NotificationOutbox::schedule_delivery();
+    result
 }
 
 #[update(guard = "is_not_anonymous")]
@@ -137,56 +137,137 @@ async fn top_up_wallet(
     icp_transfer_blockheight: u64,
     wallet_principal: Principal,
 ) -> CanisterResult<()> {
-    // check if spawn already exists
-    if Store::get_spawn(icp_transfer_blockheight).is_ok() {
-        return Err(Error::bad_request()
-            .add_message(format!("Duplicate blockheight: {}", icp_transfer_blockheight).as_str()));
-    }
+    let result = Spawn::default()
+        .top_up_wallet(icp_transfer_blockheight, wallet_principal)
+        .await;
+    NotificationOutbox::schedule_delivery();
+    result
+}
 
-    // initialize new status tracker
-    let mut spawn_status = SpawnStatus::new(Some("Top up wallet".to_string()));
-    Store::save_status(icp_transfer_blockheight, spawn_status.clone())?;
+#[update(guard = "is_not_anonymous")]
+async fn propose_ownership_transfer(
+    canister_id: Principal,
+    new_owner: Principal,
+) -> CanisterResult<(Principal, OwnershipTransfer)> {
+    Ownership::default()
+        .propose_transfer(canister_id, new_owner)
+        .await
+}
 
-    // validate ICP transaction
-    let amount = Ledger::validate_transaction(caller(), icp_transfer_blockheight).await?;
+#[update(guard = "is_not_anonymous")]
+async fn accept_ownership(canister_id: Principal) -> CanisterResult<(Principal, WalletData)> {
+    let result = Ownership::default().accept_transfer(canister_id).await;
+    NotificationOutbox::schedule_delivery();
+    result
+}
+
+#[update(guard = "is_not_anonymous")]
+fn cancel_ownership_transfer(canister_id: Principal) -> CanisterResult<()> {
+    Ownership::default().cancel_transfer(canister_id)
+}
 
-    Store::update_status(
-        icp_transfer_blockheight,
-        spawn_status.transaction_valid(amount),
-    )?;
+#[update(guard = "is_not_anonymous")]
+async fn import_wallet(
+    canister_id: Principal,
+    label: Option<String>,
+) -> CanisterResult<(Principal, WalletData)> {
+    Import::default().import_wallet(canister_id, label).await
+}
 
-    // transfer ICP to the cycles management canister
-    let cmc_transfer_block_height = Ledger::transfer_icp_to_cmc(amount, wallet_principal).await?;
+#[update(guard = "is_not_anonymous")]
+async fn decommission_wallet(
+    canister_id: Principal,
+    force: Option<bool>,
+) -> CanisterResult<(Principal, ArchivedWallet)> {
+    Decommission::default()
+        .decommission_wallet(canister_id, force.unwrap_or(false))
+        .await
+}
 
-    Store::update_status(
-        icp_transfer_blockheight,
-        spawn_status.transferred_to_cmc(cmc_transfer_block_height),
-    )?;
+#[update(guard = "is_not_anonymous")]
+async fn upgrade_wallet(canister_id: Principal) -> CanisterResult<(Principal, WalletData)> {
+    let result = WalletUpgrade::default().upgrade_wallet(canister_id).await;
+    NotificationOutbox::schedule_delivery();
+    result
+}
 
-    // top up this canister with cycles
-    let cycles = CyclesManagement::top_up(cmc_transfer_block_height, wallet_principal).await?;
+#[update(guard = "is_not_anonymous")]
+async fn take_wallet_snapshot(canister_id: Principal) -> CanisterResult<CanisterSnapshot> {
+    WalletUpgrade::default().take_snapshot(canister_id).await
+}
 
-    Store::update_status(
-        icp_transfer_blockheight,
-        spawn_status.topped_up_self(cycles.clone()),
-    )?;
+#[update(guard = "is_not_anonymous")]
+async fn list_wallet_snapshots(canister_id: Principal) -> CanisterResult<Vec<CanisterSnapshot>> {
+    WalletUpgrade::default().list_snapshots(canister_id).await
+}
 
-    Store::update_status(icp_transfer_blockheight, spawn_status.done())?;
+#[update(guard = "is_not_anonymous")]
+async fn load_wallet_snapshot(
+    canister_id: Principal,
+    snapshot_id: Vec<u8>,
+) -> CanisterResult<(Principal, WalletData)> {
+    WalletUpgrade::default()
+        .load_snapshot(canister_id, snapshot_id)
+        .await
+}
 
-    Ok(())
+#[update(guard = "is_not_anonymous")]
+async fn delete_wallet_snapshot(
+    canister_id: Principal,
+    snapshot_id: Vec<u8>,
+) -> CanisterResult<(Principal, WalletData)> {
+    WalletUpgrade::default()
+        .delete_snapshot(canister_id, snapshot_id)
+        .await
 }
 
 #[update(guard = "is_not_anonymous")]
-async fn transfer_ownership(
+async fn add_wallet_controllers(
     canister_id: Principal,
-    new_owner: Principal,
+    controllers: Vec<Principal>,
 ) -> CanisterResult<(Principal, WalletData)> {
-    Store::transfer_ownership(canister_id, new_owner).await
+    Controllers::default()
+        .add_controllers(canister_id, controllers)
+        .await
+}
+
+#[update(guard = "is_not_anonymous")]
+async fn release_wallet_control(canister_id: Principal) -> CanisterResult<(Principal, WalletData)> {
+    Controllers::default().release_control(canister_id).await
+}
+
+#[update(guard = "is_not_anonymous")]
+async fn wallet_health(canister_id: Principal) -> CanisterResult<WalletHealth> {
+    let result = WalletHealthCheck::default()
+        .wallet_health(canister_id)
+        .await;
+    NotificationOutbox::schedule_delivery();
+    result
+}
+
+#[query]
+fn get_fleet_status() -> FleetStatus {
+    WalletHealthCheck::get_fleet_status()
+}
+
+#[query]
+fn get_archived_wallets(page: Option<PageArgs<Principal>>) -> Page<Principal, ArchivedWallet> {
+    Decommission::get_archived_wallets(page.unwrap_or_default())
+}
+
+#[query]
+fn get_pending_ownership_transfers(
+    page: Option<PageArgs<Principal>>,
+    principal: Option<Principal>,
+) -> Page<Principal, OwnershipTransfer> {
+    Ownership::default().get_pending_transfers(page.unwrap_or_default(), principal)
 }
 
 #[update]
 async fn get_minimum_spawn_icp_amount() -> CanisterResult<Tokens> {
-    CyclesManagement::get_minimum_spawn_icp_amount().await
+    CyclesManagement::default()
+        .get_minimum_spawn_icp_amount()
+        .await
 }
 
 #[query]
@@ -199,31 +280,78 @@ pub fn icts_version() -> String {
     env!("CARGO_PKG_VERSION").to_string()
 }
 
-#[update(guard = "is_prod_developer")]
+#[update(guard = "is_admin")]
 fn _dev_add_wallet(canister_id: Principal) -> bool {
-    Store::_test_add_wallet(canister_id).is_ok()
+    Store::_test_add_wallet(canister_id, caller(), time()).is_ok()
+}
+
+#[query(guard = "is_admin")]
+fn get_notification_outbox(page: Option<PageArgs<u64>>) -> Page<u64, Notification> {
+    NotificationOutbox::get_outbox(page.unwrap_or_default())
+}
+
+#[query(guard = "is_admin")]
+fn get_dead_letters(page: Option<PageArgs<u64>>) -> Page<u64, Notification> {
+    NotificationOutbox::get_dead_letters(page.unwrap_or_default())
 }
 
-#[update(guard = "is_prod_developer")]
-fn _dev_set_proxy(canister_id: Principal) -> bool {
-    ProxyCanisterStorage::set(canister_id).is_ok()
+#[query(guard = "is_admin")]
+fn get_notification_counters(
+    page: Option<PageArgs<Principal>>,
+) -> Page<Principal, NotificationCounter> {
+    NotificationLimits::get_counters(page.unwrap_or_default())
 }
 
-#[update(guard = "is_prod_developer")]
+#[query(composite = true, guard = "is_admin")]
+async fn get_owner_mismatches(page: Option<PageArgs<Principal>>) -> OwnerReconciliation {
+    Reconciliation::default()
+        .check_owners(page.unwrap_or_default())
+        .await
+}
+
+#[update(guard = "is_admin")]
+async fn repair_owners(page: Option<PageArgs<Principal>>) -> CanisterResult<OwnerReconciliation> {
+    Reconciliation::default()
+        .repair_owners(page.unwrap_or_default())
+        .await
+}
+
+#[update(guard = "is_admin")]
+async fn sync_wallets(page: Option<PageArgs<Principal>>) -> WalletSyncReport {
+    WalletSync::default()
+        .sync_wallets(page.unwrap_or_default())
+        .await
+}
+
+#[update(guard = "is_admin")]
+async fn check_wallets_health(page: Option<PageArgs<Principal>>) -> WalletSyncReport {
+    let report = WalletHealthCheck::default()
+        .check_wallets(page.unwrap_or_default())
+        .await;
+    NotificationOutbox::schedule_delivery();
+    report
+}
+
+#[update(guard = "is_admin")]
+fn replay_dead_letter(id: u64) -> CanisterResult<u64> {
+    let result = NotificationOutbox::default().replay_dead_letter(id);
+    NotificationOutbox::schedule_delivery();
+    result
+}
+
+#[update(guard = "is_admin")]
 fn _dev_upload_multisig_wasm(wasm: Vec<u8>) -> bool {
-    MultisigWasmStorage::set(wasm).is_ok()
+    Setup::default().upload_wasm(wasm).is_ok()
 }
 
-#[update(guard = "is_prod_developer")]
-fn _dev_prod_init() -> CanisterResult<()> {
-    if id().to_string() != "o7ouu-niaaa-aaaap-ahhdq-cai" {
-        return Err(
-            Error::unsupported().add_message("This canister is not the production canister")
-        );
-    }
+#[update(guard = "is_admin")]
+fn register_wasm_version(version: String) -> CanisterResult<()> {
+    Setup::default().register_wasm_version(version)
+}
 
-    let _ = ProxyCanisterStorage::set(Principal::from_text("2jvhk-5aaaa-aaaap-ahewa-cai").unwrap());
-    Ok(())
+#[query]
+fn get_wasm_versions() -> Vec<(String, WasmVersion)> {
+    Setup::get_wasm_versions()
 }
 
 #[query]
@@ -0,0 +1,43 @@
+use std::future::Future;
+
+use crate::{
+    services::cmc_service::{
+        CmcService, IcpXdrConversionRateResponse, NotifyTopUpArg, NotifyTopUpResult,
+    },
+    storage::{cell_api::CellStorage, config_storage::ConfigStorage},
+    types::{error::Error, result::CanisterResult},
+};
+
+/// Calls to the cycles minting canister
+pub trait CmcApi {
+    fn notify_top_up(
+        &self,
+        arg: NotifyTopUpArg,
+    ) -> impl Future<Output = CanisterResult<NotifyTopUpResult>>;
+
+    fn get_icp_xdr_conversion_rate(
+        &self,
+    ) -> impl Future<Output = CanisterResult<IcpXdrConversionRateResponse>>;
+}
+
+/// The cycles minting canister configured in the `ConfigStorage`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IcCmc;
+
+impl CmcApi for IcCmc {
+    async fn notify_top_up(&self, arg: NotifyTopUpArg) -> CanisterResult<NotifyTopUpResult> {
+        CmcService(ConfigStorage::get()?.cmc_canister_id())
+            .notify_top_up(arg)
+            .await
+            .map(|(result,)| result)
+            .map_err(|e| Error::internal().add_message(e.1.as_str()))
+    }
+
+    async fn get_icp_xdr_conversion_rate(&self) -> CanisterResult<IcpXdrConversionRateResponse> {
+        CmcService(ConfigStorage::get()?.cmc_canister_id())
+            .get_icp_xdr_conversion_rate()
+            .await
+            .map(|(rate,)| rate)
+            .map_err(|_| Error::bad_request().add_message("Error getting XDR conversion rate"))
+    }
+}
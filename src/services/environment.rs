@@ -0,0 +1,30 @@
+use candid::Principal;
+
+/// The system api of the canister, abstracted so the logic can run outside of a canister
+pub trait Environment {
+    fn caller(&self) -> Principal;
+    fn id(&self) -> Principal;
+    fn time(&self) -> u64;
+    fn canister_balance(&self) -> u64;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IcEnvironment;
+
+impl Environment for IcEnvironment {
+    fn caller(&self) -> Principal {
+        ic_cdk::caller()
+    }
+
+    fn id(&self) -> Principal {
+        ic_cdk::id()
+    }
+
+    fn time(&self) -> u64 {
+        ic_cdk::api::time()
+    }
+
+    fn canister_balance(&self) -> u64 {
+        ic_cdk::api::canister_balance()
+    }
+}
@@ -0,0 +1,726 @@
+//! In-memory implementations of the service traits for unit tests. Each fake shares its
+//! state through an `Rc`, so a clone handed to the logic can be inspected by the test.
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use candid::{Nat, Principal};
+use ic_cdk::api::management_canister::main::{
+    CanisterStatusResponse, CanisterStatusType, CreateCanisterArgument, DefiniteCanisterSettings,
+    InstallCodeArgument, QueryStats,
+};
+use ic_ledger_types::{
+    AccountIdentifier, Block, GetBlocksArgs, GetBlocksResult, Memo, Operation, QueryArchiveFn,
+    QueryBlocksResponse, Timestamp, Tokens, Transaction, TransferArgs, TransferError,
+    TransferResult, DEFAULT_SUBACCOUNT,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    services::{
+        cmc_api::CmcApi,
+        cmc_service::{
+            IcpXdrConversionRate, IcpXdrConversionRateResponse, NotifyTopUpArg, NotifyTopUpResult,
+        },
+        environment::Environment,
+        ledger_api::LedgerApi,
+        management_api::ManagementApi,
+        multisig_api::{MultisigApi, WalletInfo},
+        proxy_api::{GroupMember, ProxyApi},
+    },
+    types::{canister_snapshot::CanisterSnapshot, error::Error, result::CanisterResult},
+};
+
+pub fn principal(id: u8) -> Principal {
+    Principal::from_slice(&[id, 1])
+}
+
+/// Time of the machine running the tests, for code that has no environment to ask, such as
+/// the timestamp of an error
+pub fn system_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+#[derive(Clone)]
+pub struct FakeEnvironment {
+    pub caller: Principal,
+    pub id: Principal,
+    pub time: u64,
+}
+
+impl Default for FakeEnvironment {
+    fn default() -> Self {
+        Self {
+            caller: principal(1),
+            id: principal(100),
+            time: 1_700_000_000_000_000_000,
+        }
+    }
+}
+
+impl Environment for FakeEnvironment {
+    fn caller(&self) -> Principal {
+        self.caller
+    }
+
+    fn id(&self) -> Principal {
+        self.id
+    }
+
+    fn time(&self) -> u64 {
+        self.time
+    }
+
+    fn canister_balance(&self) -> u64 {
+        0
+    }
+}
+
+#[derive(Default)]
+pub struct FakeLedgerState {
+    pub blocks: Vec<Block>,
+    pub transfers: Vec<TransferArgs>,
+    /// Transfers that are executed by the ledger but whose reply is lost
+    pub drop_replies: usize,
+    /// Transfers created before this time are rejected as too old
+    pub too_old_before: Option<u64>,
+    pub balances: HashMap<AccountIdentifier, Tokens>,
+    /// ICRC-1 balances by token ledger and owner
+    pub token_balances: HashMap<(Principal, Principal), Nat>,
+}
+
+#[derive(Clone, Default)]
+pub struct FakeLedger(pub Rc<RefCell<FakeLedgerState>>);
+
+impl FakeLedger {
+    /// Records a transfer from `from` to `to` as if it was made by a user, returns its block index
+    pub fn add_transfer(&self, from: Principal, to: Principal, amount: Tokens) -> u64 {
+        let mut state = self.0.borrow_mut();
+        state.blocks.push(Block {
+            parent_hash: None,
+            transaction: Transaction {
+                memo: Memo(0),
+                operation: Some(Operation::Transfer {
+                    from: AccountIdentifier::new(&from, &DEFAULT_SUBACCOUNT),
+                    to: AccountIdentifier::new(&to, &DEFAULT_SUBACCOUNT),
+                    amount,
+                    fee: Tokens::from_e8s(10_000),
+                }),
+                created_at_time: Timestamp { timestamp_nanos: 0 },
+                icrc1_memo: None,
+            },
+            timestamp: Timestamp { timestamp_nanos: 0 },
+        });
+        state.blocks.len() as u64 - 1
+    }
+
+    pub fn transfers(&self) -> Vec<TransferArgs> {
+        self.0.borrow().transfers.clone()
+    }
+
+    pub fn set_balance(&self, owner: Principal, balance: Tokens) {
+        self.0
+            .borrow_mut()
+            .balances
+            .insert(AccountIdentifier::new(&owner, &DEFAULT_SUBACCOUNT), balance);
+    }
+
+    pub fn set_token_balance(&self, token: Principal, owner: Principal, balance: u64) {
+        self.0
+            .borrow_mut()
+            .token_balances
+            .insert((token, owner), Nat::from(balance));
+    }
+}
+
+impl LedgerApi for FakeLedger {
+    async fn transfer(&self, args: TransferArgs) -> CanisterResult<TransferResult> {
+        let mut state = self.0.borrow_mut();
+
+        let duplicate_of = state.transfers.iter().position(|existing| {
+            existing.memo == args.memo
+                && existing.amount == args.amount
+                && existing.to == args.to
+                && existing.created_at_time == args.created_at_time
+        });
+
+        let created_at_time = args.created_at_time.map(|time| time.timestamp_nanos);
+        if created_at_time < state.too_old_before {
+            return Ok(Err(TransferError::TxTooOld {
+                allowed_window_nanos: 24 * 60 * 60 * 1_000_000_000,
+            }));
+        }
+
+        if let Some(duplicate_of) = duplicate_of {
+            return Ok(Err(TransferError::TxDuplicate {
+                duplicate_of: duplicate_of as u64,
+            }));
+        }
+
+        state.transfers.push(args);
+        let block_index = state.transfers.len() as u64 - 1;
+
+        if state.drop_replies > 0 {
+            state.drop_replies -= 1;
+            return Err(Error::internal().add_message("reply lost"));
+        }
+
+        Ok(Ok(block_index))
+    }
+
+    async fn query_blocks(&self, args: GetBlocksArgs) -> CanisterResult<QueryBlocksResponse> {
+        let state = self.0.borrow();
+
+        Ok(QueryBlocksResponse {
+            chain_length: state.blocks.len() as u64,
+            certificate: None,
+            blocks: state
+                .blocks
+                .iter()
+                .skip(args.start as usize)
+                .take(args.length as usize)
+                .cloned()
+                .collect(),
+            first_block_index: args.start,
+            archived_blocks: vec![],
+        })
+    }
+
+    async fn query_archived_blocks(
+        &self,
+        _func: &QueryArchiveFn,
+        _args: GetBlocksArgs,
+    ) -> CanisterResult<GetBlocksResult> {
+        Err(Error::not_implemented())
+    }
+
+    async fn account_balance(&self, account: AccountIdentifier) -> CanisterResult<Tokens> {
+        Ok(self
+            .0
+            .borrow()
+            .balances
+            .get(&account)
+            .copied()
+            .unwrap_or(Tokens::from_e8s(0)))
+    }
+
+    async fn token_balance(&self, token: Principal, owner: Principal) -> CanisterResult<Nat> {
+        Ok(self
+            .0
+            .borrow()
+            .token_balances
+            .get(&(token, owner))
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+pub struct FakeCmcState {
+    pub xdr_permyriad_per_icp: u64,
+    pub cycles_per_top_up: u128,
+    pub top_ups: Vec<(u64, Principal)>,
+}
+
+#[derive(Clone)]
+pub struct FakeCmc(pub Rc<RefCell<FakeCmcState>>);
+
+impl Default for FakeCmc {
+    fn default() -> Self {
+        Self(Rc::new(RefCell::new(FakeCmcState {
+            // 5 XDR per ICP, the minimum spawn amount is 1 ICP plus the catalyze fee
+            xdr_permyriad_per_icp: 50_000,
+            cycles_per_top_up: 5_000_000_000_000,
+            top_ups: vec![],
+        })))
+    }
+}
+
+impl FakeCmc {
+    pub fn top_ups(&self) -> Vec<(u64, Principal)> {
+        self.0.borrow().top_ups.clone()
+    }
+}
+
+impl CmcApi for FakeCmc {
+    async fn notify_top_up(&self, arg: NotifyTopUpArg) -> CanisterResult<NotifyTopUpResult> {
+        let mut state = self.0.borrow_mut();
+        state.top_ups.push((arg.block_index, arg.canister_id));
+        Ok(NotifyTopUpResult::Ok(Nat::from(state.cycles_per_top_up)))
+    }
+
+    async fn get_icp_xdr_conversion_rate(&self) -> CanisterResult<IcpXdrConversionRateResponse> {
+        Ok(IcpXdrConversionRateResponse {
+            certificate: vec![],
+            data: IcpXdrConversionRate {
+                xdr_permyriad_per_icp: self.0.borrow().xdr_permyriad_per_icp,
+                timestamp_seconds: 0,
+            },
+            hash_tree: vec![],
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct FakeManagementState {
+    pub created: Vec<(Principal, u128)>,
+    pub installed: HashMap<Principal, Vec<u8>>,
+    /// Canisters that are not in the map are running
+    pub statuses: HashMap<Principal, CanisterStatusType>,
+    pub deleted: Vec<Principal>,
+    /// Calls to stop a canister fail while set
+    pub fail_stop: bool,
+    pub controllers: HashMap<Principal, Vec<Principal>>,
+    pub module_hashes: HashMap<Principal, Vec<u8>>,
+    pub snapshots: HashMap<Principal, Vec<CanisterSnapshot>>,
+    /// The module hash each snapshot was taken with, restored when it is loaded
+    pub snapshot_modules: HashMap<(Principal, Vec<u8>), Option<Vec<u8>>>,
+    /// Snapshots that were loaded, with the canister they were loaded into
+    pub loaded: Vec<(Principal, Vec<u8>)>,
+    /// Calls to install code fail while set
+    pub fail_install: bool,
+    /// The cycles and idle burn per day of a canister, zero when not set
+    pub cycles: HashMap<Principal, (u64, u64)>,
+}
+
+#[derive(Clone, Default)]
+pub struct FakeManagement(pub Rc<RefCell<FakeManagementState>>);
+
+impl FakeManagement {
+    pub fn created(&self) -> Vec<(Principal, u128)> {
+        self.0.borrow().created.clone()
+    }
+
+    pub fn install_arg(&self, canister_id: Principal) -> Option<Vec<u8>> {
+        self.0.borrow().installed.get(&canister_id).cloned()
+    }
+
+    pub fn status(&self, canister_id: Principal) -> CanisterStatusType {
+        self.0
+            .borrow()
+            .statuses
+            .get(&canister_id)
+            .cloned()
+            .unwrap_or(CanisterStatusType::Running)
+    }
+
+    pub fn deleted(&self) -> Vec<Principal> {
+        self.0.borrow().deleted.clone()
+    }
+
+    pub fn set_fail_stop(&self, fail_stop: bool) {
+        self.0.borrow_mut().fail_stop = fail_stop;
+    }
+
+    pub fn controllers(&self, canister_id: Principal) -> Option<Vec<Principal>> {
+        self.0.borrow().controllers.get(&canister_id).cloned()
+    }
+
+    pub fn snapshots(&self, canister_id: Principal) -> Vec<CanisterSnapshot> {
+        self.0
+            .borrow()
+            .snapshots
+            .get(&canister_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn loaded(&self) -> Vec<(Principal, Vec<u8>)> {
+        self.0.borrow().loaded.clone()
+    }
+
+    pub fn set_fail_install(&self, fail_install: bool) {
+        self.0.borrow_mut().fail_install = fail_install;
+    }
+
+    /// Adds a canister that was not created through the fake, such as a wallet to import
+    pub fn add_canister(
+        &self,
+        canister_id: Principal,
+        controllers: Vec<Principal>,
+        module_hash: Vec<u8>,
+    ) {
+        let mut state = self.0.borrow_mut();
+        state.controllers.insert(canister_id, controllers);
+        state.module_hashes.insert(canister_id, module_hash);
+    }
+
+    pub fn set_cycles(&self, canister_id: Principal, cycles: u64, burned_per_day: u64) {
+        self.0
+            .borrow_mut()
+            .cycles
+            .insert(canister_id, (cycles, burned_per_day));
+    }
+}
+
+impl ManagementApi for FakeManagement {
+    async fn create_canister(
+        &self,
+        _args: CreateCanisterArgument,
+        cycles: u128,
+    ) -> CanisterResult<Principal> {
+        let mut state = self.0.borrow_mut();
+        let canister_id = principal(200 + state.created.len() as u8);
+        state.created.push((canister_id, cycles));
+        Ok(canister_id)
+    }
+
+    async fn install_code(&self, args: InstallCodeArgument) -> CanisterResult<()> {
+        let mut state = self.0.borrow_mut();
+
+        if state.fail_install {
+            return Err(Error::internal().add_message("install failed"));
+        }
+
+        state
+            .module_hashes
+            .insert(args.canister_id, Sha256::digest(&args.wasm_module).to_vec());
+        state.installed.insert(args.canister_id, args.arg);
+        Ok(())
+    }
+
+    async fn canister_status(
+        &self,
+        canister_id: Principal,
+    ) -> CanisterResult<CanisterStatusResponse> {
+        if self.0.borrow().deleted.contains(&canister_id) {
+            return Err(Error::not_found().add_message("canister not found"));
+        }
+
+        let state = self.0.borrow();
+        let (cycles, burned_per_day) = state.cycles.get(&canister_id).copied().unwrap_or_default();
+
+        Ok(CanisterStatusResponse {
+            status: self.status(canister_id),
+            settings: DefiniteCanisterSettings {
+                controllers: state
+                    .controllers
+                    .get(&canister_id)
+                    .cloned()
+                    .unwrap_or_default(),
+                ..Default::default()
+            },
+            module_hash: state.module_hashes.get(&canister_id).cloned(),
+            memory_size: Nat::from(0_u64),
+            cycles: Nat::from(cycles),
+            idle_cycles_burned_per_day: Nat::from(burned_per_day),
+            query_stats: QueryStats {
+                num_calls_total: Nat::from(0_u64),
+                num_instructions_total: Nat::from(0_u64),
+                request_payload_bytes_total: Nat::from(0_u64),
+                response_payload_bytes_total: Nat::from(0_u64),
+            },
+            reserved_cycles: Nat::from(0_u64),
+        })
+    }
+
+    async fn start_canister(&self, canister_id: Principal) -> CanisterResult<()> {
+        self.0
+            .borrow_mut()
+            .statuses
+            .insert(canister_id, CanisterStatusType::Running);
+        Ok(())
+    }
+
+    async fn stop_canister(&self, canister_id: Principal) -> CanisterResult<()> {
+        let mut state = self.0.borrow_mut();
+
+        if state.fail_stop {
+            return Err(Error::internal().add_message("stop failed"));
+        }
+
+        state
+            .statuses
+            .insert(canister_id, CanisterStatusType::Stopped);
+        Ok(())
+    }
+
+    async fn delete_canister(&self, canister_id: Principal) -> CanisterResult<()> {
+        let mut state = self.0.borrow_mut();
+
+        if state.statuses.get(&canister_id) != Some(&CanisterStatusType::Stopped) {
+            return Err(Error::bad_request().add_message("canister is not stopped"));
+        }
+
+        state.deleted.push(canister_id);
+        Ok(())
+    }
+
+    async fn set_controllers(
+        &self,
+        canister_id: Principal,
+        controllers: Vec<Principal>,
+    ) -> CanisterResult<()> {
+        self.0
+            .borrow_mut()
+            .controllers
+            .insert(canister_id, controllers);
+        Ok(())
+    }
+
+    // snapshot ids count up from 1 per canister
+    async fn take_canister_snapshot(
+        &self,
+        canister_id: Principal,
+        replace: Option<Vec<u8>>,
+    ) -> CanisterResult<CanisterSnapshot> {
+        let mut state = self.0.borrow_mut();
+        let snapshots = state.snapshots.entry(canister_id).or_default();
+
+        if let Some(replace) = replace {
+            snapshots.retain(|snapshot| snapshot.id != replace);
+        }
+
+        let id = snapshots
+            .iter()
+            .map(|snapshot| snapshot.id[0])
+            .max()
+            .unwrap_or(0)
+            + 1;
+        let snapshot = CanisterSnapshot {
+            id: vec![id],
+            taken_at_timestamp: 0,
+            total_size: 0,
+        };
+        snapshots.push(snapshot.clone());
+
+        let module_hash = state.module_hashes.get(&canister_id).cloned();
+        state
+            .snapshot_modules
+            .insert((canister_id, snapshot.id.clone()), module_hash);
+        Ok(snapshot)
+    }
+
+    async fn list_canister_snapshots(
+        &self,
+        canister_id: Principal,
+    ) -> CanisterResult<Vec<CanisterSnapshot>> {
+        Ok(self.snapshots(canister_id))
+    }
+
+    async fn load_canister_snapshot(
+        &self,
+        canister_id: Principal,
+        snapshot_id: Vec<u8>,
+    ) -> CanisterResult<()> {
+        if !self
+            .snapshots(canister_id)
+            .iter()
+            .any(|snapshot| snapshot.id == snapshot_id)
+        {
+            return Err(Error::not_found().add_message("snapshot not found"));
+        }
+
+        let mut state = self.0.borrow_mut();
+
+        match state
+            .snapshot_modules
+            .get(&(canister_id, snapshot_id.clone()))
+            .cloned()
+            .flatten()
+        {
+            Some(module_hash) => state.module_hashes.insert(canister_id, module_hash),
+            None => state.module_hashes.remove(&canister_id),
+        };
+
+        state.loaded.push((canister_id, snapshot_id));
+        Ok(())
+    }
+
+    async fn delete_canister_snapshot(
+        &self,
+        canister_id: Principal,
+        snapshot_id: Vec<u8>,
+    ) -> CanisterResult<()> {
+        let mut state = self.0.borrow_mut();
+        let snapshots = state.snapshots.entry(canister_id).or_default();
+
+        if !snapshots.iter().any(|snapshot| snapshot.id == snapshot_id) {
+            return Err(Error::not_found().add_message("snapshot not found"));
+        }
+
+        snapshots.retain(|snapshot| snapshot.id != snapshot_id);
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct FakeProxyState {
+    pub members: HashMap<(u64, Principal), GroupMember>,
+    pub notifications: Vec<(String, Vec<u8>)>,
+    /// Notifications are rejected while the proxy is unavailable
+    pub unavailable: bool,
+    /// Methods the proxy does not have, like `multisig_notification` on the deployed proxy
+    pub missing_methods: Vec<String>,
+}
+
+#[derive(Clone, Default)]
+pub struct FakeProxy(pub Rc<RefCell<FakeProxyState>>);
+
+impl FakeProxy {
+    pub fn add_member(&self, group_id: u64, principal: Principal, roles: &[&str]) {
+        let roles = roles.iter().map(|role| role.to_string()).collect();
+        self.0
+            .borrow_mut()
+            .members
+            .insert((group_id, principal), GroupMember { roles });
+    }
+
+    pub fn remove_member(&self, group_id: u64, principal: Principal) {
+        self.0.borrow_mut().members.remove(&(group_id, principal));
+    }
+
+    pub fn set_unavailable(&self, unavailable: bool) {
+        self.0.borrow_mut().unavailable = unavailable;
+    }
+
+    pub fn notifications(&self) -> Vec<(String, Vec<u8>)> {
+        self.0.borrow().notifications.clone()
+    }
+
+    pub fn remove_method(&self, method: &str) {
+        self.0.borrow_mut().missing_methods.push(method.to_string());
+    }
+}
+
+impl ProxyApi for FakeProxy {
+    async fn get_group_member(
+        &self,
+        group_id: u64,
+        principal: Principal,
+    ) -> CanisterResult<Option<GroupMember>> {
+        Ok(self.0.borrow().members.get(&(group_id, principal)).cloned())
+    }
+
+    async fn send_notification(&self, method: &str, args: &[u8]) -> CanisterResult<()> {
+        let mut state = self.0.borrow_mut();
+
+        if state.unavailable {
+            return Err(Error::internal().add_message("proxy unavailable"));
+        }
+
+        if state
+            .missing_methods
+            .iter()
+            .any(|missing| missing == method)
+        {
+            return Err(Error::not_found().add_message("proxy has no update method"));
+        }
+
+        state
+            .notifications
+            .push((method.to_string(), args.to_vec()));
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct FakeMultisigState {
+    pub owners: HashMap<Principal, Principal>,
+    pub infos: HashMap<Principal, WalletInfo>,
+    /// Wallets that sent their cycles back, with the receiver
+    pub withdrawals: Vec<(Principal, Principal)>,
+    /// Calls to the wallets fail while they are unavailable
+    pub unavailable: bool,
+    /// Runs while `set_owner` is awaited, for changes other calls make in the meantime
+    pub during_set_owner: Option<Rc<dyn Fn()>>,
+    /// Wallets of a release without `withdraw_cycles`
+    pub without_withdraw: Vec<Principal>,
+}
+
+#[derive(Clone, Default)]
+pub struct FakeMultisig(pub Rc<RefCell<FakeMultisigState>>);
+
+impl FakeMultisig {
+    pub fn owner(&self, wallet: Principal) -> Option<Principal> {
+        self.0.borrow().owners.get(&wallet).copied()
+    }
+
+    /// Sets the owner on the wallet only, as if the index missed the change
+    pub fn set_wallet_owner(&self, wallet: Principal, owner: Principal) {
+        self.0.borrow_mut().owners.insert(wallet, owner);
+    }
+
+    pub fn set_unavailable(&self, unavailable: bool) {
+        self.0.borrow_mut().unavailable = unavailable;
+    }
+
+    pub fn set_wallet_info(&self, wallet: Principal, info: WalletInfo) {
+        self.0.borrow_mut().infos.insert(wallet, info);
+    }
+
+    pub fn withdrawals(&self) -> Vec<(Principal, Principal)> {
+        self.0.borrow().withdrawals.clone()
+    }
+
+    pub fn remove_withdraw_cycles(&self, wallet: Principal) {
+        self.0.borrow_mut().without_withdraw.push(wallet);
+    }
+}
+
+impl MultisigApi for FakeMultisig {
+    async fn set_owner(&self, wallet: Principal, owner: Principal) -> CanisterResult<Principal> {
+        let during_set_owner = self.0.borrow().during_set_owner.clone();
+        if let Some(during_set_owner) = during_set_owner {
+            during_set_owner();
+        }
+
+        let mut state = self.0.borrow_mut();
+
+        if state.unavailable {
+            return Err(Error::internal().add_message("wallet unavailable"));
+        }
+
+        state.owners.insert(wallet, owner);
+        Ok(wallet)
+    }
+
+    async fn get_owner(&self, wallet: Principal) -> CanisterResult<Principal> {
+        let state = self.0.borrow();
+
+        if state.unavailable {
+            return Err(Error::internal().add_message("wallet unavailable"));
+        }
+
+        state
+            .owners
+            .get(&wallet)
+            .copied()
+            .ok_or_else(|| Error::not_found().add_message("unknown wallet"))
+    }
+
+    async fn get_wallet_info(&self, wallet: Principal) -> CanisterResult<WalletInfo> {
+        let state = self.0.borrow();
+
+        if state.unavailable {
+            return Err(Error::internal().add_message("wallet unavailable"));
+        }
+
+        state
+            .infos
+            .get(&wallet)
+            .cloned()
+            .ok_or_else(|| Error::not_found().add_message("unknown wallet"))
+    }
+
+    async fn withdraw_cycles(&self, wallet: Principal, to: Principal) -> CanisterResult<u64> {
+        let mut state = self.0.borrow_mut();
+
+        if state.unavailable {
+            return Err(Error::internal().add_message("wallet unavailable"));
+        }
+
+        if state.without_withdraw.contains(&wallet) {
+            return Err(Error::bad_request()
+                .add_method_name("withdraw_cycles")
+                .add_message("The wallet does not implement withdraw_cycles, upgrade it first"));
+        }
+
+        state.withdrawals.push((wallet, to));
+        Ok(state
+            .infos
+            .get_mut(&wallet)
+            .map(|info| std::mem::take(&mut info.cycles))
+            .unwrap_or_default())
+    }
+}
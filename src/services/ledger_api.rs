@@ -0,0 +1,107 @@
+use std::future::Future;
+
+use candid::{CandidType, Nat, Principal};
+use ic_cdk::api::call::{call, CallResult};
+use ic_ledger_types::{
+    account_balance, query_archived_blocks, query_blocks, transfer, AccountBalanceArgs,
+    AccountIdentifier, GetBlocksArgs, GetBlocksResult, QueryArchiveFn, QueryBlocksResponse, Tokens,
+    TransferArgs, TransferResult,
+};
+
+use crate::{
+    storage::{cell_api::CellStorage, config_storage::ConfigStorage},
+    types::{error::Error, result::CanisterResult},
+};
+
+/// Calls to the ICP ledger canister
+pub trait LedgerApi {
+    fn transfer(&self, args: TransferArgs) -> impl Future<Output = CanisterResult<TransferResult>>;
+
+    fn query_blocks(
+        &self,
+        args: GetBlocksArgs,
+    ) -> impl Future<Output = CanisterResult<QueryBlocksResponse>>;
+
+    fn query_archived_blocks(
+        &self,
+        func: &QueryArchiveFn,
+        args: GetBlocksArgs,
+    ) -> impl Future<Output = CanisterResult<GetBlocksResult>>;
+
+    fn account_balance(
+        &self,
+        account: AccountIdentifier,
+    ) -> impl Future<Output = CanisterResult<Tokens>>;
+
+    /// Reads the balance of the default account of `owner` on the ICRC-1 ledger `token`
+    fn token_balance(
+        &self,
+        token: Principal,
+        owner: Principal,
+    ) -> impl Future<Output = CanisterResult<Nat>>;
+}
+
+/// ICRC-1 account
+#[derive(CandidType, Clone, Debug)]
+struct Account {
+    owner: Principal,
+    subaccount: Option<Vec<u8>>,
+}
+
+/// The ledger canister configured in the `ConfigStorage`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IcLedger;
+
+impl LedgerApi for IcLedger {
+    async fn transfer(&self, args: TransferArgs) -> CanisterResult<TransferResult> {
+        let ledger = ConfigStorage::get()?.ledger_canister_id();
+
+        transfer(ledger, args)
+            .await
+            .map_err(|e| Error::internal().add_message(e.1.as_str()))
+    }
+
+    async fn query_blocks(&self, args: GetBlocksArgs) -> CanisterResult<QueryBlocksResponse> {
+        let ledger = ConfigStorage::get()?.ledger_canister_id();
+
+        query_blocks(ledger, args)
+            .await
+            .map_err(|e| Error::internal().add_message(e.1.as_str()))
+    }
+
+    async fn query_archived_blocks(
+        &self,
+        func: &QueryArchiveFn,
+        args: GetBlocksArgs,
+    ) -> CanisterResult<GetBlocksResult> {
+        query_archived_blocks(func, args)
+            .await
+            .map_err(|e| Error::internal().add_message(e.1.as_str()))
+    }
+
+    async fn account_balance(&self, account: AccountIdentifier) -> CanisterResult<Tokens> {
+        let ledger = ConfigStorage::get()?.ledger_canister_id();
+
+        account_balance(ledger, AccountBalanceArgs { account })
+            .await
+            .map_err(|e| Error::internal().add_message(e.1.as_str()))
+    }
+
+    async fn token_balance(&self, token: Principal, owner: Principal) -> CanisterResult<Nat> {
+        let result: CallResult<(Nat,)> = call(
+            token,
+            "icrc1_balance_of",
+            (Account {
+                owner,
+                subaccount: None,
+            },),
+        )
+        .await;
+
+        result.map(|(balance,)| balance).map_err(|(_, err)| {
+            Error::internal()
+                .add_method_name("icrc1_balance_of")
+                .add_message(err.as_str())
+        })
+    }
+}
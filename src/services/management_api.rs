@@ -0,0 +1,218 @@
+use std::future::Future;
+
+use candid::Principal;
+use ic_cdk::api::{
+    call::{call, CallResult},
+    management_canister::main::{
+        canister_status, create_canister, delete_canister, install_code, start_canister,
+        stop_canister, update_settings, CanisterIdRecord, CanisterSettings, CanisterStatusResponse,
+        CreateCanisterArgument, InstallCodeArgument, UpdateSettingsArgument,
+    },
+};
+
+use crate::types::{
+    canister_snapshot::{
+        CanisterSnapshot, DeleteCanisterSnapshotArgs, LoadCanisterSnapshotArgs,
+        TakeCanisterSnapshotArgs,
+    },
+    error::Error,
+    result::CanisterResult,
+};
+
+/// Calls to the management canister
+pub trait ManagementApi {
+    fn create_canister(
+        &self,
+        args: CreateCanisterArgument,
+        cycles: u128,
+    ) -> impl Future<Output = CanisterResult<Principal>>;
+
+    fn install_code(&self, args: InstallCodeArgument) -> impl Future<Output = CanisterResult<()>>;
+
+    fn canister_status(
+        &self,
+        canister_id: Principal,
+    ) -> impl Future<Output = CanisterResult<CanisterStatusResponse>>;
+
+    fn start_canister(&self, canister_id: Principal) -> impl Future<Output = CanisterResult<()>>;
+
+    fn stop_canister(&self, canister_id: Principal) -> impl Future<Output = CanisterResult<()>>;
+
+    /// Deletes a stopped canister, the cycles it still has are lost
+    fn delete_canister(&self, canister_id: Principal) -> impl Future<Output = CanisterResult<()>>;
+
+    /// Replaces the controllers of a canister, the other settings are kept
+    fn set_controllers(
+        &self,
+        canister_id: Principal,
+        controllers: Vec<Principal>,
+    ) -> impl Future<Output = CanisterResult<()>>;
+
+    /// Takes a snapshot of the canister, replacing `replace` when given
+    fn take_canister_snapshot(
+        &self,
+        canister_id: Principal,
+        replace: Option<Vec<u8>>,
+    ) -> impl Future<Output = CanisterResult<CanisterSnapshot>>;
+
+    fn list_canister_snapshots(
+        &self,
+        canister_id: Principal,
+    ) -> impl Future<Output = CanisterResult<Vec<CanisterSnapshot>>>;
+
+    /// Restores the module and memory of the canister from the snapshot
+    fn load_canister_snapshot(
+        &self,
+        canister_id: Principal,
+        snapshot_id: Vec<u8>,
+    ) -> impl Future<Output = CanisterResult<()>>;
+
+    fn delete_canister_snapshot(
+        &self,
+        canister_id: Principal,
+        snapshot_id: Vec<u8>,
+    ) -> impl Future<Output = CanisterResult<()>>;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IcManagement;
+
+impl ManagementApi for IcManagement {
+    async fn create_canister(
+        &self,
+        args: CreateCanisterArgument,
+        cycles: u128,
+    ) -> CanisterResult<Principal> {
+        create_canister(args, cycles)
+            .await
+            .map(|(result,)| result.canister_id)
+            .map_err(|(_, err)| Error::internal().add_message(err.as_str()))
+    }
+
+    async fn install_code(&self, args: InstallCodeArgument) -> CanisterResult<()> {
+        install_code(args)
+            .await
+            .map_err(|(_, err)| Error::internal().add_message(err.as_str()))
+    }
+
+    async fn canister_status(
+        &self,
+        canister_id: Principal,
+    ) -> CanisterResult<CanisterStatusResponse> {
+        canister_status(CanisterIdRecord { canister_id })
+            .await
+            .map(|(status,)| status)
+            .map_err(|(_, err)| Error::internal().add_message(err.as_str()))
+    }
+
+    async fn start_canister(&self, canister_id: Principal) -> CanisterResult<()> {
+        start_canister(CanisterIdRecord { canister_id })
+            .await
+            .map_err(|(_, err)| Error::internal().add_message(err.as_str()))
+    }
+
+    async fn stop_canister(&self, canister_id: Principal) -> CanisterResult<()> {
+        stop_canister(CanisterIdRecord { canister_id })
+            .await
+            .map_err(|(_, err)| Error::internal().add_message(err.as_str()))
+    }
+
+    async fn delete_canister(&self, canister_id: Principal) -> CanisterResult<()> {
+        delete_canister(CanisterIdRecord { canister_id })
+            .await
+            .map_err(|(_, err)| Error::internal().add_message(err.as_str()))
+    }
+
+    async fn set_controllers(
+        &self,
+        canister_id: Principal,
+        controllers: Vec<Principal>,
+    ) -> CanisterResult<()> {
+        update_settings(UpdateSettingsArgument {
+            canister_id,
+            settings: CanisterSettings {
+                controllers: Some(controllers),
+                ..Default::default()
+            },
+        })
+        .await
+        .map_err(|(_, err)| Error::internal().add_message(err.as_str()))
+    }
+
+    // the snapshot calls are not wrapped by this version of ic-cdk
+    async fn take_canister_snapshot(
+        &self,
+        canister_id: Principal,
+        replace: Option<Vec<u8>>,
+    ) -> CanisterResult<CanisterSnapshot> {
+        let args = TakeCanisterSnapshotArgs {
+            canister_id,
+            replace_snapshot: replace,
+        };
+        let result: CallResult<(CanisterSnapshot,)> = call(
+            Principal::management_canister(),
+            "take_canister_snapshot",
+            (args,),
+        )
+        .await;
+
+        result
+            .map(|(snapshot,)| snapshot)
+            .map_err(|(_, err)| Error::internal().add_message(err.as_str()))
+    }
+
+    async fn list_canister_snapshots(
+        &self,
+        canister_id: Principal,
+    ) -> CanisterResult<Vec<CanisterSnapshot>> {
+        let result: CallResult<(Vec<CanisterSnapshot>,)> = call(
+            Principal::management_canister(),
+            "list_canister_snapshots",
+            (CanisterIdRecord { canister_id },),
+        )
+        .await;
+
+        result
+            .map(|(snapshots,)| snapshots)
+            .map_err(|(_, err)| Error::internal().add_message(err.as_str()))
+    }
+
+    async fn load_canister_snapshot(
+        &self,
+        canister_id: Principal,
+        snapshot_id: Vec<u8>,
+    ) -> CanisterResult<()> {
+        let args = LoadCanisterSnapshotArgs {
+            canister_id,
+            snapshot_id,
+            sender_canister_version: None,
+        };
+        let result: CallResult<()> = call(
+            Principal::management_canister(),
+            "load_canister_snapshot",
+            (args,),
+        )
+        .await;
+
+        result.map_err(|(_, err)| Error::internal().add_message(err.as_str()))
+    }
+
+    async fn delete_canister_snapshot(
+        &self,
+        canister_id: Principal,
+        snapshot_id: Vec<u8>,
+    ) -> CanisterResult<()> {
+        let args = DeleteCanisterSnapshotArgs {
+            canister_id,
+            snapshot_id,
+        };
+        let result: CallResult<()> = call(
+            Principal::management_canister(),
+            "delete_canister_snapshot",
+            (args,),
+        )
+        .await;
+
+        result.map_err(|(_, err)| Error::internal().add_message(err.as_str()))
+    }
+}
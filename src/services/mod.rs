@@ -1 +1,23 @@
+pub mod cmc_api;
 pub mod cmc_service;
+pub mod environment;
+pub mod ledger_api;
+pub mod management_api;
+pub mod multisig_api;
+pub mod proxy_api;
+
+#[cfg(test)]
+pub mod fakes;
+
+use ic_cdk::api::call::RejectionCode;
+
+/// Whether a reject is the one of a call to a method the canister does not export, e.g. an
+/// older release of the canister. Replicas reject such a call as an invalid destination, older
+/// ones as an error of the canister, which only the reject message tells apart from a trap.
+pub fn is_missing_method(code: RejectionCode, reject_message: &str) -> bool {
+    match code {
+        RejectionCode::DestinationInvalid => true,
+        RejectionCode::CanisterError => reject_message.contains("has no update method"),
+        _ => false,
+    }
+}
@@ -0,0 +1,117 @@
+use std::future::Future;
+
+use candid::{CandidType, Deserialize, Principal};
+use ic_cdk::api::call::{call, CallResult};
+
+use crate::{
+    services::is_missing_method,
+    types::{error::Error, result::CanisterResult},
+};
+
+/// The part of the wallet info response the index caches, candid skips the other fields
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct WalletInfo {
+    pub whitelist: Vec<Principal>,
+    pub threshold: u64,
+    pub cycles: u64,
+    /// `None` for wallets that do not report their group
+    pub group_id: Option<u64>,
+}
+
+/// Calls to the multisig wallets spawned by the index
+pub trait MultisigApi {
+    /// Sets the owner of the wallet, returns the principal the wallet replies with
+    fn set_owner(
+        &self,
+        wallet: Principal,
+        owner: Principal,
+    ) -> impl Future<Output = CanisterResult<Principal>>;
+
+    /// Reads the owner the wallet itself has
+    fn get_owner(&self, wallet: Principal) -> impl Future<Output = CanisterResult<Principal>>;
+
+    /// Reads the whitelist, threshold, cycles balance and group of the wallet
+    fn get_wallet_info(
+        &self,
+        wallet: Principal,
+    ) -> impl Future<Output = CanisterResult<WalletInfo>>;
+
+    /// Has the wallet send its cycles to `to` with `deposit_cycles`, returns the amount sent.
+    /// Wallets implement `withdraw_cycles : (principal) -> (variant { Ok : nat64; Err : text })`
+    /// and only accept it from a controller, a wallet without the method fails with a bad request
+    fn withdraw_cycles(
+        &self,
+        wallet: Principal,
+        to: Principal,
+    ) -> impl Future<Output = CanisterResult<u64>>;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IcMultisig;
+
+impl MultisigApi for IcMultisig {
+    async fn set_owner(&self, wallet: Principal, owner: Principal) -> CanisterResult<Principal> {
+        let result: CallResult<(Result<Principal, String>,)> =
+            call(wallet, "set_owner", (owner,)).await;
+
+        result
+            .map_err(|(_, err)| {
+                Error::internal()
+                    .add_method_name("set_owner")
+                    .add_message(err.as_str())
+            })?
+            .0
+            .map_err(|err| {
+                Error::internal()
+                    .add_method_name("set_owner")
+                    .add_message(err.as_str())
+            })
+    }
+
+    async fn get_owner(&self, wallet: Principal) -> CanisterResult<Principal> {
+        let result: CallResult<(Principal,)> = call(wallet, "get_owner", ()).await;
+
+        result.map(|(owner,)| owner).map_err(|(_, err)| {
+            Error::internal()
+                .add_method_name("get_owner")
+                .add_message(err.as_str())
+        })
+    }
+
+    async fn get_wallet_info(&self, wallet: Principal) -> CanisterResult<WalletInfo> {
+        let result: CallResult<(WalletInfo,)> = call(wallet, "get_wallet_info", ()).await;
+
+        result.map(|(info,)| info).map_err(|(_, err)| {
+            Error::internal()
+                .add_method_name("get_wallet_info")
+                .add_message(err.as_str())
+        })
+    }
+
+    async fn withdraw_cycles(&self, wallet: Principal, to: Principal) -> CanisterResult<u64> {
+        let result: CallResult<(Result<u64, String>,)> =
+            call(wallet, "withdraw_cycles", (to,)).await;
+
+        result
+            .map_err(|(code, err)| {
+                // deleting the wallet would burn the cycles it can not send back
+                match is_missing_method(code, &err) {
+                    true => Error::bad_request()
+                        .add_method_name("withdraw_cycles")
+                        .add_info(err.as_str())
+                        .add_message(
+                            "The wallet does not implement withdraw_cycles, upgrade it first",
+                        ),
+                    false => Error::internal()
+                        .add_method_name("withdraw_cycles")
+                        .add_message(err.as_str()),
+                }
+            })?
+            .0
+            .map_err(|err| {
+                Error::internal()
+                    .add_method_name("withdraw_cycles")
+                    .add_message(err.as_str())
+            })
+    }
+}
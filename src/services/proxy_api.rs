@@ -0,0 +1,78 @@
+use std::future::Future;
+
+use candid::{CandidType, Principal, Reserved};
+use ic_cdk::api::call::{call, call_raw, CallResult};
+use serde::Deserialize;
+
+use crate::{
+    services::is_missing_method,
+    storage::{cell_api::CellStorage, proxy_storage::ProxyCanisterStorage},
+    types::{error::Error, result::CanisterResult},
+};
+
+/// The part of the proxy member response the index uses, candid skips the other fields
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct GroupMember {
+    pub roles: Vec<String>,
+}
+
+/// Calls to the proxy canister that runs the groups
+pub trait ProxyApi {
+    /// Returns `None` if the principal is not a member of the group or the group does not exist
+    fn get_group_member(
+        &self,
+        group_id: u64,
+        principal: Principal,
+    ) -> impl Future<Output = CanisterResult<Option<GroupMember>>>;
+
+    /// Calls a notification method with candid encoded arguments, the reply is not used. A
+    /// method the proxy does not have fails with a not found error
+    fn send_notification(
+        &self,
+        method: &str,
+        args: &[u8],
+    ) -> impl Future<Output = CanisterResult<()>>;
+}
+
+/// The proxy canister stored in the `ProxyCanisterStorage`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IcProxy;
+
+impl ProxyApi for IcProxy {
+    async fn get_group_member(
+        &self,
+        group_id: u64,
+        principal: Principal,
+    ) -> CanisterResult<Option<GroupMember>> {
+        // the error type of the proxy is not needed, any error means the member is not found
+        let result: CallResult<(Result<GroupMember, Reserved>,)> = call(
+            ProxyCanisterStorage::get()?,
+            "get_group_member",
+            (group_id, principal),
+        )
+        .await;
+
+        match result {
+            Ok((member,)) => Ok(member.ok()),
+            Err((_, err)) => Err(Error::internal()
+                .add_method_name("get_group_member")
+                .add_message(err.as_str())),
+        }
+    }
+
+    async fn send_notification(&self, method: &str, args: &[u8]) -> CanisterResult<()> {
+        call_raw(ProxyCanisterStorage::get()?, method, args, 0)
+            .await
+            .map(|_| ())
+            .map_err(|(code, err)| {
+                let error = match is_missing_method(code, &err) {
+                    true => Error::not_found(),
+                    false => Error::internal(),
+                };
+
+                error
+                    .add_method_name(method)
+                    .add_message(format!("{:?}: {}", code, err).as_str())
+            })
+    }
+}
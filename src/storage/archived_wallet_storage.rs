@@ -0,0 +1,27 @@
+use candid::Principal;
+use ic_stable_structures::memory_manager::MemoryId;
+
+use crate::types::archived_wallet::ArchivedWallet;
+
+use super::{
+    state::{StaticStorageRef, ARCHIVED_WALLETS, ARCHIVED_WALLETS_MEMORY_ID},
+    storage_api::{Storage, StorageInsertableByKey, StorageQueryable},
+};
+
+/// Decommissioned wallets by their former canister id
+pub struct ArchivedWalletStorage;
+
+impl Storage<Principal, ArchivedWallet> for ArchivedWalletStorage {
+    const NAME: &'static str = "archived_wallets";
+
+    fn storage() -> StaticStorageRef<Principal, ArchivedWallet> {
+        &ARCHIVED_WALLETS
+    }
+
+    fn memory_id() -> MemoryId {
+        ARCHIVED_WALLETS_MEMORY_ID
+    }
+}
+
+impl StorageQueryable<Principal, ArchivedWallet> for ArchivedWalletStorage {}
+impl StorageInsertableByKey<Principal, ArchivedWallet> for ArchivedWalletStorage {}
@@ -0,0 +1,26 @@
+use ic_stable_structures::memory_manager::MemoryId;
+
+use crate::types::index_event::IndexEvent;
+
+use super::{
+    log_api::{LogStorage, LogStorageRef},
+    state::{EVENT_LOG, EVENT_LOG_DATA_MEMORY_ID, EVENT_LOG_INDEX_MEMORY_ID},
+};
+
+pub struct EventLogStorage;
+
+impl LogStorage<IndexEvent> for EventLogStorage {
+    const NAME: &'static str = "event_log";
+
+    fn storage() -> LogStorageRef<IndexEvent> {
+        &EVENT_LOG
+    }
+
+    fn index_memory_id() -> MemoryId {
+        EVENT_LOG_INDEX_MEMORY_ID
+    }
+
+    fn data_memory_id() -> MemoryId {
+        EVENT_LOG_DATA_MEMORY_ID
+    }
+}
@@ -0,0 +1,26 @@
+use ic_stable_structures::memory_manager::MemoryId;
+
+use crate::types::ledger_transfer::{LedgerTransfer, LedgerTransferKey};
+
+use super::{
+    state::{StaticStorageRef, LEDGER_TRANSFERS, LEDGER_TRANSFERS_MEMORY_ID},
+    storage_api::{Storage, StorageInsertableByKey, StorageQueryable, StorageUpdateable},
+};
+
+pub struct LedgerTransferStorage;
+
+impl Storage<LedgerTransferKey, LedgerTransfer> for LedgerTransferStorage {
+    const NAME: &'static str = "ledger_transfers";
+
+    fn storage() -> StaticStorageRef<LedgerTransferKey, LedgerTransfer> {
+        &LEDGER_TRANSFERS
+    }
+
+    fn memory_id() -> MemoryId {
+        LEDGER_TRANSFERS_MEMORY_ID
+    }
+}
+
+impl StorageQueryable<LedgerTransferKey, LedgerTransfer> for LedgerTransferStorage {}
+impl StorageInsertableByKey<LedgerTransferKey, LedgerTransfer> for LedgerTransferStorage {}
+impl StorageUpdateable<LedgerTransferKey, LedgerTransfer> for LedgerTransferStorage {}
@@ -0,0 +1,51 @@
+use std::{cell::RefCell, thread::LocalKey};
+
+use ic_stable_structures::{memory_manager::MemoryId, StableLog, Storable};
+
+use crate::types::{error::Error, result::CanisterResult};
+
+use super::state::Memory;
+
+pub type LogStorageRef<V> = &'static LocalKey<RefCell<StableLog<V, Memory, Memory>>>;
+
+/// Append only storage, the key of an entry is its position in the log
+pub trait LogStorage<V: Storable + 'static> {
+    const NAME: &'static str;
+
+    fn index_memory_id() -> MemoryId;
+    fn data_memory_id() -> MemoryId;
+    fn storage() -> LogStorageRef<V>;
+
+    fn append(value: V) -> CanisterResult<u64> {
+        Self::storage()
+            .with(|data| data.borrow().append(&value))
+            .map_err(|_| {
+                Error::internal().add_message(&format!("Failed to append to {}", Self::NAME))
+            })
+    }
+
+    fn get(key: u64) -> CanisterResult<(u64, V)> {
+        Self::storage()
+            .with(|data| data.borrow().get(key))
+            .map(|value| (key, value))
+            .ok_or_else(|| {
+                Error::not_found().add_message(&format!("{} {} not found", Self::NAME, key))
+            })
+    }
+
+    /// Returns up to `limit` entries starting at `from`
+    fn get_range(from: u64, limit: usize) -> Vec<(u64, V)> {
+        Self::storage().with(|data| {
+            let data = data.borrow();
+            let end = from.saturating_add(limit as u64).min(data.len());
+
+            (from..end)
+                .filter_map(|key| data.get(key).map(|value| (key, value)))
+                .collect()
+        })
+    }
+
+    fn len() -> u64 {
+        Self::storage().with(|data| data.borrow().len())
+    }
+}
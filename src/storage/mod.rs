@@ -1,7 +1,15 @@
+pub mod archived_wallet_storage;
 pub mod cell_api;
+pub mod config_storage;
+pub mod event_log_storage;
+pub mod ledger_transfer_storage;
+pub mod log_api;
 pub mod multisig_storage;
 pub mod multisig_wasm_storage;
+pub mod notification_storage;
+pub mod ownership_transfer_storage;
 pub mod proxy_storage;
 pub mod spawn_status_storage;
 pub mod state;
 pub mod storage_api;
+pub mod wasm_version_storage;
@@ -1,11 +1,18 @@
 use candid::Principal;
 use ic_stable_structures::memory_manager::MemoryId;
 
-use crate::types::wallet_data::WalletData;
+use crate::types::{wallet_data::WalletData, wallet_health::HealthSummary};
 
 use super::{
-    state::{StaticStorageRef, MULTISIGS, MULTISIGS_MEMORY_ID},
-    storage_api::{Storage, StorageInsertableByKey, StorageQueryable, StorageUpdateable},
+    state::{
+        StaticIndexStorageRef, StaticStorageRef, FLEET_COUNTS, GROUP_WALLETS,
+        GROUP_WALLETS_MEMORY_ID, MULTISIGS, MULTISIGS_MEMORY_ID, OWNER_WALLETS,
+        OWNER_WALLETS_MEMORY_ID,
+    },
+    storage_api::{
+        IndexWriter, Storage, StorageIndex, StorageIndexed, StorageInsertableByKey,
+        StorageQueryable, StorageUpdateable,
+    },
 };
 
 pub struct MultisigStorage;
@@ -20,8 +27,101 @@ impl Storage<Principal, WalletData> for MultisigStorage {
     fn memory_id() -> MemoryId {
         MULTISIGS_MEMORY_ID
     }
+
+    fn indexes() -> Vec<&'static dyn IndexWriter<Principal, WalletData>> {
+        vec![&GroupIndex, &OwnerIndex, &FleetIndex]
+    }
 }
 
 impl StorageQueryable<Principal, WalletData> for MultisigStorage {}
 impl StorageInsertableByKey<Principal, WalletData> for MultisigStorage {}
 impl StorageUpdateable<Principal, WalletData> for MultisigStorage {}
+impl StorageIndexed<Principal, WalletData> for MultisigStorage {}
+
+/// Wallets by group id
+pub struct GroupIndex;
+
+impl StorageIndex<Principal, WalletData> for GroupIndex {
+    type Key = u64;
+
+    const NAME: &'static str = "multisigs_by_group";
+
+    fn memory_id() -> MemoryId {
+        GROUP_WALLETS_MEMORY_ID
+    }
+
+    fn storage() -> StaticIndexStorageRef<u64, Principal> {
+        &GROUP_WALLETS
+    }
+
+    fn index_keys(value: &WalletData) -> Vec<u64> {
+        vec![value.group_id()]
+    }
+}
+
+/// Wallets by owner
+pub struct OwnerIndex;
+
+impl StorageIndex<Principal, WalletData> for OwnerIndex {
+    type Key = Principal;
+
+    const NAME: &'static str = "multisigs_by_owner";
+
+    fn memory_id() -> MemoryId {
+        OWNER_WALLETS_MEMORY_ID
+    }
+
+    fn storage() -> StaticIndexStorageRef<Principal, Principal> {
+        &OWNER_WALLETS
+    }
+
+    fn index_keys(value: &WalletData) -> Vec<Principal> {
+        vec![value.owner()]
+    }
+}
+
+/// Number of wallets by their last health check, so the fleet status does not read every wallet
+pub struct FleetIndex;
+
+impl FleetIndex {
+    pub fn counts() -> Vec<(Option<HealthSummary>, u64)> {
+        FLEET_COUNTS.with(|counts| {
+            counts
+                .borrow()
+                .iter()
+                .map(|(summary, count)| (summary.clone(), *count))
+                .collect()
+        })
+    }
+
+    fn summary(value: &WalletData) -> Option<HealthSummary> {
+        value.health().map(|health| health.summary())
+    }
+}
+
+impl IndexWriter<Principal, WalletData> for FleetIndex {
+    fn insert(&self, _: &Principal, value: &WalletData) {
+        FLEET_COUNTS.with(|counts| {
+            *counts.borrow_mut().entry(Self::summary(value)).or_default() += 1;
+        })
+    }
+
+    fn remove(&self, _: &Principal, value: &WalletData) {
+        FLEET_COUNTS.with(|counts| {
+            let mut counts = counts.borrow_mut();
+            let summary = Self::summary(value);
+
+            if let Some(count) = counts.get_mut(&summary) {
+                *count = count.saturating_sub(1);
+
+                if *count == 0 {
+                    counts.remove(&summary);
+                }
+            }
+        })
+    }
+
+    fn clear(&self) {
+        FLEET_COUNTS.with(|counts| counts.borrow_mut().clear())
+    }
+}
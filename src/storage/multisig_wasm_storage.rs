@@ -1,12 +1,33 @@
 use ic_stable_structures::memory_manager::MemoryId;
+use sha2::{Digest, Sha256};
+
+use crate::types::{error::Error, result::CanisterResult};
 
 use super::{
     cell_api::{CellStorage, CellStorageRef},
-    state::{MULTISIG_WASM, MULTISIG_WASM_MEMORY_ID},
+    state::{
+        MULTISIG_WASM, MULTISIG_WASM_MEMORY_ID, MULTISIG_WASM_VERSION,
+        MULTISIG_WASM_VERSION_MEMORY_ID,
+    },
 };
 
 pub struct MultisigWasmStorage;
 
+/// Version of the stored wasm, kept next to the wasm so it is not hashed on every read
+pub struct MultisigWasmVersionStorage;
+
+impl CellStorage<String> for MultisigWasmVersionStorage {
+    const NAME: &'static str = "multisig_wasm_version";
+
+    fn storage() -> CellStorageRef<String> {
+        &MULTISIG_WASM_VERSION
+    }
+
+    fn memory_id() -> MemoryId {
+        MULTISIG_WASM_VERSION_MEMORY_ID
+    }
+}
+
 impl CellStorage<Vec<u8>> for MultisigWasmStorage {
     const NAME: &'static str = "multisig_wasm";
 
@@ -17,4 +38,29 @@ impl CellStorage<Vec<u8>> for MultisigWasmStorage {
     fn memory_id() -> MemoryId {
         MULTISIG_WASM_MEMORY_ID
     }
+
+    /// Stores the wasm together with its version
+    fn set(value: Vec<u8>) -> Result<Vec<u8>, Error> {
+        Self::storage()
+            .with(|data| data.borrow_mut().set(Some(value.clone())))
+            .map_err(|_| Error::internal().add_message(&format!("Failed to set {}", Self::NAME)))?;
+
+        MultisigWasmVersionStorage::set(Self::version_of(&Sha256::digest(&value)))?;
+        Ok(value)
+    }
+}
+
+impl MultisigWasmStorage {
+    /// Hex encoded sha256 of the stored wasm, equal to the module hash of the installed wallets
+    pub fn version() -> CanisterResult<String> {
+        MultisigWasmVersionStorage::get()
+    }
+
+    /// Hex encodes a module hash, as reported by `canister_status`, to a version
+    pub fn version_of(module_hash: &[u8]) -> String {
+        module_hash
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
 }
@@ -0,0 +1,68 @@
+use candid::Principal;
+use ic_stable_structures::memory_manager::MemoryId;
+
+use crate::types::{notification::Notification, notification_counter::NotificationCounter};
+
+use super::{
+    state::{
+        StaticStorageRef, DEAD_LETTERS, DEAD_LETTERS_MEMORY_ID, NOTIFICATION_COUNTERS,
+        NOTIFICATION_COUNTERS_MEMORY_ID, NOTIFICATION_OUTBOX, NOTIFICATION_OUTBOX_MEMORY_ID,
+    },
+    storage_api::{Storage, StorageInsertable, StorageQueryable, StorageUpdateable},
+};
+
+/// Notifications that are waiting to be delivered to the proxy
+pub struct NotificationOutboxStorage;
+
+impl Storage<u64, Notification> for NotificationOutboxStorage {
+    const NAME: &'static str = "notification_outbox";
+
+    fn storage() -> StaticStorageRef<u64, Notification> {
+        &NOTIFICATION_OUTBOX
+    }
+
+    fn memory_id() -> MemoryId {
+        NOTIFICATION_OUTBOX_MEMORY_ID
+    }
+}
+
+impl StorageQueryable<u64, Notification> for NotificationOutboxStorage {}
+impl StorageInsertable<Notification> for NotificationOutboxStorage {}
+impl StorageUpdateable<u64, Notification> for NotificationOutboxStorage {}
+
+/// Notifications that could not be delivered after the maximum number of attempts
+pub struct DeadLetterStorage;
+
+impl Storage<u64, Notification> for DeadLetterStorage {
+    const NAME: &'static str = "dead_letters";
+
+    fn storage() -> StaticStorageRef<u64, Notification> {
+        &DEAD_LETTERS
+    }
+
+    fn memory_id() -> MemoryId {
+        DEAD_LETTERS_MEMORY_ID
+    }
+}
+
+impl StorageQueryable<u64, Notification> for DeadLetterStorage {}
+impl StorageInsertable<Notification> for DeadLetterStorage {}
+impl StorageUpdateable<u64, Notification> for DeadLetterStorage {}
+
+/// Notification counters per wallet, used for the rate limit
+pub struct NotificationCounterStorage;
+
+impl Storage<Principal, NotificationCounter> for NotificationCounterStorage {
+    const NAME: &'static str = "notification_counters";
+
+    fn storage() -> StaticStorageRef<Principal, NotificationCounter> {
+        &NOTIFICATION_COUNTERS
+    }
+
+    fn memory_id() -> MemoryId {
+        NOTIFICATION_COUNTERS_MEMORY_ID
+    }
+}
+
+impl StorageQueryable<Principal, NotificationCounter> for NotificationCounterStorage {}
+impl StorageUpdateable<Principal, NotificationCounter> for NotificationCounterStorage {}
@@ -0,0 +1,27 @@
+use candid::Principal;
+use ic_stable_structures::memory_manager::MemoryId;
+
+use crate::types::ownership_transfer::OwnershipTransfer;
+
+use super::{
+    state::{StaticStorageRef, OWNERSHIP_TRANSFERS, OWNERSHIP_TRANSFERS_MEMORY_ID},
+    storage_api::{Storage, StorageQueryable, StorageUpdateable},
+};
+
+/// Pending ownership transfers by wallet
+pub struct OwnershipTransferStorage;
+
+impl Storage<Principal, OwnershipTransfer> for OwnershipTransferStorage {
+    const NAME: &'static str = "ownership_transfers";
+
+    fn storage() -> StaticStorageRef<Principal, OwnershipTransfer> {
+        &OWNERSHIP_TRANSFERS
+    }
+
+    fn memory_id() -> MemoryId {
+        OWNERSHIP_TRANSFERS_MEMORY_ID
+    }
+}
+
+impl StorageQueryable<Principal, OwnershipTransfer> for OwnershipTransferStorage {}
+impl StorageUpdateable<Principal, OwnershipTransfer> for OwnershipTransferStorage {}
@@ -1,17 +1,31 @@
-use std::{cell::RefCell, thread::LocalKey};
+use std::{cell::RefCell, collections::HashMap, thread::LocalKey};
 
 use candid::Principal;
 use ic_ledger_types::{Memo, Tokens};
 use ic_stable_structures::{
     memory_manager::{MemoryId, MemoryManager, VirtualMemory},
-    Cell, DefaultMemoryImpl, StableBTreeMap,
+    Cell, DefaultMemoryImpl, StableBTreeMap, StableLog,
 };
 
-use crate::types::{spawn_status::SpawnStatus, wallet_data::WalletData};
+use crate::types::{
+    archived_wallet::ArchivedWallet,
+    config::Config,
+    index_event::IndexEvent,
+    ledger_transfer::{LedgerTransfer, LedgerTransferKey},
+    notification::Notification,
+    notification_counter::NotificationCounter,
+    ownership_transfer::OwnershipTransfer,
+    spawn_status::SpawnStatus,
+    wallet_data::WalletData,
+    wallet_health::HealthSummary,
+    wasm_version::WasmVersion,
+};
 
 pub type Memory = VirtualMemory<DefaultMemoryImpl>;
 pub type StorageRef<K, V> = RefCell<StableBTreeMap<K, V, Memory>>;
 pub type StaticStorageRef<K, V> = &'static LocalKey<StorageRef<K, V>>;
+pub type IndexStorageRef<I, K> = StorageRef<(I, Option<K>), ()>;
+pub type StaticIndexStorageRef<I, K> = &'static LocalKey<IndexStorageRef<I, K>>;
 
 type MemoryManagerStorage = RefCell<MemoryManager<DefaultMemoryImpl>>;
 
@@ -21,11 +35,48 @@ pub static ICP_TRANSACTION_FEE: Tokens = Tokens::from_e8s(10000);
 pub static MIN_CYCLES_FOR_SPINUP: u64 = 5_000_000_000_000;
 pub static CATALYZE_E8S_FEE: Tokens = Tokens::from_e8s(10000000);
 pub static CATALYZE_MULTI_SIG: &str = "fcygz-gqaaa-aaaap-abpaa-cai";
+pub static DEFAULT_PAGE_LIMIT: u64 = 100;
+pub static MAX_PAGE_LIMIT: u64 = 500;
+pub static MAX_WALLET_LABEL_LENGTH: usize = 64;
+pub static MIN_SIGNERS: usize = 2;
+pub static MAX_WALLET_NAME_LENGTH: usize = 64;
+pub static MAX_WALLET_DESCRIPTION_LENGTH: usize = 1024;
+pub static MAX_SPENDING_LIMITS: usize = 20;
+pub static DEFAULT_MAX_SIGNERS: u64 = 50;
+pub static GROUP_MANAGER_ROLES: [&str; 2] = ["owner", "admin"];
+pub static GROUP_MEMBER_CACHE_TTL: u64 = 60 * 1_000_000_000;
+pub static NOTIFICATION_DELIVERY_INTERVAL: u64 = 30;
+pub static NOTIFICATION_DELIVERY_BATCH_SIZE: usize = 50;
+pub static NOTIFICATION_RETRY_BASE_DELAY: u64 = 30 * 1_000_000_000;
+pub static NOTIFICATION_RETRY_MAX_DELAY: u64 = 60 * 60 * 1_000_000_000;
+pub static NOTIFICATION_MAX_ATTEMPTS: u32 = 10;
+pub static MAX_NOTIFICATION_RECEIVERS: usize = 100;
+pub static NOTIFICATION_RATE_LIMIT: u32 = 100;
+pub static NOTIFICATION_RATE_WINDOW: u64 = 60 * 60 * 1_000_000_000;
+pub static OWNERSHIP_TRANSFER_TTL: u64 = 7 * 24 * 60 * 60 * 1_000_000_000;
+pub static WALLET_SYNC_INTERVAL: u64 = 10 * 60;
+pub static WALLET_SYNC_BATCH_SIZE: u64 = 50;
+pub static MAX_CONTROLLERS: usize = 10;
+pub static LOW_CYCLES_DAYS: u64 = 30;
+pub static LOCK_TTL: u64 = 60 * 60 * 1_000_000_000;
 
 pub static MULTISIGS_MEMORY_ID: MemoryId = MemoryId::new(0);
 pub static SPAWN_STATUS_MEMORY_ID: MemoryId = MemoryId::new(1);
 pub static PROXY_CANISTER_MEMORY_ID: MemoryId = MemoryId::new(2);
 pub static MULTISIG_WASM_MEMORY_ID: MemoryId = MemoryId::new(3);
+pub static LEDGER_TRANSFERS_MEMORY_ID: MemoryId = MemoryId::new(4);
+pub static CONFIG_MEMORY_ID: MemoryId = MemoryId::new(5);
+pub static GROUP_WALLETS_MEMORY_ID: MemoryId = MemoryId::new(6);
+pub static OWNER_WALLETS_MEMORY_ID: MemoryId = MemoryId::new(7);
+pub static NOTIFICATION_OUTBOX_MEMORY_ID: MemoryId = MemoryId::new(8);
+pub static DEAD_LETTERS_MEMORY_ID: MemoryId = MemoryId::new(9);
+pub static NOTIFICATION_COUNTERS_MEMORY_ID: MemoryId = MemoryId::new(10);
+pub static EVENT_LOG_INDEX_MEMORY_ID: MemoryId = MemoryId::new(11);
+pub static EVENT_LOG_DATA_MEMORY_ID: MemoryId = MemoryId::new(12);
+pub static OWNERSHIP_TRANSFERS_MEMORY_ID: MemoryId = MemoryId::new(13);
+pub static ARCHIVED_WALLETS_MEMORY_ID: MemoryId = MemoryId::new(14);
+pub static WASM_VERSIONS_MEMORY_ID: MemoryId = MemoryId::new(15);
+pub static MULTISIG_WASM_VERSION_MEMORY_ID: MemoryId = MemoryId::new(16);
 
 thread_local! {
     pub static MEMORY_MANAGER: MemoryManagerStorage =
@@ -53,4 +104,81 @@ thread_local! {
         Cell::init(MEMORY_MANAGER.with(|p| p.borrow().get(MULTISIG_WASM_MEMORY_ID)), None)
             .expect("Failed to initialize proxy canister")
     );
+
+    pub static MULTISIG_WASM_VERSION: RefCell<Cell<Option<String>, Memory>> = RefCell::new(
+        Cell::init(MEMORY_MANAGER.with(|p| p.borrow().get(MULTISIG_WASM_VERSION_MEMORY_ID)), None)
+            .expect("Failed to initialize multisig wasm version")
+    );
+
+    pub static LEDGER_TRANSFERS: RefCell<StableBTreeMap<LedgerTransferKey, LedgerTransfer, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(LEDGER_TRANSFERS_MEMORY_ID)),
+        )
+    );
+
+    pub static CONFIG: RefCell<Cell<Option<Config>, Memory>> = RefCell::new(
+        Cell::init(MEMORY_MANAGER.with(|p| p.borrow().get(CONFIG_MEMORY_ID)), Some(Config::default()))
+            .expect("Failed to initialize config")
+    );
+
+    pub static GROUP_WALLETS: IndexStorageRef<u64, Principal> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(GROUP_WALLETS_MEMORY_ID)),
+        )
+    );
+
+    pub static OWNER_WALLETS: IndexStorageRef<Principal, Principal> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(OWNER_WALLETS_MEMORY_ID)),
+        )
+    );
+
+    pub static NOTIFICATION_OUTBOX: RefCell<StableBTreeMap<u64, Notification, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(NOTIFICATION_OUTBOX_MEMORY_ID)),
+        )
+    );
+
+    pub static DEAD_LETTERS: RefCell<StableBTreeMap<u64, Notification, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(DEAD_LETTERS_MEMORY_ID)),
+        )
+    );
+
+    pub static NOTIFICATION_COUNTERS: RefCell<StableBTreeMap<Principal, NotificationCounter, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(NOTIFICATION_COUNTERS_MEMORY_ID)),
+        )
+    );
+
+    pub static EVENT_LOG: RefCell<StableLog<IndexEvent, Memory, Memory>> = RefCell::new(
+        StableLog::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(EVENT_LOG_INDEX_MEMORY_ID)),
+            MEMORY_MANAGER.with(|m| m.borrow().get(EVENT_LOG_DATA_MEMORY_ID)),
+        )
+        .expect("Failed to initialize event log")
+    );
+
+    pub static OWNERSHIP_TRANSFERS: RefCell<StableBTreeMap<Principal, OwnershipTransfer, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(OWNERSHIP_TRANSFERS_MEMORY_ID)),
+        )
+    );
+
+    pub static ARCHIVED_WALLETS: RefCell<StableBTreeMap<Principal, ArchivedWallet, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(ARCHIVED_WALLETS_MEMORY_ID)),
+        )
+    );
+
+    pub static WASM_VERSIONS: RefCell<StableBTreeMap<String, WasmVersion, Memory>> = RefCell::new(
+        StableBTreeMap::init(
+            MEMORY_MANAGER.with(|m| m.borrow().get(WASM_VERSIONS_MEMORY_ID)),
+        )
+    );
+
+    // the number of wallets by their last health check, `None` for unchecked wallets. Kept in
+    // the heap as it is rebuilt with the indexes on every upgrade
+    pub static FLEET_COUNTS: RefCell<HashMap<Option<HealthSummary>, u64>> =
+        RefCell::new(HashMap::new());
 }
@@ -1,14 +1,93 @@
+use std::ops::{Bound, RangeBounds};
+
 use ic_stable_structures::{memory_manager::MemoryId, StableBTreeMap, Storable};
 
 use crate::types::error::Error;
 
-use super::state::{StaticStorageRef, MEMORY_MANAGER};
+use super::state::{StaticIndexStorageRef, StaticStorageRef, MEMORY_MANAGER};
 
-pub trait Storage<K: Storable + Ord + Clone, V: Storable + Clone> {
+pub trait Storage<K: 'static + Storable + Ord + Clone, V: 'static + Storable + Clone> {
     const NAME: &'static str;
 
     fn memory_id() -> MemoryId;
     fn storage() -> StaticStorageRef<K, V>;
+
+    /// The secondary indexes of the storage, kept up to date by the insert, update and remove
+    /// methods of the storage traits and queried through `StorageIndexed`
+    fn indexes() -> Vec<&'static dyn IndexWriter<K, V>> {
+        vec![]
+    }
+}
+
+/// A secondary index of a `Storage<K, V>`, stored as `(index key, Some(key))` entries in its own
+/// map so all keys for an index key are found with a range query. `None` sorts before any key
+/// and is used as the lower bound of those ranges, it is never stored.
+pub trait StorageIndex<K: 'static + Storable + Ord + Clone, V> {
+    type Key: 'static + Storable + Ord + Clone;
+
+    const NAME: &'static str;
+
+    fn memory_id() -> MemoryId;
+    fn storage() -> StaticIndexStorageRef<Self::Key, K>;
+
+    /// The index keys derived from a value, a value can have no, one or multiple index keys
+    fn index_keys(value: &V) -> Vec<Self::Key>;
+}
+
+/// Object safe write access to a `StorageIndex`, used by `Storage::indexes`
+pub trait IndexWriter<K, V> {
+    fn insert(&self, key: &K, value: &V);
+    fn remove(&self, key: &K, value: &V);
+    fn clear(&self);
+}
+
+impl<K, V, I> IndexWriter<K, V> for I
+where
+    K: 'static + Storable + Ord + Clone,
+    I: StorageIndex<K, V>,
+{
+    fn insert(&self, key: &K, value: &V) {
+        I::storage().with(|index| {
+            for index_key in I::index_keys(value) {
+                index
+                    .borrow_mut()
+                    .insert((index_key, Some(key.clone())), ());
+            }
+        })
+    }
+
+    fn remove(&self, key: &K, value: &V) {
+        I::storage().with(|index| {
+            for index_key in I::index_keys(value) {
+                index.borrow_mut().remove(&(index_key, Some(key.clone())));
+            }
+        })
+    }
+
+    fn clear(&self) {
+        I::storage().with(|index| {
+            index.replace(StableBTreeMap::new(
+                MEMORY_MANAGER.with(|m| m.borrow().get(I::memory_id())),
+            ))
+        });
+    }
+}
+
+// Replaces the index entries of `previous` with those of `value`
+fn reindex<K, V, S>(key: &K, previous: Option<&V>, value: Option<&V>)
+where
+    K: 'static + Storable + Ord + Clone,
+    V: 'static + Storable + Clone,
+    S: Storage<K, V> + ?Sized,
+{
+    for index in S::indexes() {
+        if let Some(previous) = previous {
+            index.remove(key, previous);
+        }
+        if let Some(value) = value {
+            index.insert(key, value);
+        }
+    }
 }
 
 pub trait StorageQueryable<K: 'static + Storable + Ord + Clone, V: 'static + Storable + Clone>:
@@ -89,6 +168,73 @@ pub trait StorageQueryable<K: 'static + Storable + Ord + Clone, V: 'static + Sto
         })
     }
 
+    /// Get a page of entities by filter, ordered by key
+    /// # Arguments
+    /// * `cursor` - The key of the last entity of the previous page, `None` for the first page
+    /// * `limit` - The maximum number of entities to return
+    /// * `filter` - The filter to apply
+    /// # Returns
+    /// * `(Vec<(K, V)>, Option<K>)` - The entities and the cursor of the next page, `None` if this is the last page
+    fn get_page<F>(cursor: Option<K>, limit: usize, filter: F) -> (Vec<(K, V)>, Option<K>)
+    where
+        F: Fn(&K, &V) -> bool,
+    {
+        Self::storage().with(|data| {
+            let data = data.borrow();
+
+            let start = match cursor {
+                Some(cursor) => Bound::Excluded(cursor),
+                None => Bound::Unbounded,
+            };
+
+            let mut entities = data
+                .range((start, Bound::Unbounded))
+                .filter(|(id, value)| filter(id, value));
+
+            let page: Vec<(K, V)> = entities.by_ref().take(limit).collect();
+
+            let next_cursor = match entities.next() {
+                Some(_) => page.last().map(|(id, _)| id.clone()),
+                None => None,
+            };
+
+            (page, next_cursor)
+        })
+    }
+
+    /// Get the entities in a range of keys
+    /// # Arguments
+    /// * `range` - The range of keys
+    /// # Returns
+    /// * `Vec<(K, V)>` - The entities ordered by key, otherwise an empty vector
+    fn range<R: RangeBounds<K>>(range: R) -> Vec<(K, V)> {
+        Self::storage().with(|data| data.borrow().range(range).collect())
+    }
+
+    /// Count the entities by filter
+    /// # Arguments
+    /// * `filter` - The filter to apply
+    /// # Returns
+    /// * `u64` - The number of entities that match the filter
+    fn count<F>(filter: F) -> u64
+    where
+        F: Fn(&K, &V) -> bool,
+    {
+        Self::storage().with(|data| {
+            data.borrow()
+                .iter()
+                .filter(|(id, value)| filter(id, value))
+                .count() as u64
+        })
+    }
+
+    /// Get the number of entities
+    /// # Returns
+    /// * `u64` - The number of entities
+    fn len() -> u64 {
+        Self::storage().with(|data| data.borrow().len())
+    }
+
     /// Check if an entity exists by key
     /// # Arguments
     /// * `key` - The key of the entity to check
@@ -123,6 +269,7 @@ pub trait StorageInsertable<V: 'static + Storable + Clone>: Storage<u64, V> {
             }
 
             data.borrow_mut().insert(key, value.clone());
+            reindex::<u64, V, Self>(&key, None, Some(&value));
             Ok((key, value))
         })
     }
@@ -149,6 +296,7 @@ pub trait StorageInsertableByKey<K: 'static + Storable + Ord + Clone, V: 'static
             }
 
             data.borrow_mut().insert(key.clone(), value.clone());
+            reindex::<K, V, Self>(&key, None, Some(&value));
             Ok((key, value))
         })
     }
@@ -174,14 +322,16 @@ pub trait StorageUpdateable<K: 'static + Storable + Ord + Clone, V: 'static + St
                     .add_message("Key does not exist"));
             }
 
-            data.borrow_mut().insert(key.clone(), value.clone());
+            let previous = data.borrow_mut().insert(key.clone(), value.clone());
+            reindex::<K, V, Self>(&key, previous.as_ref(), Some(&value));
             Ok((key, value))
         })
     }
 
     fn upsert(key: K, value: V) -> Result<(K, V), Error> {
         Self::storage().with(|data| {
-            data.borrow_mut().insert(key.clone(), value.clone());
+            let previous = data.borrow_mut().insert(key.clone(), value.clone());
+            reindex::<K, V, Self>(&key, previous.as_ref(), Some(&value));
             Ok((key, value))
         })
     }
@@ -199,7 +349,8 @@ pub trait StorageUpdateable<K: 'static + Storable + Ord + Clone, V: 'static + St
                     .add_info(Self::NAME)
                     .add_message("Key does not exist"));
             }
-            data.borrow_mut().remove(&key);
+            let previous = data.borrow_mut().remove(&key);
+            reindex::<K, V, Self>(&key, previous.as_ref(), None);
             Ok(())
         })
     }
@@ -210,17 +361,83 @@ pub trait StorageUpdateable<K: 'static + Storable + Ord + Clone, V: 'static + St
     fn remove_many(keys: Vec<K>) {
         Self::storage().with(|data| {
             for key in keys {
-                data.borrow_mut().remove(&key);
+                let previous = data.borrow_mut().remove(&key);
+                reindex::<K, V, Self>(&key, previous.as_ref(), None);
             }
         })
     }
 
-    /// Clear all entities
+    /// Clear all entities and their index entries
     fn clear() {
         Self::storage().with(|n| {
             n.replace(StableBTreeMap::new(
                 MEMORY_MANAGER.with(|m| m.borrow().get(Self::memory_id())),
             ))
         });
+
+        for index in Self::indexes() {
+            index.clear();
+        }
+    }
+}
+
+pub trait StorageIndexed<K: 'static + Storable + Ord + Clone, V: 'static + Storable + Clone>:
+    Storage<K, V> + StorageQueryable<K, V>
+{
+    /// Find all entities by index key
+    /// # Arguments
+    /// * `index_key` - The index key of the entities to find
+    /// # Returns
+    /// * `Vec<(K, V)>` - The entities ordered by key, otherwise an empty vector
+    fn find_by<I: StorageIndex<K, V>>(index_key: I::Key) -> Vec<(K, V)> {
+        Self::range_by::<I, _>(index_key.clone()..=index_key)
+    }
+
+    /// Find all entities by a range of index keys
+    /// # Arguments
+    /// * `range` - The range of index keys of the entities to find
+    /// # Returns
+    /// * `Vec<(K, V)>` - The entities ordered by index key and key, otherwise an empty vector
+    fn range_by<I, R>(range: R) -> Vec<(K, V)>
+    where
+        I: StorageIndex<K, V>,
+        R: RangeBounds<I::Key>,
+    {
+        let start = match range.start_bound() {
+            Bound::Included(start) | Bound::Excluded(start) => {
+                Bound::Included((start.clone(), None))
+            }
+            Bound::Unbounded => Bound::Unbounded,
+        };
+
+        let keys: Vec<K> = I::storage().with(|index| {
+            index
+                .borrow()
+                .range((start, Bound::Unbounded))
+                .skip_while(|((index_key, _), _)| !range.contains(index_key))
+                .take_while(|((index_key, _), _)| range.contains(index_key))
+                .filter_map(|((_, key), _)| key)
+                .collect()
+        });
+
+        Self::get_many(keys)
+    }
+
+    /// Rebuild the indexes from the stored entities, used to index entities that were stored
+    /// before an index was added
+    fn rebuild_indexes() {
+        let indexes = Self::indexes();
+
+        for index in &indexes {
+            index.clear();
+        }
+
+        Self::storage().with(|data| {
+            for (key, value) in data.borrow().iter() {
+                for index in &indexes {
+                    index.insert(&key, &value);
+                }
+            }
+        });
     }
 }
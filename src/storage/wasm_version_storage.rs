@@ -0,0 +1,27 @@
+use ic_stable_structures::memory_manager::MemoryId;
+
+use crate::types::wasm_version::WasmVersion;
+
+use super::{
+    state::{StaticStorageRef, WASM_VERSIONS, WASM_VERSIONS_MEMORY_ID},
+    storage_api::{Storage, StorageQueryable, StorageUpdateable},
+};
+
+/// Wallet wasm versions by their hex encoded sha256, wallets with another module can not be
+/// imported
+pub struct WasmVersionStorage;
+
+impl Storage<String, WasmVersion> for WasmVersionStorage {
+    const NAME: &'static str = "wasm_versions";
+
+    fn storage() -> StaticStorageRef<String, WasmVersion> {
+        &WASM_VERSIONS
+    }
+
+    fn memory_id() -> MemoryId {
+        WASM_VERSIONS_MEMORY_ID
+    }
+}
+
+impl StorageQueryable<String, WasmVersion> for WasmVersionStorage {}
+impl StorageUpdateable<String, WasmVersion> for WasmVersionStorage {}
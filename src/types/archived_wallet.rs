@@ -0,0 +1,16 @@
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+
+use crate::{impl_storable_for, types::wallet_data::WalletData};
+
+impl_storable_for!(ArchivedWallet);
+
+/// A decommissioned wallet, its canister is deleted and its data is kept for reference
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct ArchivedWallet {
+    pub wallet: WalletData,
+    pub archived_at: u64,
+    pub archived_by: Principal,
+    /// Cycles the wallet sent back to the index before it was deleted
+    pub cycles_returned: u64,
+}
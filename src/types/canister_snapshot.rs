@@ -0,0 +1,31 @@
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+
+/// Snapshot of the memory and module of a wallet canister, as returned by the management
+/// canister
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct CanisterSnapshot {
+    pub id: Vec<u8>,
+    pub taken_at_timestamp: u64,
+    pub total_size: u64,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct TakeCanisterSnapshotArgs {
+    pub canister_id: Principal,
+    /// The snapshot to replace, a canister can only have a limited number of snapshots
+    pub replace_snapshot: Option<Vec<u8>>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct LoadCanisterSnapshotArgs {
+    pub canister_id: Principal,
+    pub snapshot_id: Vec<u8>,
+    pub sender_canister_version: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct DeleteCanisterSnapshotArgs {
+    pub canister_id: Principal,
+    pub snapshot_id: Vec<u8>,
+}
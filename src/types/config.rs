@@ -0,0 +1,247 @@
+use std::collections::HashSet;
+
+use candid::{CandidType, Principal};
+use ic_ledger_types::{Tokens, MAINNET_CYCLES_MINTING_CANISTER_ID, MAINNET_LEDGER_CANISTER_ID};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    impl_storable_for,
+    storage::state::{
+        CATALYZE_E8S_FEE, DEFAULT_MAX_SIGNERS, ICP_TRANSACTION_FEE, MIN_CYCLES_FOR_SPINUP,
+        MIN_SIGNERS,
+    },
+    types::{error::Error, result::CanisterResult},
+};
+
+impl_storable_for!(Config);
+
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct Config {
+    ledger_canister_id: Principal,
+    cmc_canister_id: Principal,
+    admins: Vec<Principal>,
+    catalyze_fee: Tokens,
+    min_cycles_for_spinup: u64,
+    treasury: Option<Principal>,
+    wallet_policy: WalletPolicy,
+    /// Maximum length of a wallet whitelist, `None` for configs stored before it was added
+    max_signers: Option<u64>,
+}
+
+/// How many wallets a group can have
+#[derive(CandidType, Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WalletPolicy {
+    /// A group has at most one wallet
+    #[default]
+    SingleWallet,
+    /// A group can have multiple wallets, each with a label that is unique within the group
+    MultiWallet,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            ledger_canister_id: MAINNET_LEDGER_CANISTER_ID,
+            cmc_canister_id: MAINNET_CYCLES_MINTING_CANISTER_ID,
+            admins: vec![Principal::from_text(
+                "ledm3-52ncq-rffuv-6ed44-hg5uo-iicyu-pwkzj-syfva-heo4k-p7itq-aqe",
+            )
+            .unwrap()],
+            catalyze_fee: CATALYZE_E8S_FEE,
+            min_cycles_for_spinup: MIN_CYCLES_FOR_SPINUP,
+            treasury: None,
+            wallet_policy: WalletPolicy::default(),
+            max_signers: Some(DEFAULT_MAX_SIGNERS),
+        }
+    }
+}
+
+impl Config {
+    pub fn from_init_args(args: InitArgs) -> Self {
+        let default = Self::default();
+
+        Self {
+            ledger_canister_id: args
+                .ledger_canister_id
+                .unwrap_or(default.ledger_canister_id),
+            cmc_canister_id: args.cmc_canister_id.unwrap_or(default.cmc_canister_id),
+            admins: args.admins,
+            catalyze_fee: args.catalyze_fee.unwrap_or(default.catalyze_fee),
+            min_cycles_for_spinup: args
+                .min_cycles_for_spinup
+                .unwrap_or(default.min_cycles_for_spinup),
+            treasury: args.treasury,
+            wallet_policy: args.wallet_policy.unwrap_or(default.wallet_policy),
+            max_signers: args.max_signers.or(default.max_signers),
+        }
+    }
+
+    /// Overwrites the values that are set in the args, unset values are kept
+    pub fn apply_upgrade_args(&mut self, args: UpgradeArgs) -> Self {
+        if let Some(ledger_canister_id) = args.ledger_canister_id {
+            self.ledger_canister_id = ledger_canister_id;
+        }
+
+        if let Some(cmc_canister_id) = args.cmc_canister_id {
+            self.cmc_canister_id = cmc_canister_id;
+        }
+
+        if let Some(admins) = args.admins {
+            self.admins = admins;
+        }
+
+        if let Some(catalyze_fee) = args.catalyze_fee {
+            self.catalyze_fee = catalyze_fee;
+        }
+
+        if let Some(min_cycles_for_spinup) = args.min_cycles_for_spinup {
+            self.min_cycles_for_spinup = min_cycles_for_spinup;
+        }
+
+        if let Some(treasury) = args.treasury {
+            self.treasury = Some(treasury);
+        }
+
+        if let Some(wallet_policy) = args.wallet_policy {
+            self.wallet_policy = wallet_policy;
+        }
+
+        if let Some(max_signers) = args.max_signers {
+            self.max_signers = Some(max_signers);
+        }
+
+        self.clone()
+    }
+
+    pub fn validate(&self) -> CanisterResult<()> {
+        let mut error = Error::bad_request().add_method_name("validate_config");
+        let mut valid = true;
+
+        if self.ledger_canister_id == Principal::anonymous() {
+            error = error.add_info("ledger_canister_id can not be anonymous");
+            valid = false;
+        }
+
+        if self.cmc_canister_id == Principal::anonymous() {
+            error = error.add_info("cmc_canister_id can not be anonymous");
+            valid = false;
+        }
+
+        if self.ledger_canister_id == self.cmc_canister_id {
+            error = error.add_info("ledger_canister_id and cmc_canister_id must differ");
+            valid = false;
+        }
+
+        if self.admins.is_empty() {
+            error = error.add_info("at least one admin is required");
+            valid = false;
+        }
+
+        let mut seen = HashSet::new();
+        for admin in self.admins.iter() {
+            if *admin == Principal::anonymous() {
+                error = error.add_info("admins can not contain the anonymous principal");
+                valid = false;
+            }
+
+            if !seen.insert(admin) {
+                error = error.add_info(&format!("duplicate admin: {}", admin));
+                valid = false;
+            }
+        }
+
+        // the fee has to cover the ledger fees of the cycles minting and treasury transfers
+        let min_catalyze_fee = ICP_TRANSACTION_FEE + ICP_TRANSACTION_FEE;
+        if self.catalyze_fee <= min_catalyze_fee {
+            error = error.add_info(&format!(
+                "catalyze_fee must be more than {} e8s",
+                min_catalyze_fee.e8s()
+            ));
+            valid = false;
+        }
+
+        if self.min_cycles_for_spinup == 0 {
+            error = error.add_info("min_cycles_for_spinup must be more than 0");
+            valid = false;
+        }
+
+        if self.treasury == Some(Principal::anonymous()) {
+            error = error.add_info("treasury can not be anonymous");
+            valid = false;
+        }
+
+        if self.max_signers() < MIN_SIGNERS as u64 {
+            error = error.add_info(&format!("max_signers must be at least {}", MIN_SIGNERS));
+            valid = false;
+        }
+
+        match valid {
+            true => Ok(()),
+            false => Err(error.add_message("Invalid config")),
+        }
+    }
+
+    pub fn ledger_canister_id(&self) -> Principal {
+        self.ledger_canister_id
+    }
+
+    pub fn cmc_canister_id(&self) -> Principal {
+        self.cmc_canister_id
+    }
+
+    pub fn is_admin(&self, principal: Principal) -> bool {
+        self.admins.contains(&principal)
+    }
+
+    pub fn catalyze_fee(&self) -> Tokens {
+        self.catalyze_fee
+    }
+
+    pub fn min_cycles_for_spinup(&self) -> u64 {
+        self.min_cycles_for_spinup
+    }
+
+    pub fn treasury(&self) -> Option<Principal> {
+        self.treasury
+    }
+
+    pub fn wallet_policy(&self) -> WalletPolicy {
+        self.wallet_policy
+    }
+
+    pub fn max_signers(&self) -> u64 {
+        self.max_signers.unwrap_or(DEFAULT_MAX_SIGNERS)
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub enum IndexArgs {
+    Init(InitArgs),
+    Upgrade(Option<UpgradeArgs>),
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct InitArgs {
+    pub proxy_canister_id: Principal,
+    pub admins: Vec<Principal>,
+    pub ledger_canister_id: Option<Principal>,
+    pub cmc_canister_id: Option<Principal>,
+    pub catalyze_fee: Option<Tokens>,
+    pub min_cycles_for_spinup: Option<u64>,
+    pub treasury: Option<Principal>,
+    pub wallet_policy: Option<WalletPolicy>,
+    pub max_signers: Option<u64>,
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct UpgradeArgs {
+    pub proxy_canister_id: Option<Principal>,
+    pub admins: Option<Vec<Principal>>,
+    pub ledger_canister_id: Option<Principal>,
+    pub cmc_canister_id: Option<Principal>,
+    pub catalyze_fee: Option<Tokens>,
+    pub min_cycles_for_spinup: Option<u64>,
+    pub treasury: Option<Principal>,
+    pub wallet_policy: Option<WalletPolicy>,
+    pub max_signers: Option<u64>,
+}
@@ -1,9 +1,11 @@
 use std::fmt;
 
 use candid::CandidType;
-use ic_cdk::api::time;
 use serde::{Deserialize, Serialize};
 
+#[cfg(not(test))]
+use crate::services::environment::{Environment, IcEnvironment};
+
 #[derive(Clone, CandidType, Debug, Serialize, Deserialize)]
 pub struct Error {
     tag: Option<String>,
@@ -22,10 +24,21 @@ impl Error {
             method_name: None,
             error_type,
             info: None,
-            timestamp: time(),
+            timestamp: Self::now(),
         }
     }
 
+    #[cfg(not(test))]
+    fn now() -> u64 {
+        IcEnvironment.time()
+    }
+
+    // errors are also created outside of a canister by the unit tests
+    #[cfg(test)]
+    fn now() -> u64 {
+        crate::services::fakes::system_time()
+    }
+
     pub fn insufficient_balance() -> Self {
         Self::new(ErrorKind::InsufficientBalance)
     }
@@ -66,6 +79,10 @@ impl Error {
         Self::new(ErrorKind::Duplicate)
     }
 
+    pub fn rate_limited() -> Self {
+        Self::new(ErrorKind::RateLimited)
+    }
+
     pub fn add_tag(mut self, tag: &str) -> Self {
         self.tag = Some(tag.to_string());
         self
@@ -86,6 +103,14 @@ impl Error {
         self
     }
 
+    pub fn kind(&self) -> &ErrorKind {
+        &self.error_type
+    }
+
+    pub fn info(&self) -> Option<&Vec<String>> {
+        self.info.as_ref()
+    }
+
     pub fn add_method_name(mut self, method_name: &str) -> Self {
         self.method_name = Some(method_name.to_string());
         self
@@ -104,6 +129,7 @@ pub enum ErrorKind {
     InsufficientBalance,
     SerializeError,
     DeserializeError,
+    RateLimited,
 }
 
 impl fmt::Display for Error {
@@ -130,6 +156,7 @@ impl fmt::Display for ErrorKind {
             InsufficientBalance => write!(f, "InsufficientBalance"),
             SerializeError => write!(f, "SerializeError"),
             DeserializeError => write!(f, "DeserializeError"),
+            RateLimited => write!(f, "RateLimited"),
         }
     }
 }
@@ -0,0 +1,112 @@
+use candid::{CandidType, Principal};
+use ic_ledger_types::Tokens;
+use serde::{Deserialize, Serialize};
+
+use crate::impl_storable_for;
+
+impl_storable_for!(IndexEvent);
+
+/// Entry of the event log, the id of an event is its position in the log
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct IndexEvent {
+    created_at: u64,
+    kind: IndexEventKind,
+}
+
+impl IndexEvent {
+    pub fn new(kind: IndexEventKind, created_at: u64) -> Self {
+        Self { created_at, kind }
+    }
+
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    pub fn kind(&self) -> &IndexEventKind {
+        &self.kind
+    }
+}
+
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub enum IndexEventKind {
+    SpawnStarted {
+        icp_transfer_blockheight: u64,
+        caller: Principal,
+        group_id: u64,
+    },
+    SpawnFinished {
+        icp_transfer_blockheight: u64,
+        wallet: Principal,
+    },
+    SpawnFailed {
+        icp_transfer_blockheight: u64,
+        error: String,
+    },
+    ToppedUp {
+        icp_transfer_blockheight: u64,
+        wallet: Principal,
+        cycles: u64,
+    },
+    OwnershipTransferProposed {
+        wallet: Principal,
+        from: Principal,
+        to: Principal,
+        expires_at: u64,
+    },
+    OwnershipTransferCancelled {
+        wallet: Principal,
+        cancelled_by: Principal,
+    },
+    OwnershipTransferred {
+        wallet: Principal,
+        previous_owner: Principal,
+        new_owner: Principal,
+    },
+    OwnerRepaired {
+        wallet: Principal,
+        previous_owner: Principal,
+        owner: Principal,
+    },
+    ControllersChanged {
+        wallet: Principal,
+        caller: Principal,
+        controllers: Vec<Principal>,
+    },
+    WalletImported {
+        wallet: Principal,
+        caller: Principal,
+        group_id: u64,
+        owner: Principal,
+    },
+    WalletUpgraded {
+        wallet: Principal,
+        caller: Principal,
+        version: String,
+        snapshot_id: Vec<u8>,
+    },
+    WalletRestored {
+        wallet: Principal,
+        caller: Principal,
+        snapshot_id: Vec<u8>,
+    },
+    WalletDecommissioned {
+        wallet: Principal,
+        caller: Principal,
+        cycles_returned: u64,
+    },
+    WasmUploaded {
+        version: String,
+    },
+    WasmRegistered {
+        version: String,
+    },
+    ConfigChanged {
+        caller: Principal,
+    },
+    Refunded {
+        icp_transfer_blockheight: u64,
+        to: Principal,
+        amount: Tokens,
+        blockheight: u64,
+    },
+}
@@ -0,0 +1,99 @@
+use candid::CandidType;
+use ic_ledger_types::{AccountIdentifier, Memo, Timestamp, Tokens, TransferArgs};
+use serde::{Deserialize, Serialize};
+
+use crate::{impl_storable_for, storage::state::ICP_TRANSACTION_FEE};
+
+impl_storable_for!(LedgerTransfer);
+
+/// The blockheight of the ICP transfer that started the flow and the step of the flow
+pub type LedgerTransferKey = (u64, u8);
+
+#[derive(CandidType, Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferStep {
+    Refund,
+    CyclesManagement,
+    Fee,
+}
+
+impl TransferStep {
+    /// Storage key of the transfer made for this step of the flow started by `blockheight`,
+    /// the transfers of a blockheight are next to each other
+    pub fn key(&self, blockheight: u64) -> LedgerTransferKey {
+        let step = match self {
+            TransferStep::Refund => 0,
+            TransferStep::CyclesManagement => 1,
+            TransferStep::Fee => 2,
+        };
+
+        (blockheight, step)
+    }
+}
+
+/// A ledger transfer made by the index, persisted before it is sent so that a retry
+/// sends the exact same arguments and is deduplicated by the ledger
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct LedgerTransfer {
+    origin_blockheight: u64,
+    step: TransferStep,
+    memo: u64,
+    created_at_time: u64,
+    amount: Tokens,
+    to: AccountIdentifier,
+    blockheight: Option<u64>,
+}
+
+impl LedgerTransfer {
+    pub fn new(
+        origin_blockheight: u64,
+        step: TransferStep,
+        memo: Memo,
+        created_at_time: u64,
+        amount: Tokens,
+        to: AccountIdentifier,
+    ) -> Self {
+        Self {
+            origin_blockheight,
+            step,
+            memo: memo.0,
+            created_at_time,
+            amount,
+            to,
+            blockheight: None,
+        }
+    }
+
+    pub fn key(&self) -> LedgerTransferKey {
+        self.step.key(self.origin_blockheight)
+    }
+
+    pub fn origin_blockheight(&self) -> u64 {
+        self.origin_blockheight
+    }
+
+    pub fn step(&self) -> TransferStep {
+        self.step
+    }
+
+    pub fn blockheight(&self) -> Option<u64> {
+        self.blockheight
+    }
+
+    pub fn set_blockheight(&mut self, blockheight: u64) -> Self {
+        self.blockheight = Some(blockheight);
+        self.clone()
+    }
+
+    pub fn to_transfer_args(&self) -> TransferArgs {
+        TransferArgs {
+            memo: Memo(self.memo),
+            amount: self.amount,
+            fee: ICP_TRANSACTION_FEE,
+            from_subaccount: None,
+            to: self.to,
+            created_at_time: Some(Timestamp {
+                timestamp_nanos: self.created_at_time,
+            }),
+        }
+    }
+}
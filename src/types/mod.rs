@@ -1,5 +1,20 @@
+pub mod archived_wallet;
+pub mod canister_snapshot;
+pub mod config;
 pub mod error;
+pub mod index_event;
+pub mod ledger_transfer;
 pub mod macros;
+pub mod multisig_event;
+pub mod notification;
+pub mod notification_counter;
+pub mod owner_reconciliation;
+pub mod ownership_transfer;
+pub mod page;
 pub mod result;
 pub mod spawn_status;
+pub mod wallet_config;
 pub mod wallet_data;
+pub mod wallet_health;
+pub mod wallet_snapshot;
+pub mod wasm_version;
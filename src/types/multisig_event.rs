@@ -0,0 +1,108 @@
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+
+/// Notification about a wallet, sent by the wallet itself or by the index for the lifecycle
+/// events it handles, the proxy receives it together with the wallet
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub enum MultisigEvent {
+    WhitelistNotice {
+        receivers: Vec<Principal>,
+        group_id: u64,
+    },
+    NewProposal {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        proposal_id: u64,
+    },
+    ProposalAccepted {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        proposal_id: u64,
+    },
+    ProposalDeclined {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        proposal_id: u64,
+    },
+    ProposalStatusUpdate {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        proposal_id: u64,
+    },
+    ThresholdChanged {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        threshold: u64,
+    },
+    OwnerTransferred {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        new_owner: Principal,
+    },
+    LowCycles {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        cycles: u64,
+    },
+    WalletSpawned {
+        receivers: Vec<Principal>,
+        group_id: u64,
+    },
+    WalletToppedUp {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        cycles: u64,
+    },
+    WalletUpgraded {
+        receivers: Vec<Principal>,
+        group_id: u64,
+        wasm_version: String,
+    },
+}
+
+impl MultisigEvent {
+    pub fn group_id(&self) -> u64 {
+        match self {
+            Self::WhitelistNotice { group_id, .. }
+            | Self::NewProposal { group_id, .. }
+            | Self::ProposalAccepted { group_id, .. }
+            | Self::ProposalDeclined { group_id, .. }
+            | Self::ProposalStatusUpdate { group_id, .. }
+            | Self::ThresholdChanged { group_id, .. }
+            | Self::OwnerTransferred { group_id, .. }
+            | Self::LowCycles { group_id, .. }
+            | Self::WalletSpawned { group_id, .. }
+            | Self::WalletToppedUp { group_id, .. }
+            | Self::WalletUpgraded { group_id, .. } => *group_id,
+        }
+    }
+
+    /// Events about the lifecycle of a wallet that only the index sends, a wallet can not
+    /// send them about itself
+    pub fn is_index_event(&self) -> bool {
+        matches!(
+            self,
+            Self::OwnerTransferred { .. }
+                | Self::LowCycles { .. }
+                | Self::WalletSpawned { .. }
+                | Self::WalletToppedUp { .. }
+                | Self::WalletUpgraded { .. }
+        )
+    }
+
+    pub fn receivers(&self) -> &[Principal] {
+        match self {
+            Self::WhitelistNotice { receivers, .. }
+            | Self::NewProposal { receivers, .. }
+            | Self::ProposalAccepted { receivers, .. }
+            | Self::ProposalDeclined { receivers, .. }
+            | Self::ProposalStatusUpdate { receivers, .. }
+            | Self::ThresholdChanged { receivers, .. }
+            | Self::OwnerTransferred { receivers, .. }
+            | Self::LowCycles { receivers, .. }
+            | Self::WalletSpawned { receivers, .. }
+            | Self::WalletToppedUp { receivers, .. }
+            | Self::WalletUpgraded { receivers, .. } => receivers,
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    impl_storable_for,
+    storage::state::{NOTIFICATION_RETRY_BASE_DELAY, NOTIFICATION_RETRY_MAX_DELAY},
+};
+
+impl_storable_for!(Notification);
+
+/// A call to the proxy canister that is persisted until it is delivered
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct Notification {
+    method: String,
+    /// Candid encoded arguments of the call
+    args: Vec<u8>,
+    created_at: u64,
+    attempts: u32,
+    next_attempt_at: u64,
+    last_error: Option<String>,
+}
+
+impl Notification {
+    pub fn new(method: &str, args: Vec<u8>, created_at: u64) -> Self {
+        Self {
+            method: method.to_string(),
+            args,
+            created_at,
+            attempts: 0,
+            next_attempt_at: created_at,
+            last_error: None,
+        }
+    }
+
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    pub fn args(&self) -> &[u8] {
+        &self.args
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    pub fn is_due(&self, now: u64) -> bool {
+        self.next_attempt_at <= now
+    }
+
+    /// Records a failed attempt, the delay before the next attempt doubles with every failure
+    pub fn failed(&mut self, error: String, now: u64) -> Self {
+        let delay = NOTIFICATION_RETRY_BASE_DELAY
+            .saturating_mul(1 << self.attempts.min(32))
+            .min(NOTIFICATION_RETRY_MAX_DELAY);
+
+        self.attempts += 1;
+        self.next_attempt_at = now + delay;
+        self.last_error = Some(error);
+        self.clone()
+    }
+
+    /// Makes a dead letter due again with a fresh number of attempts
+    pub fn reset(&mut self, now: u64) -> Self {
+        self.attempts = 0;
+        self.next_attempt_at = now;
+        self.clone()
+    }
+}
@@ -0,0 +1,53 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    impl_storable_for,
+    storage::state::{NOTIFICATION_RATE_LIMIT, NOTIFICATION_RATE_WINDOW},
+};
+
+impl_storable_for!(NotificationCounter);
+
+/// Notifications sent by a wallet, the rate limit applies to a fixed window
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, Default)]
+pub struct NotificationCounter {
+    window_start: u64,
+    window_count: u32,
+    accepted: u64,
+    rejected: u64,
+}
+
+impl NotificationCounter {
+    pub fn window_count(&self) -> u32 {
+        self.window_count
+    }
+
+    pub fn accepted(&self) -> u64 {
+        self.accepted
+    }
+
+    pub fn rejected(&self) -> u64 {
+        self.rejected
+    }
+
+    /// Counts a notification, returns `false` when the wallet exceeded the rate limit
+    pub fn try_accept(&mut self, now: u64) -> bool {
+        if now >= self.window_start.saturating_add(NOTIFICATION_RATE_WINDOW) {
+            self.window_start = now;
+            self.window_count = 0;
+        }
+
+        if self.window_count >= NOTIFICATION_RATE_LIMIT {
+            return false;
+        }
+
+        self.window_count += 1;
+        self.accepted += 1;
+        true
+    }
+
+    /// Counts a notification that was rejected for any reason
+    pub fn reject(&mut self) {
+        self.rejected += 1;
+    }
+}
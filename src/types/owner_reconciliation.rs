@@ -0,0 +1,21 @@
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+
+/// A wallet whose owner in the index is not the owner the wallet itself has
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct OwnerMismatch {
+    pub index_owner: Principal,
+    pub wallet_owner: Principal,
+}
+
+/// Result of comparing the owners of a page of wallets
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, Default)]
+pub struct OwnerReconciliation {
+    pub mismatches: Vec<(Principal, OwnerMismatch)>,
+    /// Wallets whose owner could not be read, with the error
+    pub unreachable: Vec<(Principal, String)>,
+    /// The number of wallets that were compared
+    pub checked: u64,
+    /// Pass as `cursor` to check the next page, `None` if this was the last page
+    pub next_cursor: Option<Principal>,
+}
@@ -0,0 +1,47 @@
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+
+use crate::{impl_storable_for, storage::state::OWNERSHIP_TRANSFER_TTL};
+
+impl_storable_for!(OwnershipTransfer);
+
+/// Nomination of a new owner for a wallet, the owner changes when the nominee accepts it
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct OwnershipTransfer {
+    from: Principal,
+    to: Principal,
+    created_at: u64,
+    expires_at: u64,
+}
+
+impl OwnershipTransfer {
+    pub fn new(from: Principal, to: Principal, created_at: u64) -> Self {
+        Self {
+            from,
+            to,
+            created_at,
+            expires_at: created_at + OWNERSHIP_TRANSFER_TTL,
+        }
+    }
+
+    pub fn from(&self) -> Principal {
+        self.from
+    }
+
+    pub fn to(&self) -> Principal {
+        self.to
+    }
+
+    pub fn expires_at(&self) -> u64 {
+        self.expires_at
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+
+    /// The current owner and the nominee can both cancel the transfer
+    pub fn is_party(&self, principal: Principal) -> bool {
+        self.from == principal || self.to == principal
+    }
+}
@@ -0,0 +1,38 @@
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::storage::state::{DEFAULT_PAGE_LIMIT, MAX_PAGE_LIMIT};
+
+#[derive(CandidType, Deserialize, Clone, Debug)]
+pub struct PageArgs<K> {
+    /// The key of the last entry of the previous page, `None` for the first page
+    pub cursor: Option<K>,
+    /// Defaults to 100, capped at 500
+    pub limit: Option<u64>,
+}
+
+impl<K> Default for PageArgs<K> {
+    fn default() -> Self {
+        Self {
+            cursor: None,
+            limit: None,
+        }
+    }
+}
+
+impl<K> PageArgs<K> {
+    pub fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_LIMIT)
+            .clamp(1, MAX_PAGE_LIMIT) as usize
+    }
+}
+
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug)]
+pub struct Page<K, V> {
+    pub items: Vec<(K, V)>,
+    /// Pass as `cursor` to get the next page, `None` if this is the last page
+    pub next_cursor: Option<K>,
+    /// The number of entries that match the filter over all pages
+    pub total: u64,
+}
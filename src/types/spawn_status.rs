@@ -16,6 +16,7 @@ pub struct SpawnStatus {
     canister_spawned: Option<Principal>,
     canister_installed: Option<Principal>,
     done: Option<()>,
+    group_id: Option<u64>,
 }
 
 impl Default for SpawnStatus {
@@ -35,9 +36,42 @@ impl SpawnStatus {
             canister_spawned: None,
             canister_installed: None,
             done: None,
+            group_id: None,
         }
     }
 
+    pub fn set_group_id(mut self, group_id: u64) -> Self {
+        self.group_id = Some(group_id);
+        self
+    }
+
+    /// An unfinished spawn is only resumed by the same flow for the same group
+    pub fn is_resumed_by(&self, status: &SpawnStatus) -> bool {
+        self.status_type == status.status_type && self.group_id == status.group_id
+    }
+
+    /// A finished spawn either saved its wallet or refunded the caller, every other spawn
+    /// stopped halfway and is resumed with the same blockheight
+    pub fn is_finished(&self) -> bool {
+        self.done.is_some() || self.min_amount_error.is_some()
+    }
+
+    pub fn get_transferred_to_cmc(&self) -> Option<u64> {
+        self.transferred_to_cmc
+    }
+
+    pub fn get_topped_up_self(&self) -> Option<Nat> {
+        self.topped_up_self.clone()
+    }
+
+    pub fn get_canister_spawned(&self) -> Option<Principal> {
+        self.canister_spawned
+    }
+
+    pub fn get_canister_installed(&self) -> Option<Principal> {
+        self.canister_installed
+    }
+
     pub fn transaction_valid(&mut self, amount: Tokens) -> Self {
         self.transaction_valid = Some(amount);
         self.clone()
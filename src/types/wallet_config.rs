@@ -0,0 +1,21 @@
+use candid::{CandidType, Nat, Principal};
+use serde::{Deserialize, Serialize};
+
+/// Settings a wallet is installed with, passed to `spawn_wallet` and forwarded to the wallet
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct WalletConfig {
+    /// The number of approvals a proposal needs, between 1 and the length of the whitelist
+    pub threshold: u64,
+    pub name: String,
+    pub description: Option<String>,
+    pub spending_limits: Option<Vec<SpendingLimit>>,
+}
+
+/// The maximum amount of a token the wallet can spend in a single proposal
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct SpendingLimit {
+    /// Ledger canister of the token
+    pub token: Principal,
+    /// Amount in the smallest unit of the token
+    pub amount: Nat,
+}
@@ -1,8 +1,13 @@
 use candid::{CandidType, Principal};
-use ic_cdk::{api::time, caller};
 use serde::{Deserialize, Serialize};
 
-use crate::impl_storable_for;
+use crate::{
+    impl_storable_for,
+    types::{
+        canister_snapshot::CanisterSnapshot, wallet_config::WalletConfig,
+        wallet_health::WalletHealth, wallet_snapshot::WalletSnapshot,
+    },
+};
 
 impl_storable_for!(WalletData);
 
@@ -15,19 +20,133 @@ pub struct WalletData {
     icp_blockheight: u64,
     cmc_blockheight: u64,
     group_id: u64,
+    /// Hex encoded sha256 of the installed wallet wasm, `None` for wallets spawned before it was tracked
+    wasm_version: Option<String>,
+    /// Distinguishes the wallets of a group under the `MultiWallet` policy
+    label: Option<String>,
+    /// Whitelist the wallet was installed with, `None` for wallets spawned before it was tracked
+    whitelist: Option<Vec<Principal>>,
+    /// Whitelist, threshold and balances read from the wallet, `None` until the first sync
+    snapshot: Option<WalletSnapshot>,
+    /// Settings the wallet was installed with, `None` if it was spawned without them
+    config: Option<WalletConfig>,
+    /// Controllers of the wallet canister, `None` for wallets spawned before they were
+    /// tracked, which are controlled by the index only
+    controllers: Option<Vec<Principal>>,
+    /// How the wallet came into the index, `None` for wallets spawned before it was tracked
+    origin: Option<WalletOrigin>,
+    /// The last canister snapshot the index took of the wallet, replaced by the next one
+    canister_snapshot: Option<CanisterSnapshot>,
+    /// Canister status of the wallet, `None` until the first health check
+    health: Option<WalletHealth>,
+}
+
+#[derive(CandidType, Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WalletOrigin {
+    /// Created and installed by the index
+    #[default]
+    Spawned,
+    /// Deployed elsewhere and registered with `import_wallet`
+    Imported,
 }
 
 impl WalletData {
-    pub fn new(icp_blockheight: u64, cmc_blockheight: u64, group_id: u64) -> Self {
+    pub fn new(
+        created_by: Principal,
+        created_at: u64,
+        icp_blockheight: u64,
+        cmc_blockheight: u64,
+        group_id: u64,
+        wasm_version: Option<String>,
+        label: Option<String>,
+    ) -> Self {
         Self {
-            created_by: caller(),
-            owner: caller(),
-            created_at: time(),
-            updated_at: time(),
+            created_by,
+            owner: created_by,
+            created_at,
+            updated_at: created_at,
             icp_blockheight,
             cmc_blockheight,
             group_id,
+            wasm_version,
+            label,
+            whitelist: None,
+            snapshot: None,
+            config: None,
+            controllers: None,
+            origin: None,
+            canister_snapshot: None,
+            health: None,
+        }
+    }
+
+    pub fn owner(&self) -> Principal {
+        self.owner
+    }
+
+    pub fn group_id(&self) -> u64 {
+        self.group_id
+    }
+
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    pub fn wasm_version(&self) -> Option<&String> {
+        self.wasm_version.as_ref()
+    }
+
+    pub fn label(&self) -> Option<&String> {
+        self.label.as_ref()
+    }
+
+    pub fn config(&self) -> Option<&WalletConfig> {
+        self.config.as_ref()
+    }
+
+    /// The controllers of the wallet, `index` is the principal of the index canister
+    pub fn controllers(&self, index: Principal) -> Vec<Principal> {
+        self.controllers.clone().unwrap_or_else(|| vec![index])
+    }
+
+    /// Whether the index is still a controller and can upgrade, stop or delete the wallet
+    pub fn is_managed_by(&self, index: Principal) -> bool {
+        self.controllers(index).contains(&index)
+    }
+
+    pub fn origin(&self) -> WalletOrigin {
+        self.origin.unwrap_or_default()
+    }
+
+    pub fn canister_snapshot(&self) -> Option<&CanisterSnapshot> {
+        self.canister_snapshot.as_ref()
+    }
+
+    pub fn health(&self) -> Option<&WalletHealth> {
+        self.health.as_ref()
+    }
+
+    pub fn snapshot(&self) -> Option<&WalletSnapshot> {
+        self.snapshot.as_ref()
+    }
+
+    /// The owner and the whitelist, the principals that are notified about the wallet. The
+    /// synced whitelist is used once there is one, it follows changes made on the wallet
+    pub fn members(&self) -> Vec<Principal> {
+        let mut members = vec![self.owner];
+
+        let whitelist = match &self.snapshot {
+            Some(snapshot) => Some(&snapshot.whitelist),
+            None => self.whitelist.as_ref(),
+        };
+
+        for principal in whitelist.into_iter().flatten() {
+            if !members.contains(principal) {
+                members.push(*principal);
+            }
         }
+
+        members
     }
 
     pub fn is_owner(&self, principal: Principal) -> bool {
@@ -38,4 +157,90 @@ impl WalletData {
         self.owner = owner;
         self.clone()
     }
+
+    pub fn set_whitelist(&mut self, whitelist: Vec<Principal>) -> Self {
+        self.whitelist = Some(whitelist);
+        self.clone()
+    }
+
+    pub fn set_config(&mut self, config: Option<WalletConfig>) -> Self {
+        self.config = config;
+        self.clone()
+    }
+
+    pub fn set_controllers(&mut self, controllers: Vec<Principal>) -> Self {
+        self.controllers = Some(controllers);
+        self.clone()
+    }
+
+    pub fn set_wasm_version(&mut self, wasm_version: String) -> Self {
+        self.wasm_version = Some(wasm_version);
+        self.clone()
+    }
+
+    pub fn set_canister_snapshot(&mut self, canister_snapshot: Option<CanisterSnapshot>) -> Self {
+        self.canister_snapshot = canister_snapshot;
+        self.clone()
+    }
+
+    pub fn set_origin(&mut self, origin: WalletOrigin) -> Self {
+        self.origin = Some(origin);
+        self.clone()
+    }
+
+    pub fn set_health(&mut self, health: WalletHealth) -> Self {
+        self.health = Some(health);
+        self.clone()
+    }
+
+    pub fn set_snapshot(&mut self, snapshot: WalletSnapshot) -> Self {
+        self.snapshot = Some(snapshot);
+        self.clone()
+    }
+}
+
+#[derive(CandidType, Deserialize, Clone, Debug, Default)]
+pub struct WalletFilter {
+    pub owner: Option<Principal>,
+    pub created_by: Option<Principal>,
+    pub group_id: Option<u64>,
+    /// Inclusive lower bound of `created_at` in nanoseconds
+    pub created_after: Option<u64>,
+    /// Exclusive upper bound of `created_at` in nanoseconds
+    pub created_before: Option<u64>,
+    pub wasm_version: Option<String>,
+    pub origin: Option<WalletOrigin>,
+}
+
+impl WalletFilter {
+    pub fn is_empty(&self) -> bool {
+        self.owner.is_none()
+            && self.created_by.is_none()
+            && self.group_id.is_none()
+            && self.created_after.is_none()
+            && self.created_before.is_none()
+            && self.wasm_version.is_none()
+            && self.origin.is_none()
+    }
+
+    pub fn matches(&self, wallet: &WalletData) -> bool {
+        self.owner.is_none_or(|owner| wallet.owner == owner)
+            && self
+                .created_by
+                .is_none_or(|created_by| wallet.created_by == created_by)
+            && self
+                .group_id
+                .is_none_or(|group_id| wallet.group_id == group_id)
+            && self
+                .created_after
+                .is_none_or(|after| wallet.created_at >= after)
+            && self
+                .created_before
+                .is_none_or(|before| wallet.created_at < before)
+            && self
+                .wasm_version
+                .as_ref()
+                .is_none_or(|version| wallet.wasm_version.as_ref() == Some(version))
+            && self.origin.is_none_or(|origin| wallet.origin() == origin)
+    }
 }
@@ -0,0 +1,125 @@
+use std::convert::TryFrom;
+
+use candid::{CandidType, Nat};
+use ic_cdk::api::management_canister::main::{CanisterStatusResponse, CanisterStatusType};
+use serde::{Deserialize, Serialize};
+
+use crate::storage::{multisig_wasm_storage::MultisigWasmStorage, state::LOW_CYCLES_DAYS};
+
+const SECONDS_PER_DAY: u128 = 24 * 60 * 60;
+
+/// Canister status of a wallet as read by the index, which controls the wallet
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct WalletHealth {
+    pub status: CanisterStatusType,
+    pub cycles: u64,
+    pub memory_size: u64,
+    /// Hex encoded sha256 of the installed module, `None` if the wallet has no module
+    pub module_hash: Option<String>,
+    pub idle_cycles_burned_per_day: u64,
+    /// Estimated days until the cycles reach the freezing threshold, `None` if the wallet
+    /// burns no cycles while idle
+    pub days_until_freezing: Option<u64>,
+    pub checked_at: u64,
+}
+
+impl WalletHealth {
+    pub fn from_status(status: CanisterStatusResponse, checked_at: u64) -> Self {
+        let cycles = to_u128(&status.cycles);
+        let burned_per_day = to_u128(&status.idle_cycles_burned_per_day);
+
+        // the wallet freezes when its cycles can only pay for the freezing threshold
+        let days_until_freezing = (burned_per_day > 0).then(|| {
+            let frozen_below =
+                burned_per_day * to_u128(&status.settings.freezing_threshold) / SECONDS_PER_DAY;
+            to_u64(cycles.saturating_sub(frozen_below) / burned_per_day)
+        });
+
+        Self {
+            status: status.status,
+            cycles: to_u64(cycles),
+            memory_size: to_u64(to_u128(&status.memory_size)),
+            module_hash: status
+                .module_hash
+                .map(|hash| MultisigWasmStorage::version_of(&hash)),
+            idle_cycles_burned_per_day: to_u64(burned_per_day),
+            days_until_freezing,
+            checked_at,
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.status != CanisterStatusType::Running
+    }
+
+    pub fn is_low_on_cycles(&self) -> bool {
+        self.days_until_freezing
+            .is_some_and(|days| days < LOW_CYCLES_DAYS)
+    }
+
+    /// Whether the wallet runs another module than `version`, the uploaded wasm
+    pub fn is_outdated(&self, version: &str) -> bool {
+        self.module_hash.as_deref() != Some(version)
+    }
+
+    pub fn summary(&self) -> HealthSummary {
+        HealthSummary {
+            module_hash: self.module_hash.clone(),
+            stopped: self.is_stopped(),
+            low_cycles: self.is_low_on_cycles(),
+        }
+    }
+}
+
+/// The part of a health check the fleet status counts wallets by
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct HealthSummary {
+    pub module_hash: Option<String>,
+    pub stopped: bool,
+    pub low_cycles: bool,
+}
+
+/// Wallet counts by their last health check, a wallet can be counted in multiple categories
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FleetStatus {
+    pub total: u64,
+    /// Running, not low on cycles and on the uploaded wasm
+    pub healthy: u64,
+    pub low_cycles: u64,
+    pub outdated: u64,
+    pub stopped: u64,
+    /// Wallets without a health check, they are not counted in the other categories
+    pub unchecked: u64,
+}
+
+impl FleetStatus {
+    /// Counts `wallets` with the same last health check, `None` for unchecked wallets,
+    /// outdated is compared with `version`, the uploaded wasm
+    pub fn add(&mut self, summary: Option<&HealthSummary>, wallets: u64, version: Option<&str>) {
+        self.total += wallets;
+
+        let summary = match summary {
+            Some(summary) => summary,
+            None => {
+                self.unchecked += wallets;
+                return;
+            }
+        };
+
+        let outdated =
+            version.is_some_and(|version| summary.module_hash.as_deref() != Some(version));
+
+        self.stopped += summary.stopped as u64 * wallets;
+        self.low_cycles += summary.low_cycles as u64 * wallets;
+        self.outdated += outdated as u64 * wallets;
+        self.healthy += (!summary.stopped && !summary.low_cycles && !outdated) as u64 * wallets;
+    }
+}
+
+fn to_u128(value: &Nat) -> u128 {
+    u128::try_from(value.0.clone()).unwrap_or(u128::MAX)
+}
+
+fn to_u64(value: u128) -> u64 {
+    u64::try_from(value).unwrap_or(u64::MAX)
+}
@@ -0,0 +1,25 @@
+use candid::{CandidType, Principal};
+use ic_ledger_types::Tokens;
+use serde::{Deserialize, Serialize};
+
+/// State of a wallet as last read from the wallet and the ledger by the sync
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct WalletSnapshot {
+    pub whitelist: Vec<Principal>,
+    pub threshold: u64,
+    /// Balance of the default account of the wallet on the ICP ledger
+    pub icp_balance: Tokens,
+    pub cycles: u64,
+    pub synced_at: u64,
+}
+
+/// Result of syncing a page of wallets
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, Default)]
+pub struct WalletSyncReport {
+    /// The number of wallets whose snapshot was updated
+    pub synced: u64,
+    /// Wallets that could not be read, with the error, they keep their previous snapshot
+    pub unreachable: Vec<(Principal, String)>,
+    /// Pass as `cursor` to sync the next page, `None` if this was the last page
+    pub next_cursor: Option<Principal>,
+}
@@ -0,0 +1,16 @@
+use candid::{CandidType, Principal};
+use serde::{Deserialize, Serialize};
+
+use crate::impl_storable_for;
+
+impl_storable_for!(WasmVersion);
+
+/// A wallet wasm the index knows by the hex encoded sha256 of the module
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct WasmVersion {
+    pub registered_at: u64,
+    pub registered_by: Principal,
+    /// Whether the wasm was uploaded to the index, versions that are only registered can be
+    /// imported but not installed
+    pub uploaded: bool,
+}